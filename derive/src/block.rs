@@ -37,6 +37,17 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream> {
         SpecializedTypeGenerics::new(parse_quote!(::posh::Gl), ident, &input.generics)?;
 
     let fields = StructFields::new(&input.ident, &input.data)?;
+
+    // Vertex formats are applied by the `vertex_formats` attribute, which
+    // removes the field attributes before this derive sees them.
+    for attrs in fields.attrs() {
+        if let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("vertex")) {
+            return Err(Error::new_spanned(
+                attr,
+                "posh derive(Block) needs `#[posh::vertex_formats]` before it for `#[vertex(format = ...)]` fields",
+            ));
+        }
+    }
     let field_idents = fields.idents();
     let field_strings = fields.strings();
 
@@ -173,8 +184,10 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream> {
                         #field_idents
                     );
 
+                    // Use the `Gl` view of the field, since it determines
+                    // the attribute's format in the vertex buffer.
                     let attrs = <
-                        #field_types_sl as ::posh::Block<::posh::Sl>
+                        #field_types_gl as ::posh::Block<::posh::Gl>
                     >::vertex_attribute_defs(
                         &::posh::internal::join_ident_path(path, #field_strings),
                     );
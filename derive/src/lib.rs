@@ -5,13 +5,17 @@ mod interpolant;
 mod uniform_interface;
 mod utils;
 mod value;
+mod vertex_formats;
 mod vs_interface;
 
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, ItemStruct};
 
 /// Derives `Block` for a struct that is generic in `BlockDom`.
-#[proc_macro_derive(Block)]
+///
+/// Fields with a `#[vertex(format = F)]` attribute need the
+/// [`vertex_formats`](macro@vertex_formats) attribute on the struct.
+#[proc_macro_derive(Block, attributes(vertex))]
 pub fn derive_block(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     match block::derive(input) {
@@ -21,6 +25,41 @@ pub fn derive_block(input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Stores the fields of a `Block` struct that have a `#[vertex(format = F)]`
+/// attribute in the vertex format `F`.
+///
+/// Such fields are declared with their logical type, e.g. `D::Vec4`, and are
+/// rewritten to `D::Packed<F>`. The logical view of `F` must match the declared
+/// type. This attribute must come before `#[derive(Block)]`:
+///
+/// ```ignore
+/// #[posh::vertex_formats]
+/// #[derive(Clone, Copy, Block)]
+/// #[repr(C)]
+/// struct Vertex<D: BlockDom> {
+///     pos: D::Vec3,
+///     #[vertex(format = gl::Normalized<[u8; 4]>)]
+///     color: D::Vec4,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn vertex_formats(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let attr = proc_macro2::TokenStream::from(attr);
+
+    if !attr.is_empty() {
+        return syn::Error::new_spanned(attr, "posh vertex_formats attribute takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let input = parse_macro_input!(input as ItemStruct);
+    match vertex_formats::expand(input) {
+        Ok(ts) => ts,
+        Err(e) => e.to_compile_error(),
+    }
+    .into()
+}
+
 /// Derives `Const` for a struct.
 #[proc_macro_derive(Const)]
 pub fn derive_consts(input: TokenStream) -> TokenStream {
@@ -56,7 +95,12 @@ pub fn derive_varying(input: TokenStream) -> TokenStream {
 
 /// Derives `UniformInterface` for a struct that is generic in
 /// `UniformInterfaceDom`.
-#[proc_macro_derive(UniformInterface)]
+///
+/// Block fields can be bound to a specific uniform buffer binding with
+/// `#[uniform(binding = N)]`, and sampler fields to a specific texture unit
+/// with `#[uniform(unit = N)]`. Fields without an attribute are assigned the
+/// lowest free binding or texture unit.
+#[proc_macro_derive(UniformInterface, attributes(uniform))]
 pub fn derive_uniform_interface(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     match uniform_interface::derive(input) {
@@ -78,6 +122,10 @@ pub fn derive_value(input: TokenStream) -> TokenStream {
 }
 
 /// Derives `VsInterface` for a struct that is generic in `VsInterfaceDom`.
+///
+/// The first attribute location of a block field can be specified with
+/// `#[vertex(location = N)]`. Fields without an attribute are assigned the
+/// lowest free range of locations.
 #[proc_macro_derive(VsInterface, attributes(vertex))]
 pub fn derive_vs_interface(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
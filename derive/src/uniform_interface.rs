@@ -3,7 +3,8 @@ use quote::quote;
 use syn::{parse_quote, DeriveInput, Result};
 
 use crate::utils::{
-    get_domain_param, remove_domain_param, validate_generics, SpecializedTypeGenerics, StructFields,
+    get_domain_param, parse_field_attr, remove_domain_param, validate_generics,
+    SpecializedTypeGenerics, StructFields,
 };
 
 pub fn derive(input: DeriveInput) -> Result<TokenStream> {
@@ -27,6 +28,34 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream> {
     let field_types = fields.types();
     let field_strings = fields.strings();
 
+    let field_visits = field_idents
+        .iter()
+        .zip(&field_types)
+        .zip(&field_strings)
+        .zip(fields.attrs())
+        .map(|(((field_ident, field_ty), field_string), attrs)| {
+            let path = quote! {
+                &::posh::internal::join_ident_path_if(visitor.needs_paths(), path, #field_string)
+            };
+
+            let visit = match parse_field_attr(attrs, "uniform", &["binding", "unit"])? {
+                Some((key, binding)) if key == "binding" => quote! {
+                    <#field_ty as ::posh::internal::BlockField<#generics_view_type>>::
+                        visit_with_binding(&self.#field_ident, #path, #binding, visitor);
+                },
+                Some((_, unit)) => quote! {
+                    <#field_ty as ::posh::internal::SamplerField<#generics_view_type>>::
+                        visit_with_unit(&self.#field_ident, #path, #unit, visitor);
+                },
+                None => quote! {
+                    self.#field_ident.visit(#path, visitor);
+                },
+            };
+
+            Ok(visit)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     Ok(quote! {
         // Implement `UniformInterface<D>` for the struct.
         unsafe impl #impl_generics ::posh::UniformInterface<#generics_view_type>
@@ -41,12 +70,7 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream> {
                 path: &str,
                 visitor: &mut impl ::posh::internal::UniformVisitor<'a, #generics_view_type>,
             ) {
-                #(
-                    self.#field_idents.visit(
-                        &::posh::internal::join_ident_path(path, #field_strings),
-                        visitor,
-                    );
-                )*
+                #(#field_visits)*
             }
 
             fn shader_input(path: &str) -> Self {
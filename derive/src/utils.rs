@@ -4,8 +4,8 @@ use syn::{
     parse_quote,
     spanned::Spanned,
     visit_mut::{visit_type_mut, VisitMut},
-    Attribute, Data, Error, Field, Fields, GenericParam, Generics, Ident, Path, QSelf, Result,
-    Token, Type, TypePath,
+    Attribute, Data, Error, Field, Fields, GenericParam, Generics, Ident, LitInt, Path, QSelf,
+    Result, Token, Type, TypePath,
 };
 
 #[derive(Clone)]
@@ -61,6 +61,43 @@ impl StructFields {
     }
 }
 
+/// Parses a field attribute of the form `#[name(key = N)]`, where `key` is one
+/// of `keys`.
+///
+/// Returns the key that was given together with its value, if any.
+pub fn parse_field_attr(
+    attrs: &[Attribute],
+    name: &str,
+    keys: &[&str],
+) -> Result<Option<(String, usize)>> {
+    let mut result: Option<(String, usize)> = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident(name)) {
+        attr.parse_nested_meta(|meta| {
+            let key = keys
+                .iter()
+                .find(|key| meta.path.is_ident(key))
+                .ok_or_else(|| {
+                    meta.error(format!(
+                        "unsupported `{name}` attribute, expected one of: {}",
+                        keys.join(", ")
+                    ))
+                })?;
+
+            if result.is_some() {
+                return Err(meta.error(format!("duplicate `{name}` attribute")));
+            }
+
+            let value = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            result = Some((key.to_string(), value));
+
+            Ok(())
+        })?;
+    }
+
+    Ok(result)
+}
+
 pub struct SpecializedTypeGenerics {
     domain: Type,
     params: Vec<GenericParam>,
@@ -109,7 +146,7 @@ impl ToTokens for SpecializedTypeGenerics {
 pub fn associated_type_to_trait(ty: &str) -> Option<Path> {
     let block_tys = vec![
         "F32", "I32", "U32", "Vec2", "Vec3", "Vec4", "IVec2", "IVec3", "IVec4", "UVec2", "UVec3",
-        "UVec4", "Mat2", "Mat3", "Mat4",
+        "UVec4", "Mat2", "Mat3", "Mat4", "Packed",
     ];
 
     let vertex_tys = vec!["Block"];
@@ -165,7 +202,9 @@ pub fn specialize_field_types(
             }
 
             let Some(trait_path) = associated_type_to_trait(&i.path.segments[1].ident.to_string())
-            else { return; };
+            else {
+                return;
+            };
 
             i.qself = Some(QSelf {
                 lt_token: Token![<](first_segment.span()),
@@ -200,6 +239,22 @@ pub fn specialize_field_types(
     Ok(types)
 }
 
+/// Specializes a single type for the given domain, like
+/// [`specialize_field_types`].
+pub fn specialize_type(
+    domain: Path,
+    ident: &Ident,
+    generics: &Generics,
+    ty: &Type,
+) -> Result<Type> {
+    let field: Field = parse_quote!(field: #ty);
+    let fields = StructFields {
+        fields: vec![field],
+    };
+
+    Ok(specialize_field_types(domain, ident, generics, &fields)?.remove(0))
+}
+
 pub fn validate_generics(generics: &Generics) -> Result<()> {
     for param in generics.params.iter() {
         if let GenericParam::Lifetime(param) = param {
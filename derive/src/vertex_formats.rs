@@ -0,0 +1,84 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Error, Fields, ItemStruct, Result, Type};
+
+use crate::utils::{get_domain_param, specialize_type};
+
+/// Parses a field attribute of the form `#[vertex(format = F)]`, removing it
+/// from `attrs`.
+fn take_format(attrs: &mut Vec<syn::Attribute>) -> Result<Option<Type>> {
+    let mut format = None;
+    let mut result = Ok(());
+
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("vertex") {
+            return true;
+        }
+
+        let parsed = attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("format") {
+                return Err(meta.error("unsupported `vertex` attribute, expected: format"));
+            }
+
+            if format.is_some() {
+                return Err(meta.error("duplicate `vertex` attribute"));
+            }
+
+            format = Some(meta.value()?.parse::<Type>()?);
+
+            Ok(())
+        });
+
+        if let Err(error) = parsed {
+            result = Err(error);
+        }
+
+        false
+    });
+
+    result.map(|()| format)
+}
+
+pub fn expand(mut input: ItemStruct) -> Result<TokenStream> {
+    let ident = input.ident.clone();
+    let domain = get_domain_param(&ident, &input.generics)?;
+
+    let Fields::Named(fields) = &mut input.fields else {
+        return Err(Error::new_spanned(
+            &ident,
+            "posh vertex_formats attribute only supports structs with named fields",
+        ));
+    };
+
+    let mut checks = Vec::new();
+
+    for field in &mut fields.named {
+        let Some(format) = take_format(&mut field.attrs)? else {
+            continue;
+        };
+
+        // The logical view of the format must match the declared type, so that
+        // shaders see the field as declared.
+        let declared_sl =
+            specialize_type(parse_quote!(::posh::Sl), &ident, &input.generics, &field.ty)?;
+
+        checks.push(quote! {
+            {
+                #[allow(dead_code)]
+                fn check(value: <#format as ::posh::Block<::posh::Gl>>::Sl) -> #declared_sl {
+                    value
+                }
+            }
+        });
+
+        field.ty = parse_quote!(#domain::Packed<#format>);
+    }
+
+    Ok(quote! {
+        #input
+
+        const _: () = {
+            #(#checks)*
+        };
+    })
+}
@@ -4,8 +4,8 @@ use syn::{parse_quote, DeriveInput, Result};
 
 use crate::{
     utils::{
-        get_domain_param, remove_domain_param, specialize_field_types, validate_generics,
-        SpecializedTypeGenerics, StructFields,
+        get_domain_param, parse_field_attr, remove_domain_param, specialize_field_types,
+        validate_generics, SpecializedTypeGenerics, StructFields,
     },
     value,
 };
@@ -31,6 +31,19 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream> {
     let field_types = fields.types();
     let field_strings = fields.strings();
 
+    let field_locations = fields
+        .attrs()
+        .into_iter()
+        .map(|attrs| {
+            let location = parse_field_attr(attrs, "vertex", &["location"])?;
+
+            Ok(match location {
+                Some((_, location)) => quote! { ::std::option::Option::Some(#location) },
+                None => quote! { ::std::option::Option::None },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     let field_types_sl =
         specialize_field_types(parse_quote!(::posh::Sl), ident, &input.generics, &fields)?;
 
@@ -58,7 +71,12 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream> {
             ) {
                 #(
                     visitor.accept(
-                        &::posh::internal::join_ident_path(path, #field_strings),
+                        &::posh::internal::join_ident_path_if(
+                            visitor.needs_paths(),
+                            path,
+                            #field_strings,
+                        ),
+                        #field_locations,
                         &self.#field_idents,
                     );
                 )*
@@ -196,7 +196,7 @@ impl Demo {
         // <https://github.com/leod/posh/issues/131>.
 
         let scene_texture =
-            || gl.create_color_texture_2d(gl::ColorImage::rgb_u8_zero([WIDTH, HEIGHT]));
+            || gl.create_color_texture_2d(gl::ColorImage::zeroed::<[u8; 3]>([WIDTH, HEIGHT]));
 
         let scene_attachments = SceneAttachments {
             albedo: scene_texture()?.as_color_attachment(),
@@ -218,7 +218,7 @@ impl Demo {
 
     pub fn draw(&mut self) -> Result<(), gl::DrawError> {
         let time = Instant::now().duration_since(self.start_time).as_secs_f32();
-        self.globals.set(Globals::new(time));
+        self.globals.set(Globals::new(time))?;
 
         self.scene_program
             .with_uniforms(self.globals.as_binding())
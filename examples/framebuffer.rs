@@ -114,7 +114,7 @@ impl Demo {
     pub fn new(gl: gl::Context) -> Result<Self, gl::CreateError> {
         use gl::BufferUsage::*;
 
-        let image = gl::ColorImage::rgba_u8_zero([1024, 768]);
+        let image = gl::ColorImage::zeroed::<[u8; 4]>([1024, 768]);
 
         Ok(Self {
             scene_program: gl
@@ -134,7 +134,7 @@ impl Demo {
         let time = Instant::now().duration_since(self.start_time).as_secs_f32();
         let flip = ((time / 5.0) as usize % 2) as u32;
 
-        self.state.set(State { time, flip });
+        self.state.set(State { time, flip })?;
 
         self.scene_program
             .with_uniforms(self.state.as_binding())
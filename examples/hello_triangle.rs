@@ -63,7 +63,7 @@ impl Demo {
         self.globals.set(Globals {
             time: Instant::now().duration_since(self.start_time).as_secs_f32(),
             triangle_size: [1.0, 1.0].into(),
-        });
+        })?;
 
         self.program
             .with_uniforms(self.globals.as_binding())
@@ -92,7 +92,7 @@ impl Demo {
         self.globals.set(Globals {
             time: Instant::now().duration_since(self.start_time).as_secs_f32(),
             resolution: [SCREEN_WIDTH, SCREEN_HEIGHT].into(),
-        });
+        })?;
 
         self.program
             .with_uniforms(self.globals.as_binding())
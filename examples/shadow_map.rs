@@ -217,8 +217,8 @@ impl Demo {
         let light_x = (dt * 0.5).sin() * 20.0;
         let light_y = 20.0 + dt.sin() * 10.0;
 
-        self.light_buffer.set(Light::new(light_x, light_y));
-        self.light_vertices.set(&light_vertices(light_x, light_y));
+        self.light_buffer.set(Light::new(light_x, light_y))?;
+        self.light_vertices.set(&light_vertices(light_x, light_y))?;
 
         let scene_vertex_spec = self
             .scene_vertices
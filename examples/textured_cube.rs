@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use image::{io::Reader as ImageReader, EncodableLayout};
+use image::io::Reader as ImageReader;
 
 use posh::{gl, sl, Block, BlockDom, Gl, Sl, UniformInterface, UniformInterfaceDom};
 
@@ -75,10 +75,9 @@ impl Demo {
             .decode()
             .unwrap()
             .to_rgba8();
-        let image = gl::ColorImage::rgba_u8_slice(
-            [image.dimensions().0, image.dimensions().1],
-            image.as_bytes(),
-        );
+        let pixels: &[[u8; 4]] = bytemuck::cast_slice(image.as_raw());
+        let image =
+            gl::ColorImage::from_slice([image.dimensions().0, image.dimensions().1], pixels);
 
         Ok(Self {
             program: gl.create_program(vertex_shader, sl::ColorSampler2d::sample)?,
@@ -93,7 +92,7 @@ impl Demo {
 
     pub fn draw(&self) -> Result<(), gl::DrawError> {
         let time = Instant::now().duration_since(self.start_time).as_secs_f32();
-        self.time.set(time);
+        self.time.set(time)?;
 
         self.program
             .with_uniforms((
@@ -1,16 +1,29 @@
 //! The graphics library.
 
+mod buffer_map;
+mod command_list;
 mod context;
 mod element_buffer;
+mod fence;
 mod framebuffer;
+pub mod graph;
 mod image;
+#[cfg(feature = "image")]
+mod image_interop;
 mod mat;
+mod pixel;
+mod pixel_unpack_buffer;
 mod program;
+mod query;
 mod raw;
+mod readback;
+mod streaming_buffer;
 mod texture;
+mod texture_pool;
 mod uniform_buffer;
 mod vec;
 mod vertex_buffer;
+mod vertex_format;
 mod vertex_spec;
 
 use bytemuck::{Pod, Zeroable};
@@ -19,25 +32,39 @@ use crevice::std140::AsStd140;
 use crate::{sl, ToSl};
 
 pub use self::image::{ColorImage, DepthImage};
+pub use buffer_map::{BufferMap, BufferMapMut};
+pub use command_list::CommandList;
 pub use context::{CacheDrawBuilder, Context};
 pub use element_buffer::{Element, ElementBuffer, ElementBufferBinding};
+pub use fence::Fence;
 pub use framebuffer::{ColorAttachment, DepthAttachment, Framebuffer};
 pub use mat::{Mat2, Mat3, Mat4};
+pub use pixel::{Gamma, Integer, Pixel, Rgb10A2, Rgb9E5, F16, R11G11B10F};
+pub use pixel_unpack_buffer::PixelUnpackBuffer;
 pub use program::{
     DrawBuilder, DrawBuilderWithFramebuffer, DrawBuilderWithUniforms,
     DrawBuilderWithUniformsAndFramebuffer, Program,
 };
+pub use query::Query;
 pub use raw::{
-    BlendEquation, BlendFunc, Blending, BufferError, BufferUsage, Caps, Comparison, ContextError,
-    CreateError, CullFace, DrawError, DrawSettings, ElementType, FramebufferError, ImageFormat,
-    ImageInternalFormat, PrimitiveMode, ProgramError, ProgramValidationError, Rect,
-    Sampler2dSettings, SamplerMagFilter, SamplerMinFilter, SamplerWrap, StencilOp, StencilOps,
-    StencilTest, TextureError, VertexArrayError,
+    BlendEquation, BlendFunc, Blending, BufferError, BufferMapSettings, BufferUsage, Caps,
+    Comparison, ContextError, CreateError, CullFace, DrawError, DrawSettings, ElementType,
+    FenceError, FramebufferError, GlVersion, GraphError, ImageComponentType, ImageFormat,
+    ImageInternalFormat, PrimitiveMode, ProgramError, ProgramValidationError, QueryError,
+    QueryResult, QueryType, ReadbackError, ReadbackImage, Rect, Sampler2dSettings,
+    SamplerMagFilter, SamplerMinFilter, SamplerWrap, StencilOp, StencilOps, StencilTest,
+    TextureError, VertexArrayError,
 };
+pub use readback::PixelReadback;
+pub use streaming_buffer::{StreamingElementBuffer, StreamingVertexBuffer};
 pub use texture::{ColorSampler2d, ColorTexture2d, ComparisonSampler2d, DepthTexture2d};
-pub use uniform_buffer::{UniformBuffer, UniformBufferBinding};
+pub use texture_pool::{LeasedColorTexture2d, LeasedDepthTexture2d, TexturePool};
+pub use uniform_buffer::{GlobalUniformBlock, UniformBuffer, UniformBufferBinding};
 pub use vec::{BVec2, BVec3, BVec4, IVec2, IVec3, IVec4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
 pub use vertex_buffer::{VertexBuffer, VertexBufferBinding};
+pub use vertex_format::{
+    F16Vec2, F16Vec3, F16Vec4, Int2_10_10_10Rev, Normalized, UInt2_10_10_10Rev, VertexFormat,
+};
 pub use vertex_spec::VertexSpec;
 
 #[derive(Clone, Copy, Zeroable, Pod, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
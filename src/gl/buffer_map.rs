@@ -0,0 +1,114 @@
+use std::{
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    ops::{Deref, DerefMut, Range},
+    ptr::NonNull,
+};
+
+use super::{raw, BufferError};
+
+/// A scoped mapping of a buffer range into CPU memory for reading.
+///
+/// The buffer is unmapped when the mapping is dropped. While a buffer is
+/// mapped, it can not be used as the source of GPU operations.
+pub struct BufferMap<'a, T> {
+    raw: raw::BufferMap<'a>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, T: bytemuck::Pod> BufferMap<'a, T> {
+    pub(super) fn from_raw(raw: raw::BufferMap<'a>) -> Result<Self, BufferError> {
+        check_alignment::<T>(&raw)?;
+
+        Ok(Self {
+            raw,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<'a, T: bytemuck::Pod> Deref for BufferMap<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        cast_slice(&self.raw)
+    }
+}
+
+/// A scoped mapping of a buffer range into CPU memory for writing.
+///
+/// The buffer is unmapped when the mapping is dropped. While a buffer is
+/// mapped, it can not be used as the source of GPU operations.
+pub struct BufferMapMut<'a, T> {
+    raw: raw::BufferMap<'a>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, T: bytemuck::Pod> BufferMapMut<'a, T> {
+    pub(super) fn from_raw(raw: raw::BufferMap<'a>) -> Result<Self, BufferError> {
+        check_alignment::<T>(&raw)?;
+
+        Ok(Self {
+            raw,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Makes modifications to the items in `range` visible to the GPU.
+    ///
+    /// This requires the mapping to have been created with
+    /// [`BufferMapSettings::explicit_flush`](crate::gl::BufferMapSettings::explicit_flush).
+    /// Otherwise, modifications are flushed when the mapping is dropped.
+    pub fn flush(&self, range: Range<usize>) -> Result<(), BufferError> {
+        self.raw
+            .flush(range.start * size_of::<T>(), range.len() * size_of::<T>())
+    }
+}
+
+impl<'a, T: bytemuck::Pod> Deref for BufferMapMut<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        cast_slice(&self.raw)
+    }
+}
+
+impl<'a, T: bytemuck::Pod> DerefMut for BufferMapMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        if self.raw.is_empty() {
+            // Safety: a dangling pointer is valid for empty slices.
+            return unsafe { std::slice::from_raw_parts_mut(NonNull::<T>::dangling().as_ptr(), 0) };
+        }
+
+        bytemuck::cast_slice_mut(&mut self.raw)
+    }
+}
+
+/// Checks that the mapped memory can be viewed as a slice of `T`, so that
+/// casting it does not panic.
+fn check_alignment<T>(raw: &raw::BufferMap) -> Result<(), BufferError> {
+    // Empty mappings do not point to mapped memory.
+    if raw.is_empty() {
+        return Ok(());
+    }
+
+    let alignment = align_of::<T>();
+
+    if !raw.offset().is_multiple_of(alignment) || raw.as_ptr().align_offset(alignment) != 0 {
+        return Err(BufferError::MisalignedMapping {
+            offset: raw.offset(),
+            alignment,
+        });
+    }
+
+    Ok(())
+}
+
+fn cast_slice<T: bytemuck::Pod>(bytes: &[u8]) -> &[T] {
+    if bytes.is_empty() {
+        // Safety: a dangling pointer is valid for empty slices.
+        return unsafe { std::slice::from_raw_parts(NonNull::<T>::dangling().as_ptr(), 0) };
+    }
+
+    bytemuck::cast_slice(bytes)
+}
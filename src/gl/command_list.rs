@@ -0,0 +1,359 @@
+use std::{hash::Hash, ops::Range, rc::Rc};
+
+use fxhash::FxHashMap;
+
+use super::{raw, vertex_spec::with_vertex_path, DrawError, DrawSettings};
+
+/// A draw call that has been recorded into a [`CommandList`].
+pub(super) struct Command {
+    pub program: Rc<raw::Program>,
+    pub uniform_buffers: Vec<Option<(Rc<raw::Buffer>, Range<usize>)>>,
+    pub samplers: Vec<raw::Sampler>,
+    pub vertex_spec: raw::VertexSpec,
+
+    /// The paths of the vertex buffer bindings, which name them in errors.
+    pub vertex_paths: Vec<String>,
+    pub framebuffer: raw::Framebuffer,
+    pub settings: DrawSettings,
+    pub query: Option<Rc<raw::Query>>,
+}
+
+/// A list of recorded draw calls that are executed later.
+///
+/// Draw calls are recorded with the `record` methods of the draw builders,
+/// e.g. [`DrawBuilderWithUniforms::record`](super::DrawBuilderWithUniforms::record),
+/// which capture everything that `draw` would use. Recording does not make any
+/// GL calls, so lists can be prepared ahead of time and submitted repeatedly.
+///
+/// By default, [`submit`](CommandList::submit) sorts consecutive draw calls
+/// that render into the same framebuffer by program, then by textures, and
+/// then by draw settings, so that they share as many bindings as possible.
+/// Groups are ordered by their first occurrence within the run of draw calls,
+/// and draw calls with equal keys keep their recording order. Draw calls are
+/// never moved across a draw call into another framebuffer, so passes that
+/// sample the results of earlier passes stay behind them. Draw calls are also
+/// never moved across a draw call that clears, so clearing stays in front of
+/// the draw calls that follow it.
+///
+/// Sorting can reorder draw calls that render into the same framebuffer. Passes
+/// whose result depends on the order of draw calls, such as blending, should
+/// use a list with [`with_sorting(false)`](CommandList::with_sorting).
+pub struct CommandList {
+    commands: Vec<Command>,
+    sorting: bool,
+
+    /// The submission order, which is computed on the first submission after
+    /// the list has been changed.
+    order: Option<Vec<usize>>,
+}
+
+impl Default for CommandList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandList {
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            sorting: true,
+            order: None,
+        }
+    }
+
+    /// Sets whether draw calls are sorted on submission.
+    #[must_use]
+    pub fn with_sorting(mut self, sorting: bool) -> Self {
+        self.sorting = sorting;
+        self.order = None;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Removes all recorded draw calls.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.order = None;
+    }
+
+    pub(super) fn push(&mut self, command: Command) {
+        self.commands.push(command);
+        self.order = None;
+    }
+
+    /// Executes the recorded draw calls.
+    ///
+    /// The draw calls stay in the list, so it can be submitted again.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first draw call that fails. The remaining draw
+    /// calls are not executed.
+    pub fn submit(&mut self) -> Result<(), DrawError> {
+        let order = match &mut self.order {
+            Some(order) => order,
+            order @ None => order.insert(if self.sorting {
+                sorted_order(&self.commands)
+            } else {
+                (0..self.commands.len()).collect()
+            }),
+        };
+
+        for &index in order.iter() {
+            let command = &self.commands[index];
+
+            if let Some(query) = &command.query {
+                query.begin().map_err(DrawError::Query)?;
+            }
+
+            let result = unsafe {
+                command.program.draw(
+                    &command.uniform_buffers,
+                    &command.samplers,
+                    &command.vertex_spec,
+                    &command.framebuffer,
+                    &command.settings,
+                )
+            };
+
+            // The query needs to be ended even if drawing failed, so that it
+            // does not stay active.
+            if let Some(query) = &command.query {
+                query.end().map_err(DrawError::Query)?;
+            }
+
+            result.map_err(|error| with_vertex_path(error, &command.vertex_paths))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn sorted_order(commands: &[Command]) -> Vec<usize> {
+    let keys: Vec<_> = commands
+        .iter()
+        .map(|command| SortKey {
+            framebuffer: framebuffer_key(command),
+            clears: command.settings.clears(),
+            program: Rc::as_ptr(&command.program),
+            textures: texture_key(command),
+            settings: command.settings,
+        })
+        .collect();
+
+    sort_within_runs(&keys)
+}
+
+/// The properties of a draw call that determine where it is sorted to.
+struct SortKey<F, P, T, S> {
+    framebuffer: F,
+    clears: bool,
+    program: P,
+    textures: T,
+    settings: S,
+}
+
+/// Sorts indices by program, textures and settings within each run of
+/// consecutive draw calls that can be reordered.
+///
+/// A run ends where the framebuffer changes. Since clearing is part of a draw
+/// call's settings, a draw call that clears also starts a new run, so that it
+/// is never moved behind draw calls whose results it would clear.
+fn sort_within_runs<F, P, T, S>(keys: &[SortKey<F, P, T, S>]) -> Vec<usize>
+where
+    F: PartialEq,
+    P: Hash + Eq,
+    T: Hash + Eq,
+    S: PartialEq,
+{
+    let mut order = Vec::with_capacity(keys.len());
+    let mut start = 0;
+
+    while start < keys.len() {
+        let end = (start + 1..keys.len())
+            .find(|&i| keys[i].framebuffer != keys[start].framebuffer || keys[i].clears)
+            .unwrap_or(keys.len());
+        let run = &keys[start..end];
+
+        // Groups are ranked by their first occurrence within the run.
+        let programs = first_occurrence_ranks(run.iter().map(|key| &key.program));
+        let textures = first_occurrence_ranks(run.iter().map(|key| &key.textures));
+
+        // Draw settings can not be hashed, but there are usually only few
+        // distinct ones.
+        let settings = {
+            let mut distinct: Vec<&S> = Vec::new();
+
+            run.iter()
+                .map(|key| {
+                    distinct
+                        .iter()
+                        .position(|settings| **settings == key.settings)
+                        .unwrap_or_else(|| {
+                            distinct.push(&key.settings);
+                            distinct.len() - 1
+                        })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut run_order: Vec<usize> = (0..run.len()).collect();
+
+        // `sort_by_key` is stable, so draw calls with equal keys keep their
+        // recording order.
+        run_order.sort_by_key(|&i| (programs[i], textures[i], settings[i]));
+
+        order.extend(run_order.into_iter().map(|i| start + i));
+        start = end;
+    }
+
+    order
+}
+
+/// Maps each key to the index of its first distinct occurrence.
+fn first_occurrence_ranks<K: Hash + Eq>(keys: impl Iterator<Item = K>) -> Vec<usize> {
+    let mut ranks = FxHashMap::default();
+
+    keys.map(|key| {
+        let next = ranks.len();
+
+        *ranks.entry(key).or_insert(next)
+    })
+    .collect()
+}
+
+fn framebuffer_key(command: &Command) -> Vec<(*const raw::Texture2d, u32)> {
+    match &command.framebuffer {
+        raw::Framebuffer::Default => Vec::new(),
+        raw::Framebuffer::Attachments { attachments } => attachments
+            .iter()
+            .map(|attachment| match attachment {
+                raw::Attachment::Texture2d { texture, level } => (Rc::as_ptr(texture), *level),
+            })
+            .collect(),
+    }
+}
+
+fn texture_key(command: &Command) -> Vec<*const raw::Texture2d> {
+    command
+        .samplers
+        .iter()
+        .map(|sampler| match sampler {
+            raw::Sampler::Sampler2d(sampler) => Rc::as_ptr(&sampler.texture),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_within_runs, SortKey};
+
+    fn key(framebuffer: u32, program: u32, textures: u32) -> SortKey<u32, u32, u32, u32> {
+        SortKey {
+            framebuffer,
+            clears: false,
+            program,
+            textures,
+            settings: 0,
+        }
+    }
+
+    fn clearing(key: SortKey<u32, u32, u32, u32>) -> SortKey<u32, u32, u32, u32> {
+        SortKey {
+            clears: true,
+            ..key
+        }
+    }
+
+    #[test]
+    fn draw_calls_are_sorted_within_a_framebuffer() {
+        let keys = [key(0, 1, 0), key(0, 2, 1), key(0, 1, 1), key(0, 2, 0)];
+
+        assert_eq!(sort_within_runs(&keys), [0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn draw_calls_are_sorted_by_settings_last() {
+        let keys = [
+            SortKey {
+                settings: 1,
+                ..key(0, 0, 0)
+            },
+            SortKey {
+                settings: 2,
+                ..key(0, 0, 0)
+            },
+            SortKey {
+                settings: 1,
+                ..key(0, 0, 0)
+            },
+        ];
+
+        assert_eq!(sort_within_runs(&keys), [0, 2, 1]);
+    }
+
+    #[test]
+    fn draw_calls_are_not_moved_across_framebuffers() {
+        // A pass into framebuffer 1 sits between two passes into the default
+        // framebuffer 0, e.g. because the second pass samples its result.
+        let keys = [
+            key(0, 1, 0),
+            key(0, 0, 0),
+            key(0, 1, 0),
+            key(1, 1, 0),
+            key(1, 0, 0),
+            key(1, 1, 0),
+            key(0, 1, 0),
+            key(0, 0, 0),
+        ];
+
+        assert_eq!(sort_within_runs(&keys), [0, 2, 1, 3, 5, 4, 6, 7]);
+    }
+
+    #[test]
+    fn equal_keys_keep_their_recording_order() {
+        let keys = [key(0, 0, 0), key(0, 0, 0), key(0, 0, 0)];
+
+        assert_eq!(sort_within_runs(&keys), [0, 1, 2]);
+    }
+
+    #[test]
+    fn clearing_draw_calls_stay_in_front_of_later_draw_calls() {
+        // Program 1 first occurs in the first run, so its global rank is lower
+        // than that of program 2. The clearing draw call with program 2 must
+        // still stay first in the second run.
+        let keys = [
+            key(0, 1, 0),
+            key(0, 2, 0),
+            clearing(key(1, 2, 0)),
+            key(1, 1, 0),
+            key(1, 2, 0),
+            key(1, 1, 0),
+        ];
+
+        assert_eq!(sort_within_runs(&keys), [0, 1, 2, 4, 3, 5]);
+    }
+
+    #[test]
+    fn clearing_draw_calls_are_sort_barriers() {
+        // Draw calls are not moved across a draw call that clears in the
+        // middle of a framebuffer run.
+        let keys = [
+            key(0, 1, 0),
+            key(0, 0, 0),
+            clearing(key(0, 1, 0)),
+            key(0, 0, 0),
+            key(0, 1, 0),
+        ];
+
+        assert_eq!(sort_within_runs(&keys), [0, 1, 2, 4, 3]);
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     cell::RefCell,
     collections::hash_map,
     marker::PhantomData,
+    mem::size_of,
     rc::Rc,
 };
 
@@ -10,17 +11,23 @@ use fxhash::FxHashMap;
 
 use crate::{
     sl::{
+        program_def::ProgramDef,
         transpile::{transpile_to_program_def, transpile_to_program_def_with_consts},
         ColorSample, FsFunc, FsSig, VsFunc, VsSig,
     },
-    Block, Gl, Sl, UniformInterface, UniformUnion,
+    Block, FsInterface, Gl, Sl, UniformInterface, UniformUnion,
 };
 
 use super::{
-    program::{DrawBuilder, DrawBuilderWithUniforms},
-    raw, BufferError, BufferUsage, Caps, ColorImage, ColorTexture2d, ContextError, DepthImage,
-    DepthTexture2d, Element, ElementBuffer, Program, ProgramError, TextureError, UniformBuffer,
-    VertexBuffer,
+    graph::RenderGraph,
+    program::{DrawBuilderWithUniforms, DrawScratch},
+    raw,
+    uniform_buffer::UniformRing,
+    BufferError, BufferUsage, Caps, ColorImage, ColorTexture2d, ContextError, DepthImage,
+    DepthTexture2d, Element, ElementBuffer, Fence, FenceError, Framebuffer, PixelReadback,
+    PixelUnpackBuffer, Program, ProgramError, Query, QueryError, QueryType, ReadbackError, Rect,
+    StreamingElementBuffer, StreamingVertexBuffer, TextureError, TexturePool, UniformBuffer,
+    UniformBufferBinding, VertexBuffer,
 };
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -28,15 +35,44 @@ struct ProgramCacheKey {
     vertex_shader: TypeId,
     fragment_shader: TypeId,
     uniform_union: TypeId,
+
+    /// The generation of the global uniform blocks at the time the program was
+    /// created, since their reserved bindings are fixed in the program.
+    global_uniform_blocks: u64,
 }
 
+/// Uniform blocks that are bound once for all programs, indexed by the type of
+/// their logical view.
 #[derive(Default)]
-pub(crate) struct ProgramCache(FxHashMap<ProgramCacheKey, Rc<raw::Program>>);
+pub(crate) struct GlobalUniformBlocks {
+    blocks: FxHashMap<TypeId, (usize, Rc<raw::Buffer>)>,
+
+    /// Incremented whenever a block type is registered.
+    generation: u64,
+}
+
+impl GlobalUniformBlocks {
+    /// Moves uniform blocks of a global type to their reserved binding.
+    fn apply(&self, program_def: &mut ProgramDef) {
+        for block_def in &mut program_def.uniform_block_defs {
+            if let Some((location, _)) = self.blocks.get(&block_def.type_id) {
+                block_def.location = *location;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ProgramCache(
+    FxHashMap<ProgramCacheKey, (Rc<raw::Program>, Rc<RefCell<DrawScratch>>)>,
+);
 
 impl ProgramCache {
     pub fn get<U, VSig, VFunc, FSig, FFunc>(
         &mut self,
         raw: &raw::Context,
+        global_uniform_blocks: &GlobalUniformBlocks,
+        uniform_ring: &Rc<RefCell<UniformRing>>,
         vertex_shader: VFunc,
         fragment_shader: FFunc,
     ) -> Result<Program<U, VSig::V, FSig::F>, ProgramError>
@@ -51,15 +87,17 @@ impl ProgramCache {
             vertex_shader: TypeId::of::<VFunc>(),
             fragment_shader: TypeId::of::<FFunc>(),
             uniform_union: TypeId::of::<U>(),
+            global_uniform_blocks: global_uniform_blocks.generation,
         };
 
-        let raw = match self.0.entry(key) {
+        let (raw, scratch) = match self.0.entry(key) {
             hash_map::Entry::Occupied(entry) => entry.get().clone(),
             hash_map::Entry::Vacant(entry) => {
-                let program_def = transpile_to_program_def::<U, VSig, VFunc, FSig, FFunc>(
+                let mut program_def = transpile_to_program_def::<U, VSig, VFunc, FSig, FFunc>(
                     vertex_shader,
                     fragment_shader,
                 );
+                global_uniform_blocks.apply(&mut program_def);
 
                 log::info!(
                     "Caching vertex shader for `{}`:\n{}",
@@ -74,11 +112,15 @@ impl ProgramCache {
 
                 let raw = raw.create_program(program_def)?;
 
-                entry.insert(Rc::new(raw)).clone()
+                entry.insert((Rc::new(raw), Default::default())).clone()
             }
         };
 
-        Ok(Program::unchecked_from_raw(raw))
+        Ok(Program::unchecked_from_parts(
+            raw,
+            scratch,
+            uniform_ring.clone(),
+        ))
     }
 }
 
@@ -110,17 +152,13 @@ where
             .borrow_mut()
             .get::<U::Sl, VSig, VFunc, FSig, FFunc>(
                 &self.gl.raw,
+                &self.gl.global_uniform_blocks.borrow(),
+                &self.gl.uniform_ring,
                 self.vertex_shader,
                 self.fragment_shader,
             )?;
 
-        let inner = DrawBuilder {
-            raw: program.raw().clone(),
-            settings: Default::default(),
-            _phantom: PhantomData,
-        };
-
-        Ok(DrawBuilderWithUniforms { inner, uniforms })
+        Ok(program.with_uniforms(uniforms))
     }
 
     // TODO: Also needs `with_framebuffer` and `with_settings`.
@@ -131,22 +169,39 @@ where
 pub struct Context {
     raw: Rc<raw::Context>,
     program_cache: Rc<RefCell<ProgramCache>>,
+    global_uniform_blocks: Rc<RefCell<GlobalUniformBlocks>>,
+    uniform_ring: Rc<RefCell<UniformRing>>,
+    texture_pool: TexturePool,
 }
 
 impl Context {
     pub fn new(gl: glow::Context) -> Result<Self, ContextError> {
-        let raw = raw::Context::new(gl)?;
+        let raw = Rc::new(raw::Context::new(gl)?);
 
         Ok(Self {
-            raw: Rc::new(raw),
+            raw: raw.clone(),
             program_cache: Default::default(),
+            global_uniform_blocks: Default::default(),
+            uniform_ring: Rc::new(RefCell::new(UniformRing::new(raw.clone()))),
+            texture_pool: TexturePool::new(raw),
         })
     }
 
+    pub(super) fn raw(&self) -> &raw::Context {
+        &self.raw
+    }
+
     pub fn caps(&self) -> &Caps {
         self.raw.caps()
     }
 
+    /// Returns whether the extension with the given name is supported.
+    ///
+    /// This is a shorthand for [`Caps::has_extension`].
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.caps().has_extension(name)
+    }
+
     pub fn create_vertex_buffer<B>(
         &self,
         data: &[B],
@@ -170,13 +225,43 @@ impl Context {
     where
         E: Element,
     {
-        let raw = self.raw.create_buffer(
-            bytemuck::cast_slice(data),
+        ElementBuffer::new(&self.raw, data, usage)
+    }
+
+    /// Creates a growable vertex buffer for data that is refilled frequently,
+    /// with initial storage for `capacity` blocks.
+    pub fn create_streaming_vertex_buffer<B>(
+        &self,
+        capacity: usize,
+    ) -> Result<StreamingVertexBuffer<B>, BufferError>
+    where
+        B: Block<Gl> + bytemuck::Pod,
+    {
+        let raw = self.raw.create_buffer_with_len(
+            capacity * size_of::<B>(),
+            glow::ARRAY_BUFFER,
+            BufferUsage::StreamDraw,
+        )?;
+
+        Ok(StreamingVertexBuffer::from_raw(raw))
+    }
+
+    /// Creates a growable element buffer for data that is refilled
+    /// frequently, with initial storage for `capacity` elements.
+    pub fn create_streaming_element_buffer<E>(
+        &self,
+        capacity: usize,
+    ) -> Result<StreamingElementBuffer<E>, BufferError>
+    where
+        E: Element,
+    {
+        let raw = self.raw.create_buffer_with_len(
+            capacity * size_of::<E>(),
             glow::ELEMENT_ARRAY_BUFFER,
-            usage,
+            BufferUsage::StreamDraw,
         )?;
 
-        Ok(ElementBuffer::from_raw(raw))
+        Ok(StreamingElementBuffer::from_raw(raw))
     }
 
     pub fn create_uniform_buffer<B>(
@@ -190,6 +275,110 @@ impl Context {
         UniformBuffer::new(&self.raw, &data, usage)
     }
 
+    /// Writes a uniform block value into transient storage and returns a
+    /// binding to it.
+    ///
+    /// This is meant for small values that change with every draw call, such
+    /// as model matrices, and avoids creating a [`UniformBuffer`] for each of
+    /// them. The values are packed into shared buffers owned by the context
+    /// and bound with `bind_buffer_range`. The value stays valid for as long
+    /// as the returned binding is alive.
+    pub fn uniform_binding_from_value<B>(
+        &self,
+        value: B::Gl,
+    ) -> Result<UniformBufferBinding<B::Sl>, BufferError>
+    where
+        B: Block<Gl>,
+    {
+        self.uniform_ring.borrow_mut().push(&value)
+    }
+
+    /// Binds a uniform block globally, so that it is provided to all programs
+    /// whose uniforms contain a block of type `B`.
+    ///
+    /// Each block type is assigned a reserved uniform buffer binding, counting
+    /// down from the highest one, and stays bound there. Draw calls skip
+    /// binding global blocks, so they can be declared as
+    /// [`UniformInterfaceDom::GlobalBlock`](crate::UniformInterfaceDom::GlobalBlock)
+    /// fields, which need no binding on the host side. Regular block fields of
+    /// type `B` are satisfied by passing [`UniformBufferBinding::global`].
+    /// Calling this again for the same type replaces the buffer, and passing a
+    /// global binding leaves it unchanged.
+    ///
+    /// Global blocks must be set before creating the programs that use them,
+    /// since uniform block bindings are fixed when a program is created.
+    /// Programs that are cached by [`Context::get_program`] are recreated when a
+    /// new block type is set.
+    pub fn set_global_uniform_block<B>(
+        &self,
+        binding: &UniformBufferBinding<B>,
+    ) -> Result<(), BufferError>
+    where
+        B: Block<Sl, Sl = B> + 'static,
+    {
+        let Some(raw) = binding.shared_raw() else {
+            return Ok(());
+        };
+
+        let mut global_uniform_blocks = self.global_uniform_blocks.borrow_mut();
+        let num_blocks = global_uniform_blocks.blocks.len();
+        let max = self.caps().max_uniform_buffer_bindings;
+
+        let location = match global_uniform_blocks.blocks.get(&TypeId::of::<B>()) {
+            Some((location, _)) => *location,
+            None => {
+                let location = usize::try_from(max)
+                    .unwrap()
+                    .checked_sub(num_blocks + 1)
+                    .ok_or(BufferError::TooManyGlobalUniformBlocks(max))?;
+
+                // Cached programs that contain a block of this type need to be
+                // recreated with the reserved binding.
+                global_uniform_blocks.generation += 1;
+
+                location
+            }
+        };
+
+        self.raw
+            .set_global_uniform_buffer(location, &raw, binding.range());
+
+        global_uniform_blocks
+            .blocks
+            .insert(TypeId::of::<B>(), (location, raw));
+
+        Ok(())
+    }
+
+    /// Creates a uniform buffer that stores an array of blocks, each of which
+    /// can be bound separately with [`UniformBuffer::binding`].
+    ///
+    /// Blocks are padded to `UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+    pub fn create_uniform_buffer_array<B>(
+        &self,
+        data: &[B::Gl],
+        usage: BufferUsage,
+    ) -> Result<UniformBuffer<[B]>, BufferError>
+    where
+        B: Block<Gl>,
+    {
+        UniformBuffer::new_array(&self.raw, data, usage)
+    }
+
+    /// Creates a pixel unpack buffer with `len` bytes of uninitialized
+    /// storage.
+    pub fn create_pixel_unpack_buffer(
+        &self,
+        len: usize,
+        usage: BufferUsage,
+    ) -> Result<PixelUnpackBuffer, BufferError> {
+        let raw = self
+            .raw
+            .create_buffer_with_len(len, glow::PIXEL_UNPACK_BUFFER, usage)?;
+
+        Ok(PixelUnpackBuffer::from_raw(raw))
+    }
+
     pub fn create_color_texture_2d<S: ColorSample>(
         &self,
         image: ColorImage<'_, S>,
@@ -210,6 +399,22 @@ impl Context {
         Ok(ColorTexture2d::from_raw(raw))
     }
 
+    /// Creates a texture from a pre-built mip chain, with the `i`-th image
+    /// holding level `i`.
+    ///
+    /// Each level must be half the size of the previous one, rounded down and
+    /// clamped to 1. This is mostly useful for compressed images, for which
+    /// mipmaps can not be generated.
+    pub fn create_color_texture_2d_with_levels<'a, S: ColorSample>(
+        &self,
+        images: impl IntoIterator<Item = ColorImage<'a, S>>,
+    ) -> Result<ColorTexture2d<S>, TextureError> {
+        let images = images.into_iter().map(ColorImage::raw).collect();
+        let raw = self.raw.create_texture_2d_with_level_images(images)?;
+
+        Ok(ColorTexture2d::from_raw(raw))
+    }
+
     pub fn create_depth_texture_2d(
         &self,
         image: DepthImage<'_>,
@@ -219,6 +424,21 @@ impl Context {
         Ok(DepthTexture2d::from_raw(raw))
     }
 
+    /// Returns the pool of reusable render target textures of this context.
+    pub fn texture_pool(&self) -> &TexturePool {
+        &self.texture_pool
+    }
+
+    pub fn create_query(&self, ty: QueryType) -> Result<Query, QueryError> {
+        let raw = self.raw.create_query(ty)?;
+
+        Ok(Query::from_raw(raw))
+    }
+
+    pub fn create_render_graph(&self) -> RenderGraph {
+        RenderGraph::new(self)
+    }
+
     pub fn create_program<U, VSig, VFunc, FSig, FFunc>(
         &self,
         vertex_shader: VFunc,
@@ -231,15 +451,19 @@ impl Context {
         FSig: FsSig<C = (), W = VSig::W>,
         FFunc: FsFunc<FSig>,
     {
-        let program_def =
+        let mut program_def =
             transpile_to_program_def::<U, VSig, VFunc, FSig, FFunc>(vertex_shader, fragment_shader);
+        self.global_uniform_blocks.borrow().apply(&mut program_def);
 
         log::info!("Vertex shader:\n{}", program_def.vertex_shader_source);
         log::info!("Fragment shader:\n{}", program_def.fragment_shader_source);
 
         let raw = self.raw.create_program(program_def)?;
 
-        Ok(Program::unchecked_from_raw(Rc::new(raw)))
+        Ok(Program::unchecked_from_raw(
+            Rc::new(raw),
+            self.uniform_ring.clone(),
+        ))
     }
 
     pub fn create_program_with_consts<U, VSig, VFunc, FSig, FFunc>(
@@ -255,18 +479,22 @@ impl Context {
         FSig: FsSig<C = VSig::C, W = VSig::W>,
         FFunc: FsFunc<FSig>,
     {
-        let program_def = transpile_to_program_def_with_consts::<U, VSig, VFunc, FSig, FFunc>(
+        let mut program_def = transpile_to_program_def_with_consts::<U, VSig, VFunc, FSig, FFunc>(
             consts,
             vertex_shader,
             fragment_shader,
         );
+        self.global_uniform_blocks.borrow().apply(&mut program_def);
 
         log::info!("Vertex shader:\n{}", program_def.vertex_shader_source);
         log::info!("Fragment shader:\n{}", program_def.fragment_shader_source);
 
         let raw = self.raw.create_program(program_def)?;
 
-        Ok(Program::unchecked_from_raw(Rc::new(raw)))
+        Ok(Program::unchecked_from_raw(
+            Rc::new(raw),
+            self.uniform_ring.clone(),
+        ))
     }
 
     pub fn get_program<VSig, VFunc, FSig, FFunc>(
@@ -288,6 +516,28 @@ impl Context {
         }
     }
 
+    /// Inserts a fence into the command stream.
+    pub fn fence(&self) -> Result<Fence, FenceError> {
+        let raw = self.raw.create_fence()?;
+
+        Ok(Fence::from_raw(raw))
+    }
+
+    /// Starts copying a region of the framebuffer's first color attachment
+    /// into a pixel pack buffer, without waiting for the GPU.
+    ///
+    /// Normalized fixed-point attachments are read as `RGBA`/`U8`, while
+    /// floating-point attachments are read as `RGBA`/`F32`.
+    pub fn read_pixels_async<F: FsInterface<Sl>>(
+        &self,
+        framebuffer: impl Into<Framebuffer<F>>,
+        rect: Rect,
+    ) -> Result<PixelReadback, ReadbackError> {
+        let raw = self.raw.read_pixels_async(framebuffer.into().raw(), rect)?;
+
+        Ok(PixelReadback::from_raw(raw))
+    }
+
     pub fn default_framebuffer_size(&self) -> [u32; 2] {
         self.raw.default_framebuffer_size()
     }
@@ -3,29 +3,56 @@ use std::{marker::PhantomData, mem::size_of, ops::Range, rc::Rc};
 use bytemuck::Pod;
 use sealed::sealed;
 
-use super::{raw, ElementType};
+use super::{
+    raw, BufferError, BufferMap, BufferMapMut, BufferMapSettings, BufferUsage, ElementType,
+};
 
 #[sealed]
-pub trait Element: Pod {
+pub trait Element: Pod + Ord {
     const TYPE: ElementType;
+
+    #[doc(hidden)]
+    fn to_u32(self) -> u32;
 }
 
 #[sealed]
 impl Element for u16 {
     const TYPE: ElementType = ElementType::U16;
+
+    fn to_u32(self) -> u32 {
+        self.into()
+    }
 }
 
 #[sealed]
 impl Element for u32 {
     const TYPE: ElementType = ElementType::U32;
+
+    fn to_u32(self) -> u32 {
+        self
+    }
+}
+
+pub(super) fn indices<E: Element>(data: &[E]) -> impl ExactSizeIterator<Item = u32> + '_ {
+    data.iter().map(|&index| index.to_u32())
 }
 
 /// Stores element data in a buffer on the GPU.
 ///
 /// Instances of `ElementBuffer` can be created with
 /// [`Context::create_element_buffer`](crate::gl::Context::create_element_buffer).
+///
+/// The buffer keeps track of the largest index of each upload, so that draw
+/// calls can check that the indices in their range of elements are in bounds
+/// of the vertex buffers. Draw calls that cover only part of an upload are
+/// checked against the largest index of the whole upload, so meshes whose
+/// indices refer to different vertex ranges should be uploaded separately,
+/// e.g. with [`set_range`](ElementBuffer::set_range). Elements that are written
+/// through [`map_mut`](ElementBuffer::map_mut) can not be checked, see
+/// [`is_checked`](ElementBuffer::is_checked).
 pub struct ElementBuffer<E = u32> {
     raw: Rc<raw::Buffer>,
+    indices: Rc<raw::ElementIndices>,
     _phantom: PhantomData<E>,
 }
 
@@ -34,16 +61,29 @@ pub struct ElementBufferBinding {
     raw: Rc<raw::Buffer>,
     ty: ElementType,
     range: Range<usize>,
+    indices: Rc<raw::ElementIndices>,
 }
 
 impl<E: Element> ElementBuffer<E> {
-    pub(super) fn from_raw(raw: raw::Buffer) -> Self {
-        assert_eq!(raw.len() % size_of::<E>(), 0);
-
-        Self {
+    pub(super) fn new(
+        ctx: &raw::Context,
+        data: &[E],
+        usage: BufferUsage,
+    ) -> Result<Self, BufferError> {
+        let raw = ctx.create_buffer(
+            bytemuck::cast_slice(data),
+            glow::ELEMENT_ARRAY_BUFFER,
+            usage,
+        )?;
+
+        let element_indices = raw::ElementIndices::new();
+        element_indices.set(indices(data));
+
+        Ok(Self {
             raw: Rc::new(raw),
+            indices: Rc::new(element_indices),
             _phantom: PhantomData,
-        }
+        })
     }
 
     pub fn len(&self) -> usize {
@@ -56,20 +96,98 @@ impl<E: Element> ElementBuffer<E> {
         self.len() == 0
     }
 
-    pub fn set(&self, data: &[E]) {
-        self.raw.set(bytemuck::cast_slice(data));
+    /// Replaces all elements in the buffer, reallocating it.
+    ///
+    /// Returns an error if the buffer is mapped.
+    pub fn set(&self, data: &[E]) -> Result<(), BufferError> {
+        self.raw.set(bytemuck::cast_slice(data))?;
+        self.indices.set(indices(data));
+
+        Ok(())
+    }
+
+    /// Overwrites the elements starting at `offset` with `data`, without
+    /// reallocating the buffer.
+    pub fn set_range(&self, offset: usize, data: &[E]) -> Result<(), BufferError> {
+        self.raw
+            .set_range(offset * size_of::<E>(), bytemuck::cast_slice(data))?;
+
+        self.indices.write(offset, indices(data));
+
+        Ok(())
+    }
+
+    /// Returns whether draw calls can check all of the buffer's indices.
+    ///
+    /// This is `false` after elements have been written through
+    /// [`map_mut`](ElementBuffer::map_mut), until they are written again with
+    /// [`set`](ElementBuffer::set) or [`set_range`](ElementBuffer::set_range).
+    /// Draw calls that use unchecked elements are not checked for out of bounds
+    /// indices.
+    pub fn is_checked(&self) -> bool {
+        self.indices.is_known()
+    }
+
+    /// Maps the elements in `range` for writing.
+    ///
+    /// Since the written elements are not known, draw calls that use them can
+    /// no longer check their indices until they are written again with
+    /// [`set`](ElementBuffer::set) or [`set_range`](ElementBuffer::set_range).
+    /// Buffer mapping is not available in WebGL, where this returns an error.
+    pub fn map_mut(
+        &self,
+        range: Range<usize>,
+        settings: BufferMapSettings,
+    ) -> Result<BufferMapMut<'_, E>, BufferError> {
+        let raw = self.raw.map_write(
+            range.start * size_of::<E>(),
+            range.len() * size_of::<E>(),
+            settings,
+        )?;
+
+        self.indices.forget(range);
+
+        BufferMapMut::from_raw(raw)
+    }
+
+    /// Maps the elements in `range` for reading.
+    ///
+    /// This blocks until the GPU has finished writing to the buffer, e.g. with
+    /// transform feedback. Buffer mapping is not available in WebGL, where this
+    /// returns an error.
+    pub fn map(&self, range: Range<usize>) -> Result<BufferMap<'_, E>, BufferError> {
+        let raw = self
+            .raw
+            .map_read(range.start * size_of::<E>(), range.len() * size_of::<E>())?;
+
+        BufferMap::from_raw(raw)
     }
 
     pub fn as_binding(&self) -> ElementBufferBinding {
-        ElementBufferBinding {
-            raw: self.raw.clone(),
-            ty: E::TYPE,
-            range: 0..self.len(),
-        }
+        ElementBufferBinding::from_raw(
+            self.raw.clone(),
+            E::TYPE,
+            0..self.len(),
+            self.indices.clone(),
+        )
     }
 }
 
 impl ElementBufferBinding {
+    pub(super) fn from_raw(
+        raw: Rc<raw::Buffer>,
+        ty: ElementType,
+        range: Range<usize>,
+        indices: Rc<raw::ElementIndices>,
+    ) -> Self {
+        Self {
+            raw,
+            ty,
+            range,
+            indices,
+        }
+    }
+
     pub(crate) fn raw(&self) -> &Rc<raw::Buffer> {
         &self.raw
     }
@@ -82,6 +200,10 @@ impl ElementBufferBinding {
         self.range.clone()
     }
 
+    pub(crate) fn indices(&self) -> &Rc<raw::ElementIndices> {
+        &self.indices
+    }
+
     pub fn with_range(mut self, range: Range<usize>) -> Self {
         self.range = range;
         self
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use super::{raw, FenceError};
+
+/// A fence that becomes signaled once the GPU has finished all commands that
+/// were issued before it.
+///
+/// Instances of `Fence` can be created with
+/// [`Context::fence`](crate::gl::Context::fence).
+pub struct Fence {
+    raw: raw::Fence,
+}
+
+impl Fence {
+    pub(super) fn from_raw(raw: raw::Fence) -> Self {
+        Self { raw }
+    }
+
+    /// Returns `true` if the fence is signaled, without waiting for the GPU.
+    pub fn poll(&self) -> Result<bool, FenceError> {
+        self.raw.poll()
+    }
+
+    /// Waits for the fence to become signaled for at most `timeout`.
+    pub fn wait(&self, timeout: Duration) -> Result<bool, FenceError> {
+        self.raw.wait(timeout)
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     FsInterface, Gl, Sl,
 };
 
-use super::{raw, ColorSampler2d, Sampler2dSettings};
+use super::{raw, ColorSampler2d, Sampler2dSettings, TextureError};
 
 #[derive(Clone)]
 pub struct ColorAttachment<S = sl::Vec4> {
@@ -25,11 +25,17 @@ impl<S> ColorAttachment<S> {
     pub fn as_color_sampler(&self, settings: Sampler2dSettings) -> ColorSampler2d<S> {
         ColorSampler2d::from_raw(self.raw.sampler(settings, None))
     }
+
+    /// Regenerates all mipmap levels of the attached texture from the
+    /// contents of level 0.
+    pub fn generate_mipmaps(&self) -> Result<(), TextureError> {
+        self.raw.generate_mipmaps()
+    }
 }
 
 impl<S: ColorSample> From<ColorAttachment<S>> for Framebuffer<S> {
     fn from(value: ColorAttachment<S>) -> Self {
-        Framebuffer(FramebufferInternal::Color(value))
+        Framebuffer::color(value)
     }
 }
 
@@ -46,67 +52,63 @@ impl DepthAttachment {
 
 impl From<DepthAttachment> for Framebuffer<()> {
     fn from(value: DepthAttachment) -> Self {
-        Framebuffer(FramebufferInternal::Depth(value))
+        Framebuffer::depth(value)
     }
 }
 
+/// A set of attachments that draw calls render into.
+///
+/// The raw attachments are collected once when the framebuffer is created, so
+/// that draw calls do not need to visit `F` again. Creating a framebuffer does
+/// not allocate, so it is fine to create one for each draw call.
 #[derive(Clone)]
-enum FramebufferInternal<F: FsInterface<Sl>> {
-    Default,
-    Depth(DepthAttachment),
-    Color(F::Gl),
-    ColorDepth {
-        color: F::Gl,
-        depth: DepthAttachment,
-    },
+pub struct Framebuffer<F: FsInterface<Sl> = sl::Vec4> {
+    raw: raw::Framebuffer,
+    _phantom: PhantomData<F>,
 }
 
-#[derive(Clone)]
-pub struct Framebuffer<F: FsInterface<Sl> = sl::Vec4>(FramebufferInternal<F>);
-
 impl<F: FsInterface<Sl>> Framebuffer<F> {
+    pub(super) fn from_raw(raw: raw::Framebuffer) -> Self {
+        Self {
+            raw,
+            _phantom: PhantomData,
+        }
+    }
+
     pub fn color(color: F::Gl) -> Self {
-        Framebuffer(FramebufferInternal::Color(color))
+        Self::from_raw(raw::Framebuffer::Attachments {
+            attachments: raw_color_attachments(&color),
+        })
     }
 
     pub fn color_depth(color: F::Gl, depth: DepthAttachment) -> Self {
-        Framebuffer(FramebufferInternal::ColorDepth { color, depth })
+        let mut attachments = raw_color_attachments(&color);
+        attachments.push(depth.raw);
+
+        Self::from_raw(raw::Framebuffer::Attachments { attachments })
     }
 
-    pub fn raw(&self) -> raw::Framebuffer {
-        use FramebufferInternal::*;
-
-        match &self.0 {
-            Default => raw::Framebuffer::Default,
-            Depth(depth) => raw::Framebuffer::Attachments {
-                attachments: vec![depth.raw.clone()],
-            },
-            Color(color) => raw::Framebuffer::Attachments {
-                attachments: raw_color_attachments(color),
-            },
-            ColorDepth { color, depth } => {
-                let mut attachments = raw_color_attachments(color);
-                attachments.push(depth.raw.clone());
-                raw::Framebuffer::Attachments { attachments }
-            }
-        }
+    pub fn raw(&self) -> &raw::Framebuffer {
+        &self.raw
     }
 }
 
 impl Framebuffer<()> {
     pub fn depth(depth: DepthAttachment) -> Self {
-        Framebuffer(FramebufferInternal::Depth(depth))
+        Self::from_raw(raw::Framebuffer::Attachments {
+            attachments: [depth.raw].into_iter().collect(),
+        })
     }
 }
 
 impl Default for Framebuffer<sl::Vec4> {
     fn default() -> Self {
-        Framebuffer(FramebufferInternal::Default)
+        Self::from_raw(raw::Framebuffer::Default)
     }
 }
 
-fn raw_color_attachments<F: FsInterface<Gl>>(attachments: &F) -> Vec<raw::Attachment> {
-    struct Visitor(Vec<raw::Attachment>);
+fn raw_color_attachments<F: FsInterface<Gl>>(attachments: &F) -> raw::AttachmentList {
+    struct Visitor(raw::AttachmentList);
 
     impl<'a> FragmentVisitor<'a, Gl> for Visitor {
         fn accept<S: ColorSample>(&mut self, _: &str, attachment: &ColorAttachment<S>) {
@@ -115,7 +117,7 @@ fn raw_color_attachments<F: FsInterface<Gl>>(attachments: &F) -> Vec<raw::Attach
     }
 
     // TODO: Remove hardcoded path names.
-    let mut visitor = Visitor(Vec::new());
+    let mut visitor = Visitor(raw::AttachmentList::new());
     attachments.visit("fragment_output", &mut visitor);
 
     visitor.0
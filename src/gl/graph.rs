@@ -0,0 +1,950 @@
+//! A render graph for declaring passes and the textures that they use.
+//!
+//! Passes declare the textures that they write to and sample from, and the
+//! graph takes care of the rest: it allocates transient textures, reuses them
+//! between passes whose lifetimes do not overlap, orders the passes by their
+//! dependencies, builds the framebuffers that the passes render into, and
+//! skips passes whose results are never used.
+//!
+//! ```ignore
+//! let mut graph = gl.create_render_graph();
+//!
+//! // Each frame:
+//! let mut frame = graph.frame();
+//!
+//! let albedo = frame.create_color_texture::<[u8; 4]>("albedo", TextureSize::Screen);
+//! let depth = frame.create_depth_texture(
+//!     "depth",
+//!     TextureSize::Screen,
+//!     gl::ImageInternalFormat::DepthF32,
+//! );
+//!
+//! frame
+//!     .add_pass("scene")
+//!     .with_color_write(albedo)
+//!     .with_depth_write(depth)
+//!     .run(|resources: &PassResources<sl::Vec4>| {
+//!         // Draw the scene into `resources.framebuffer()`.
+//!
+//!         Ok(())
+//!     });
+//!
+//! frame
+//!     .add_pass("present")
+//!     .with_color_read(albedo)
+//!     .with_screen_write()
+//!     .run(|resources: &PassResources| {
+//!         let albedo = resources.color_texture(albedo).as_color_sampler(Default::default());
+//!
+//!         // Draw a full-screen quad that samples `albedo` into
+//!         // `resources.framebuffer()`.
+//!
+//!         Ok(())
+//!     });
+//!
+//! let report = frame.execute()?;
+//! ```
+
+use std::{any::TypeId, collections::BTreeSet, marker::PhantomData, rc::Rc};
+
+use crate::{
+    interface::FragmentVisitor,
+    sl::{self, ColorSample, Interpolant},
+    FsInterface, Sl,
+};
+
+use super::{
+    raw::{self, ImageInternalFormat},
+    texture_pool::RawLease,
+    ColorTexture2d, Context, DepthTexture2d, DrawError, Framebuffer, GraphError, Pixel,
+};
+
+/// The size of a texture in a render graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureSize {
+    /// A fixed size in pixels.
+    Fixed([u32; 2]),
+
+    /// The size of the default framebuffer.
+    Screen,
+
+    /// The size of the default framebuffer, scaled by the given factor and
+    /// rounded, but at least one pixel.
+    ScreenScaled(f32),
+}
+
+impl TextureSize {
+    fn resolve(self, screen_size: [u32; 2]) -> [u32; 2] {
+        match self {
+            TextureSize::Fixed(size) => size,
+            TextureSize::Screen => screen_size,
+            TextureSize::ScreenScaled(factor) => {
+                screen_size.map(|x| ((x as f32 * factor).round() as u32).max(1))
+            }
+        }
+    }
+}
+
+/// A handle to a color texture in a render graph.
+pub struct ColorTextureHandle<S = sl::Vec4> {
+    index: usize,
+    _phantom: PhantomData<S>,
+}
+
+impl<S> Clone for ColorTextureHandle<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for ColorTextureHandle<S> {}
+
+/// A handle to a depth texture in a render graph.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthTextureHandle {
+    index: usize,
+}
+
+enum TextureSource {
+    Transient {
+        internal_format: ImageInternalFormat,
+        size: TextureSize,
+    },
+    Imported(Rc<raw::Texture2d>),
+}
+
+struct TextureDecl {
+    name: String,
+    source: TextureSource,
+
+    /// The sample type of color textures, which is checked against the
+    /// outputs of framebuffers.
+    sample: Option<TypeId>,
+}
+
+type PassFn<'a> = Box<dyn FnMut(PassState<'_>) -> Result<(), DrawError> + 'a>;
+
+struct PassDecl<'a> {
+    name: String,
+    run: PassFn<'a>,
+
+    /// The sample types of the outputs of the pass's framebuffer type, which
+    /// are checked against the declared targets.
+    outputs: Vec<TypeId>,
+}
+
+/// The textures that a pass accesses, given as indices into the textures of
+/// the frame.
+#[derive(Debug, Clone, Default)]
+struct PassAccess {
+    reads: BTreeSet<usize>,
+    writes: BTreeSet<usize>,
+
+    /// The color textures that the pass renders into, in declaration order.
+    color_targets: Vec<usize>,
+
+    /// The depth textures that the pass renders into. Validation checks that
+    /// there is at most one.
+    depth_targets: Vec<usize>,
+    writes_screen: bool,
+}
+
+enum GraphTexture {
+    Leased(RawLease),
+    Imported(Rc<raw::Texture2d>),
+}
+
+impl GraphTexture {
+    fn texture(&self) -> &Rc<raw::Texture2d> {
+        match self {
+            GraphTexture::Leased(lease) => lease.texture(),
+            GraphTexture::Imported(texture) => texture,
+        }
+    }
+}
+
+/// A render graph, which declares the passes of frames.
+///
+/// The passes of a frame are declared with a [`FrameGraph`] that is obtained
+/// from [`frame`](RenderGraph::frame). Transient textures are leased from the
+/// context's [`TexturePool`](super::TexturePool), so they are reused across
+/// frames as long as the pool keeps them. Executing a frame advances the pool
+/// to the next frame, which drops textures that are no longer used, e.g. after
+/// the window has been resized.
+pub struct RenderGraph {
+    gl: Context,
+}
+
+impl RenderGraph {
+    pub fn new(gl: &Context) -> Self {
+        Self { gl: gl.clone() }
+    }
+
+    /// Releases all transient textures that are not in use.
+    pub fn clear(&mut self) {
+        self.gl.texture_pool().clear();
+    }
+
+    /// Starts declaring the passes of a frame.
+    pub fn frame<'a>(&mut self) -> FrameGraph<'_, 'a> {
+        FrameGraph {
+            graph: self,
+            textures: Vec::new(),
+            passes: Vec::new(),
+            accesses: Vec::new(),
+        }
+    }
+}
+
+/// The passes and textures of a single frame of a [`RenderGraph`].
+pub struct FrameGraph<'g, 'a> {
+    graph: &'g mut RenderGraph,
+    textures: Vec<TextureDecl>,
+    passes: Vec<PassDecl<'a>>,
+    accesses: Vec<PassAccess>,
+}
+
+/// The result of executing a [`FrameGraph`].
+#[derive(Debug, Clone, Default)]
+pub struct GraphReport {
+    /// The names of the passes that were skipped because their results are
+    /// not used, in declaration order.
+    pub unused_passes: Vec<String>,
+}
+
+impl<'g, 'a> FrameGraph<'g, 'a> {
+    /// Declares a transient color texture whose internal format is given by
+    /// the pixel type `P`.
+    pub fn create_color_texture<P: Pixel>(
+        &mut self,
+        name: &str,
+        size: TextureSize,
+    ) -> ColorTextureHandle<P::Sample> {
+        let source = TextureSource::Transient {
+            internal_format: P::INTERNAL_FORMAT,
+            size,
+        };
+
+        ColorTextureHandle {
+            index: self.push_texture(name, source, Some(TypeId::of::<P::Sample>())),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Declares a transient depth texture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `internal_format` is not a depth format.
+    pub fn create_depth_texture(
+        &mut self,
+        name: &str,
+        size: TextureSize,
+        internal_format: ImageInternalFormat,
+    ) -> DepthTextureHandle {
+        assert!(
+            internal_format.is_depth_renderable(),
+            "{internal_format:?} is not a depth format"
+        );
+
+        let source = TextureSource::Transient {
+            internal_format,
+            size,
+        };
+
+        DepthTextureHandle {
+            index: self.push_texture(name, source, None),
+        }
+    }
+
+    /// Makes an existing color texture available to the passes of the graph.
+    ///
+    /// Passes that write to imported textures are never skipped.
+    pub fn import_color_texture<S: ColorSample>(
+        &mut self,
+        name: &str,
+        texture: &ColorTexture2d<S>,
+    ) -> ColorTextureHandle<S> {
+        let source = TextureSource::Imported(texture.raw().clone());
+
+        ColorTextureHandle {
+            index: self.push_texture(name, source, Some(TypeId::of::<S>())),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Makes an existing depth texture available to the passes of the graph.
+    ///
+    /// Passes that write to imported textures are never skipped.
+    pub fn import_depth_texture(
+        &mut self,
+        name: &str,
+        texture: &DepthTexture2d,
+    ) -> DepthTextureHandle {
+        let source = TextureSource::Imported(texture.raw().clone());
+
+        DepthTextureHandle {
+            index: self.push_texture(name, source, None),
+        }
+    }
+
+    /// Starts declaring a pass.
+    ///
+    /// The pass is added to the graph when [`PassBuilder::run`] is called.
+    pub fn add_pass<'f>(&'f mut self, name: &str) -> PassBuilder<'f, 'g, 'a> {
+        PassBuilder {
+            frame: self,
+            name: name.to_string(),
+            access: PassAccess::default(),
+        }
+    }
+
+    /// Executes the passes whose results are used.
+    ///
+    /// A pass is used if it writes to the screen or to an imported texture, or
+    /// if it writes to a texture that is read by a used pass. Passes are run
+    /// in declaration order, except that a pass that reads a texture is run
+    /// after the passes that write to it. A pass reads the result of the
+    /// passes that are declared before it and write to the texture, or, if
+    /// there are none, of all passes that write to the texture.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pass's framebuffer does not match its declared
+    /// targets, if a pass reads a texture that it also writes to, if a pass
+    /// reads a transient texture that no pass writes to, or if the passes have
+    /// cyclic dependencies. In these cases, no pass is run.
+    ///
+    /// If a pass fails, the remaining passes are not run.
+    pub fn execute(mut self) -> Result<GraphReport, GraphError> {
+        self.validate()?;
+
+        let dependencies = dependencies(&self.accesses);
+        let used = used_passes(&self.accesses, &dependencies, |index| {
+            matches!(self.textures[index].source, TextureSource::Imported(_))
+        });
+        let order = order(&dependencies, &used).map_err(|cycle| GraphError::Cycle {
+            passes: cycle
+                .into_iter()
+                .map(|i| self.passes[i].name.clone())
+                .collect(),
+        })?;
+
+        let unused_passes = self
+            .passes
+            .iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+            .map(|(pass, _)| pass.name.clone())
+            .collect();
+
+        self.run(&order)?;
+
+        self.graph.gl.texture_pool().next_frame();
+
+        Ok(GraphReport { unused_passes })
+    }
+
+    fn push_texture(&mut self, name: &str, source: TextureSource, sample: Option<TypeId>) -> usize {
+        self.textures.push(TextureDecl {
+            name: name.to_string(),
+            source,
+            sample,
+        });
+
+        self.textures.len() - 1
+    }
+
+    fn validate(&self) -> Result<(), GraphError> {
+        for (pass, access) in self.passes.iter().zip(&self.accesses) {
+            validate_targets(&pass.name, access, &pass.outputs, |index| {
+                self.textures[index].sample
+            })?;
+        }
+
+        if let Some((pass, texture)) = find_feedback_loop(&self.accesses) {
+            return Err(GraphError::FeedbackLoop {
+                pass: self.passes[pass].name.clone(),
+                texture: self.textures[texture].name.clone(),
+            });
+        }
+
+        for (pass, access) in self.passes.iter().zip(&self.accesses) {
+            for &index in &access.reads {
+                let texture = &self.textures[index];
+
+                if matches!(texture.source, TextureSource::Transient { .. })
+                    && !self
+                        .accesses
+                        .iter()
+                        .any(|other| other.writes.contains(&index))
+                {
+                    return Err(GraphError::UnwrittenTexture {
+                        pass: pass.name.clone(),
+                        texture: texture.name.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run(&mut self, order: &[usize]) -> Result<(), GraphError> {
+        let pool = self.graph.gl.texture_pool();
+        let screen_size = self.graph.gl.raw().default_framebuffer_size();
+
+        // The position of the last pass in `order` that uses each texture.
+        let mut last_use = vec![None; self.textures.len()];
+
+        for (position, &i) in order.iter().enumerate() {
+            let access = &self.accesses[i];
+
+            for &index in access.reads.iter().chain(&access.writes) {
+                last_use[index] = Some(position);
+            }
+        }
+
+        let mut textures: Vec<Option<GraphTexture>> = Vec::new();
+        textures.resize_with(self.textures.len(), || None);
+
+        for (position, &i) in order.iter().enumerate() {
+            let pass = &mut self.passes[i];
+            let access = &self.accesses[i];
+
+            // Textures are acquired before the textures whose last use is this
+            // pass are released, so that the textures of a pass never alias
+            // each other.
+            for &index in access.reads.iter().chain(&access.writes) {
+                if textures[index].is_some() {
+                    continue;
+                }
+
+                let texture = match &self.textures[index].source {
+                    TextureSource::Transient {
+                        internal_format,
+                        size,
+                    } => GraphTexture::Leased(pool.lease_raw(
+                        size.resolve(screen_size),
+                        *internal_format,
+                        1,
+                    )?),
+                    TextureSource::Imported(texture) => GraphTexture::Imported(texture.clone()),
+                };
+
+                textures[index] = Some(texture);
+            }
+
+            let state = PassState {
+                name: &pass.name,
+                access,
+                textures: &textures,
+            };
+
+            (pass.run)(state).map_err(|error| GraphError::Draw {
+                pass: pass.name.clone(),
+                error,
+            })?;
+
+            // Dropping the lease returns a transient texture to the pool, so
+            // that later passes can reuse it.
+            for &index in access.reads.iter().chain(&access.writes) {
+                if last_use[index] == Some(position) {
+                    textures[index] = None;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Declares the textures that a pass uses.
+#[must_use]
+pub struct PassBuilder<'f, 'g, 'a> {
+    frame: &'f mut FrameGraph<'g, 'a>,
+    name: String,
+    access: PassAccess,
+}
+
+impl<'f, 'g, 'a> PassBuilder<'f, 'g, 'a> {
+    /// Declares that the pass samples a color texture.
+    pub fn with_color_read<S>(mut self, texture: ColorTextureHandle<S>) -> Self {
+        self.access.reads.insert(texture.index);
+        self
+    }
+
+    /// Declares that the pass samples a depth texture.
+    pub fn with_depth_read(mut self, texture: DepthTextureHandle) -> Self {
+        self.access.reads.insert(texture.index);
+        self
+    }
+
+    /// Declares that the pass renders into a color texture.
+    ///
+    /// The color textures become the color attachments of the pass's
+    /// framebuffer in the order in which they are declared.
+    pub fn with_color_write<S>(mut self, texture: ColorTextureHandle<S>) -> Self {
+        if self.access.writes.insert(texture.index) {
+            self.access.color_targets.push(texture.index);
+        }
+
+        self
+    }
+
+    /// Declares that the pass renders into a depth texture.
+    ///
+    /// A pass can render into at most one depth texture, which is checked when
+    /// the graph is executed.
+    pub fn with_depth_write(mut self, texture: DepthTextureHandle) -> Self {
+        if self.access.writes.insert(texture.index) {
+            self.access.depth_targets.push(texture.index);
+        }
+
+        self
+    }
+
+    /// Declares that the pass renders into the default framebuffer.
+    ///
+    /// Passes that render into the default framebuffer are never skipped. They
+    /// can not render into textures as well.
+    pub fn with_screen_write(mut self) -> Self {
+        self.access.writes_screen = true;
+        self
+    }
+
+    /// Adds the pass to the graph.
+    ///
+    /// `run` is called when the graph is executed, unless the pass is unused.
+    /// The pass renders into a framebuffer with outputs `F`, which must match
+    /// the declared color textures in declaration order, or be a single
+    /// [`sl::Vec4`] for passes that render into the screen.
+    pub fn run<F: FsInterface<Sl>>(
+        self,
+        mut run: impl FnMut(&PassResources<F>) -> Result<(), DrawError> + 'a,
+    ) {
+        self.frame.passes.push(PassDecl {
+            name: self.name,
+            run: Box::new(move |state| {
+                run(&PassResources {
+                    state,
+                    _phantom: PhantomData,
+                })
+            }),
+            outputs: output_sample_types::<F>(),
+        });
+        self.frame.accesses.push(self.access);
+    }
+}
+
+/// The textures of a running pass.
+#[derive(Clone, Copy)]
+struct PassState<'r> {
+    name: &'r str,
+    access: &'r PassAccess,
+    textures: &'r [Option<GraphTexture>],
+}
+
+/// Gives a running pass access to the textures that it declared and to the
+/// framebuffer with outputs `F` that it renders into.
+pub struct PassResources<'r, F = sl::Vec4> {
+    state: PassState<'r>,
+    _phantom: PhantomData<fn() -> F>,
+}
+
+impl<'r, F: FsInterface<Sl>> PassResources<'r, F> {
+    pub fn pass_name(&self) -> &str {
+        self.state.name
+    }
+
+    /// Returns the texture behind a handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pass did not declare that it reads or writes `texture`.
+    pub fn color_texture<S>(&self, texture: ColorTextureHandle<S>) -> ColorTexture2d<S> {
+        ColorTexture2d::from_shared_raw(self.texture(texture.index))
+    }
+
+    /// Returns the texture behind a handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pass did not declare that it reads or writes `texture`.
+    pub fn depth_texture(&self, texture: DepthTextureHandle) -> DepthTexture2d {
+        DepthTexture2d::from_shared_raw(self.texture(texture.index))
+    }
+
+    /// Returns the framebuffer that the pass renders into.
+    ///
+    /// For passes that render into the screen, this is the default
+    /// framebuffer. Otherwise, the framebuffer consists of the declared color
+    /// textures, in declaration order, and the declared depth texture.
+    pub fn framebuffer(&self) -> Framebuffer<F> {
+        let access = self.state.access;
+
+        if access.writes_screen {
+            return Framebuffer::from_raw(raw::Framebuffer::Default);
+        }
+
+        let attachments = access
+            .color_targets
+            .iter()
+            .chain(&access.depth_targets)
+            .map(|&index| raw::Attachment::Texture2d {
+                texture: self.texture(index),
+                level: 0,
+            })
+            .collect();
+
+        Framebuffer::from_raw(raw::Framebuffer::Attachments { attachments })
+    }
+
+    fn texture(&self, index: usize) -> Rc<raw::Texture2d> {
+        let PassState {
+            name,
+            access,
+            textures,
+        } = self.state;
+
+        assert!(
+            access.reads.contains(&index) || access.writes.contains(&index),
+            "pass {name} uses a texture that it did not declare",
+        );
+
+        textures[index].as_ref().unwrap().texture().clone()
+    }
+}
+
+/// Returns the sample types of the outputs of `F`.
+fn output_sample_types<F: FsInterface<Sl>>() -> Vec<TypeId> {
+    struct SampleTypes(Vec<TypeId>);
+
+    impl<'a> FragmentVisitor<'a, Sl> for SampleTypes {
+        fn accept<S: ColorSample>(&mut self, _: &str, _: &'a S) {
+            self.0.push(TypeId::of::<S>());
+        }
+    }
+
+    let outputs = <F::Sl as Interpolant>::shader_input("fragment_output");
+    let mut sample_types = SampleTypes(Vec::new());
+    outputs.visit("fragment_output", &mut sample_types);
+
+    sample_types.0
+}
+
+/// Checks that the outputs of a pass's framebuffer match its declared targets.
+///
+/// The sample types of color textures are given by `sample`.
+fn validate_targets(
+    name: &str,
+    access: &PassAccess,
+    outputs: &[TypeId],
+    sample: impl Fn(usize) -> Option<TypeId>,
+) -> Result<(), GraphError> {
+    if access.depth_targets.len() > 1 {
+        return Err(GraphError::MultipleDepthTargets {
+            pass: name.to_string(),
+        });
+    }
+
+    let declared: Vec<_> = if access.writes_screen {
+        if !access.writes.is_empty() {
+            return Err(GraphError::ScreenAndTextureTargets {
+                pass: name.to_string(),
+            });
+        }
+
+        // The default framebuffer has a single color attachment.
+        vec![TypeId::of::<sl::Vec4>()]
+    } else {
+        access
+            .color_targets
+            .iter()
+            .filter_map(|&index| sample(index))
+            .collect()
+    };
+
+    if outputs != declared {
+        return Err(GraphError::OutputMismatch {
+            pass: name.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns a pass and a texture that the pass both reads and writes, if any.
+fn find_feedback_loop(accesses: &[PassAccess]) -> Option<(usize, usize)> {
+    accesses.iter().enumerate().find_map(|(i, access)| {
+        access
+            .reads
+            .intersection(&access.writes)
+            .next()
+            .map(|&texture| (i, texture))
+    })
+}
+
+/// Returns the passes that each pass has to run after.
+///
+/// A pass that reads a texture depends on the passes that are declared before
+/// it and write to the texture, or, if there are none, on all passes that
+/// write to the texture. In the former case, later passes that write to the
+/// texture must not overwrite it before it is read, so they depend on the
+/// reading pass. Passes that write to the same texture keep their declaration
+/// order.
+fn dependencies(accesses: &[PassAccess]) -> Vec<BTreeSet<usize>> {
+    let writers = |texture: usize| {
+        accesses
+            .iter()
+            .enumerate()
+            .filter(move |(_, access)| access.writes.contains(&texture))
+            .map(|(j, _)| j)
+    };
+
+    let mut dependencies = vec![BTreeSet::new(); accesses.len()];
+
+    for (i, access) in accesses.iter().enumerate() {
+        for &texture in &access.reads {
+            if writers(texture).any(|j| j < i) {
+                dependencies[i].extend(writers(texture).filter(|&j| j < i));
+
+                for j in writers(texture).filter(|&j| j > i) {
+                    dependencies[j].insert(i);
+                }
+            } else {
+                dependencies[i].extend(writers(texture).filter(|&j| j != i));
+            }
+        }
+
+        for &texture in &access.writes {
+            dependencies[i].extend(writers(texture).filter(|&j| j < i));
+        }
+    }
+
+    dependencies
+}
+
+/// Returns which passes contribute to the screen or to imported textures.
+fn used_passes(
+    accesses: &[PassAccess],
+    dependencies: &[BTreeSet<usize>],
+    is_imported: impl Fn(usize) -> bool,
+) -> Vec<bool> {
+    let mut used = vec![false; accesses.len()];
+    let mut stack: Vec<usize> = accesses
+        .iter()
+        .enumerate()
+        .filter(|(_, access)| {
+            access.writes_screen || access.writes.iter().any(|&index| is_imported(index))
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    while let Some(i) = stack.pop() {
+        if !used[i] {
+            used[i] = true;
+            stack.extend(dependencies[i].iter().copied());
+        }
+    }
+
+    used
+}
+
+/// Sorts the used passes topologically, preferring declaration order.
+///
+/// Returns the passes that could not be sorted if there is a cycle.
+fn order(dependencies: &[BTreeSet<usize>], used: &[bool]) -> Result<Vec<usize>, Vec<usize>> {
+    let mut order = Vec::new();
+    let mut done = vec![false; used.len()];
+
+    while let Some(next) =
+        (0..used.len()).find(|&i| used[i] && !done[i] && dependencies[i].iter().all(|&j| done[j]))
+    {
+        done[next] = true;
+        order.push(next);
+    }
+
+    if order.len() != used.iter().filter(|used| **used).count() {
+        return Err((0..used.len()).filter(|&i| used[i] && !done[i]).collect());
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(reads: &[usize], writes: &[usize]) -> PassAccess {
+        PassAccess {
+            reads: reads.iter().copied().collect(),
+            writes: writes.iter().copied().collect(),
+            color_targets: writes.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn screen_pass(reads: &[usize]) -> PassAccess {
+        PassAccess {
+            writes_screen: true,
+            ..pass(reads, &[])
+        }
+    }
+
+    fn schedule(accesses: &[PassAccess]) -> Result<Vec<usize>, Vec<usize>> {
+        let dependencies = dependencies(accesses);
+        let used = used_passes(accesses, &dependencies, |_| false);
+
+        order(&dependencies, &used)
+    }
+
+    #[test]
+    fn independent_passes_keep_declaration_order() {
+        const A: usize = 0;
+        const B: usize = 1;
+
+        let accesses = [pass(&[], &[A]), pass(&[], &[B]), screen_pass(&[A, B])];
+
+        assert_eq!(schedule(&accesses), Ok(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn readers_run_after_later_declared_writers() {
+        const T: usize = 0;
+
+        let accesses = [screen_pass(&[T]), pass(&[], &[T])];
+
+        assert_eq!(schedule(&accesses), Ok(vec![1, 0]));
+    }
+
+    #[test]
+    fn writers_do_not_overwrite_textures_before_they_are_read() {
+        const T: usize = 0;
+        const U: usize = 1;
+        const S: usize = 2;
+
+        // W writes T; A reads T and U; B writes T; X writes U.
+        let accesses = [
+            pass(&[], &[T]),
+            pass(&[T, U], &[S]),
+            pass(&[], &[T]),
+            pass(&[], &[U]),
+            screen_pass(&[S, T]),
+        ];
+
+        assert_eq!(schedule(&accesses), Ok(vec![0, 3, 1, 2, 4]));
+    }
+
+    #[test]
+    fn unused_passes_are_culled() {
+        const T: usize = 0;
+        const U: usize = 1;
+        const V: usize = 2;
+
+        let accesses = [
+            pass(&[], &[T]),
+            pass(&[], &[U]),
+            pass(&[U], &[V]),
+            screen_pass(&[T]),
+        ];
+
+        let dependencies = dependencies(&accesses);
+        let used = used_passes(&accesses, &dependencies, |_| false);
+
+        assert_eq!(used, [true, false, false, true]);
+        assert_eq!(order(&dependencies, &used), Ok(vec![0, 3]));
+    }
+
+    #[test]
+    fn passes_that_write_imported_textures_are_used() {
+        const IMPORTED: usize = 0;
+        const T: usize = 1;
+
+        let accesses = [pass(&[], &[T]), pass(&[T], &[IMPORTED])];
+
+        let dependencies = dependencies(&accesses);
+        let used = used_passes(&accesses, &dependencies, |index| index == IMPORTED);
+
+        assert_eq!(used, [true, true]);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        const T: usize = 0;
+        const U: usize = 1;
+
+        let accesses = [pass(&[U], &[T]), pass(&[T], &[U]), screen_pass(&[T])];
+
+        assert_eq!(schedule(&accesses), Err(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn feedback_loops_are_found() {
+        const T: usize = 0;
+        const U: usize = 1;
+
+        let accesses = [pass(&[], &[T]), pass(&[T], &[U]), pass(&[U], &[U])];
+
+        assert_eq!(find_feedback_loop(&accesses), Some((2, U)));
+    }
+
+    #[test]
+    fn targets_must_match_the_outputs() {
+        const T: usize = 0;
+        const U: usize = 1;
+
+        let vec4 = TypeId::of::<sl::Vec4>();
+        let sample = |index| (index == T).then_some(vec4);
+
+        assert!(validate_targets("pass", &pass(&[], &[T]), &[vec4], sample).is_ok());
+        assert!(validate_targets("pass", &screen_pass(&[T]), &[vec4], sample).is_ok());
+
+        assert!(matches!(
+            validate_targets("pass", &pass(&[], &[T]), &[vec4, vec4], sample),
+            Err(GraphError::OutputMismatch { .. })
+        ));
+        assert!(matches!(
+            validate_targets("pass", &screen_pass(&[]), &[], sample),
+            Err(GraphError::OutputMismatch { .. })
+        ));
+
+        // Depth textures do not have outputs.
+        let depth_pass = PassAccess {
+            depth_targets: vec![U],
+            ..pass(&[], &[T])
+        };
+
+        assert!(validate_targets("pass", &depth_pass, &[vec4], sample).is_ok());
+    }
+
+    #[test]
+    fn invalid_targets_are_rejected() {
+        const T: usize = 0;
+        const D: usize = 1;
+        const E: usize = 2;
+
+        let vec4 = TypeId::of::<sl::Vec4>();
+        let sample = |index| (index == T).then_some(vec4);
+
+        let screen_and_texture = PassAccess {
+            writes_screen: true,
+            ..pass(&[], &[T])
+        };
+
+        assert!(matches!(
+            validate_targets("pass", &screen_and_texture, &[vec4], sample),
+            Err(GraphError::ScreenAndTextureTargets { pass }) if pass == "pass"
+        ));
+
+        let two_depth_targets = PassAccess {
+            depth_targets: vec![D, E],
+            ..pass(&[], &[T])
+        };
+
+        assert!(matches!(
+            validate_targets("pass", &two_depth_targets, &[vec4], sample),
+            Err(GraphError::MultipleDepthTargets { pass }) if pass == "pass"
+        ));
+    }
+}
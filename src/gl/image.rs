@@ -2,7 +2,10 @@ use std::marker::PhantomData;
 
 use crate::sl::{self, ColorSample};
 
-use super::raw::{self, ImageComponentType, ImageInternalFormat};
+use super::{
+    raw::{self, ImageComponentType, ImageData, ImageInternalFormat},
+    Pixel, PixelUnpackBuffer, TextureError,
+};
 
 pub struct ColorImage<'a, S = sl::Vec4> {
     raw: raw::Image<'a>,
@@ -13,443 +16,251 @@ impl<'a, S: ColorSample> ColorImage<'a, S> {
     pub fn raw(self) -> raw::Image<'a> {
         self.raw
     }
-}
 
-impl<'a> ColorImage<'a, sl::Vec4> {
-    pub fn rgba_u8_slice(size: [u32; 2], data: &'a [u8]) -> Self {
+    /// Creates an image from a slice of pixels.
+    ///
+    /// The pixels are given row by row, starting with the bottom row. The
+    /// pixel type `P` determines the internal format of the image, for
+    /// example:
+    ///
+    /// ```ignore
+    /// let image = gl::ColorImage::from_slice([width, height], &pixels);
+    /// ```
+    ///
+    /// where `pixels` could be a `Vec<[u8; 4]>` for an `RGBA8` image, or a
+    /// `Vec<Integer<[u32; 1]>>` for an `R32UI` image.
+    pub fn from_slice<P: Pixel<Sample = S>>(size: [u32; 2], data: &'a [P]) -> Self {
         ColorImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbaU8,
-                data: Some(data),
+                ty: P::TYPE,
+                internal_format: P::INTERNAL_FORMAT,
+                data: ImageData::Slice(bytemuck::cast_slice(data)),
             },
             _phantom: PhantomData,
         }
     }
 
-    pub fn rgba_u8_gamma_slice(size: [u32; 2], data: &'a [u8]) -> Self {
+    /// Creates an image that is filled with zeros.
+    ///
+    /// The pixel type `P` determines the internal format of the image, for
+    /// example:
+    ///
+    /// ```ignore
+    /// let image = gl::ColorImage::zeroed::<[F16; 4]>([width, height]);
+    /// ```
+    pub fn zeroed<P: Pixel<Sample = S>>(size: [u32; 2]) -> Self {
         ColorImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbaU8Gamma,
-                data: Some(data),
+                ty: P::TYPE,
+                internal_format: P::INTERNAL_FORMAT,
+                data: ImageData::Zeroed,
             },
             _phantom: PhantomData,
         }
     }
 
-    pub fn rgba_i8_snorm_slice(size: [u32; 2], data: &'a [i8]) -> Self {
+    /// Creates an image that owns its pixel data, given as bytes.
+    #[cfg(feature = "image")]
+    pub(super) fn from_owned<P: Pixel<Sample = S>>(size: [u32; 2], data: Vec<u8>) -> Self {
         ColorImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RgbaI8Snorm,
-                data: Some(bytemuck::cast_slice(data)),
+                ty: P::TYPE,
+                internal_format: P::INTERNAL_FORMAT,
+                data: ImageData::Owned(data.into()),
             },
             _phantom: PhantomData,
         }
     }
 
-    pub fn rgba_f32_slice(size: [u32; 2], data: &'a [f32]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RgbaF32,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
-        }
+    /// Replaces the image's data with the contents of `buffer`, starting at
+    /// byte `offset`.
+    ///
+    /// This keeps the size and format of the image. Uploading the resulting
+    /// image copies the data from the buffer on the GPU side, without going
+    /// through CPU memory. The image can be created with
+    /// [`ColorImage::zeroed`], for example:
+    ///
+    /// ```ignore
+    /// let image = gl::ColorImage::zeroed::<[u8; 4]>(size).with_unpack_buffer(&buffer, 0);
+    /// texture.set(0, [0, 0], image)?;
+    /// ```
+    #[must_use]
+    pub fn with_unpack_buffer(mut self, buffer: &'a PixelUnpackBuffer, offset: usize) -> Self {
+        self.raw.data = ImageData::UnpackBuffer {
+            buffer: buffer.raw(),
+            offset,
+        };
+        self
     }
 
-    pub fn rgba_u8_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbaU8,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgba_u8_gamma_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbaU8Gamma,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgba_i8_snorm_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RgbaI8Snorm,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgba_f16_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F16,
-                internal_format: ImageInternalFormat::RgbaF16,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgba_f32_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RgbaF32,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-}
-
-impl<'a> ColorImage<'a, sl::Vec3> {
-    pub fn rgb_u8_slice(size: [u32; 2], data: &'a [u8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbU8,
-                data: Some(data),
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgb_u8_gamma_slice(size: [u32; 2], data: &'a [u8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbU8Gamma,
-                data: Some(data),
-            },
-            _phantom: PhantomData,
-        }
-    }
+    fn compressed_slice(
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        data: &'a [u8],
+    ) -> Result<Self, TextureError> {
+        let raw = raw::Image {
+            size,
+            ty: ImageComponentType::U8,
+            internal_format,
+            data: ImageData::Slice(data),
+        };
 
-    pub fn rgb_i8_snorm_slice(size: [u32; 2], data: &'a [i8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RgbI8Snorm,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
-        }
-    }
+        let expected = raw.required_data_len();
 
-    pub fn rgb_f32_slice(size: [u32; 2], data: &'a [f32]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RgbF32,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
+        if data.len() != expected {
+            return Err(TextureError::DataSizeMismatch {
+                expected,
+                got: data.len(),
+            });
         }
-    }
 
-    pub fn rgb_u8_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbU8,
-                data: None,
-            },
+        Ok(ColorImage {
+            raw,
             _phantom: PhantomData,
-        }
+        })
     }
 
-    pub fn rgb_u8_gamma_zero(size: [u32; 2]) -> Self {
+    fn compressed_zero(size: [u32; 2], internal_format: ImageInternalFormat) -> Self {
         ColorImage {
             raw: raw::Image {
                 size,
                 ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgbU8Gamma,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgb_i8_snorm_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RgbI8Snorm,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgb_f16_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F16,
-                internal_format: ImageInternalFormat::RgbF16,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rgb_f32_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RgbF32,
-                data: None,
+                internal_format,
+                data: ImageData::Zeroed,
             },
             _phantom: PhantomData,
         }
     }
 }
 
-impl<'a> ColorImage<'a, sl::Vec2> {
-    pub fn rg_u8_slice(size: [u32; 2], data: &'a [u8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgU8,
-                data: Some(data),
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rg_i8_snorm_slice(size: [u32; 2], data: &'a [i8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RgI8Snorm,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rg_f32_slice(size: [u32; 2], data: &'a [f32]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RgF32,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rg_u8_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RgU8,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
+macro_rules! impl_compressed_image {
+    ($sample:ident, $($slice:ident, $zero:ident, $format:ident;)*) => {
+        impl<'a> ColorImage<'a, sl::$sample> {
+            $(
+                /// Creates an image from compressed data, which must consist
+                /// of `ceil(width / 4) * ceil(height / 4)` blocks.
+                pub fn $slice(size: [u32; 2], data: &'a [u8]) -> Result<Self, TextureError> {
+                    Self::compressed_slice(size, ImageInternalFormat::$format, data)
+                }
+
+                pub fn $zero(size: [u32; 2]) -> Self {
+                    Self::compressed_zero(size, ImageInternalFormat::$format)
+                }
+            )*
+        }
+    };
+}
 
-    pub fn rg_i8_snorm_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RgI8Snorm,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
+impl_compressed_image!(
+    Vec4,
+    rgba_etc2_slice, rgba_etc2_zero, RgbaEtc2;
+    rgba_etc2_gamma_slice, rgba_etc2_gamma_zero, RgbaEtc2Gamma;
+    rgb_a1_etc2_slice, rgb_a1_etc2_zero, RgbA1Etc2;
+    rgb_a1_etc2_gamma_slice, rgb_a1_etc2_gamma_zero, RgbA1Etc2Gamma;
+);
+
+impl_compressed_image!(
+    Vec3,
+    rgb_etc2_slice, rgb_etc2_zero, RgbEtc2;
+    rgb_etc2_gamma_slice, rgb_etc2_gamma_zero, RgbEtc2Gamma;
+);
+
+impl_compressed_image!(
+    Vec2,
+    rg_eac_slice, rg_eac_zero, RgEac;
+    rg_eac_snorm_slice, rg_eac_snorm_zero, RgEacSnorm;
+);
+
+impl_compressed_image!(
+    F32,
+    r_eac_slice, r_eac_zero, REac;
+    r_eac_snorm_slice, r_eac_snorm_zero, REacSnorm;
+);
 
-    pub fn rg_f16_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F16,
-                internal_format: ImageInternalFormat::RgF16,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn rg_f32_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RgF32,
-                data: None,
-            },
-            _phantom: PhantomData,
-        }
-    }
+pub struct DepthImage<'a> {
+    raw: raw::Image<'a>,
 }
 
-impl<'a> ColorImage<'a, sl::F32> {
-    pub fn r_u8_slice(size: [u32; 2], data: &'a [u8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RU8,
-                data: Some(data),
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn r_i8_snorm_slice(size: [u32; 2], data: &'a [i8]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RI8Snorm,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
-        }
+impl<'a> DepthImage<'a> {
+    pub fn raw(self) -> raw::Image<'a> {
+        self.raw
     }
+}
 
-    pub fn r_f32_slice(size: [u32; 2], data: &'a [f32]) -> Self {
-        ColorImage {
+impl<'a> DepthImage<'a> {
+    pub fn f32_slice(size: [u32; 2], data: &'a [f32]) -> Self {
+        DepthImage {
             raw: raw::Image {
                 size,
                 ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RF32,
-                data: Some(bytemuck::cast_slice(data)),
-            },
-            _phantom: PhantomData,
-        }
-    }
-
-    pub fn r_u8_zero(size: [u32; 2]) -> Self {
-        ColorImage {
-            raw: raw::Image {
-                size,
-                ty: ImageComponentType::U8,
-                internal_format: ImageInternalFormat::RU8,
-                data: None,
+                internal_format: ImageInternalFormat::DepthF32,
+                data: ImageData::Slice(bytemuck::cast_slice(data)),
             },
-            _phantom: PhantomData,
         }
     }
 
-    pub fn r_i8_snorm_zero(size: [u32; 2]) -> Self {
-        ColorImage {
+    pub fn f32_zero(size: [u32; 2]) -> Self {
+        DepthImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::I8,
-                internal_format: ImageInternalFormat::RI8Snorm,
-                data: None,
+                ty: ImageComponentType::F32,
+                internal_format: ImageInternalFormat::DepthF32,
+                data: ImageData::Zeroed,
             },
-            _phantom: PhantomData,
         }
     }
 
-    pub fn r_f16_zero(size: [u32; 2]) -> Self {
-        ColorImage {
+    pub fn u16_slice(size: [u32; 2], data: &'a [u16]) -> Self {
+        DepthImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::F16,
-                internal_format: ImageInternalFormat::RF16,
-                data: None,
+                ty: ImageComponentType::U16,
+                internal_format: ImageInternalFormat::DepthU16,
+                data: ImageData::Slice(bytemuck::cast_slice(data)),
             },
-            _phantom: PhantomData,
         }
     }
 
-    pub fn r_f32_zero(size: [u32; 2]) -> Self {
-        ColorImage {
+    pub fn u16_zero(size: [u32; 2]) -> Self {
+        DepthImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::RF32,
-                data: None,
+                ty: ImageComponentType::U16,
+                internal_format: ImageInternalFormat::DepthU16,
+                data: ImageData::Zeroed,
             },
-            _phantom: PhantomData,
         }
     }
-}
-
-pub struct DepthImage<'a> {
-    raw: raw::Image<'a>,
-}
-
-impl<'a> DepthImage<'a> {
-    pub fn raw(self) -> raw::Image<'a> {
-        self.raw
-    }
-}
 
-impl<'a> DepthImage<'a> {
-    pub fn f32_slice(size: [u32; 2], data: &'a [f32]) -> Self {
+    /// Creates a 24-bit depth image from normalized 32-bit depth values, which
+    /// are converted to 24 bits.
+    pub fn u24_slice(size: [u32; 2], data: &'a [u32]) -> Self {
         DepthImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::DepthF32,
-                data: Some(bytemuck::cast_slice(data)),
+                ty: ImageComponentType::U32,
+                internal_format: ImageInternalFormat::DepthU24,
+                data: ImageData::Slice(bytemuck::cast_slice(data)),
             },
         }
     }
 
-    pub fn f32_zero(size: [u32; 2]) -> Self {
+    pub fn u24_zero(size: [u32; 2]) -> Self {
         DepthImage {
             raw: raw::Image {
                 size,
-                ty: ImageComponentType::F32,
-                internal_format: ImageInternalFormat::DepthF32,
-                data: None,
+                ty: ImageComponentType::U32,
+                internal_format: ImageInternalFormat::DepthU24,
+                data: ImageData::Zeroed,
             },
         }
     }
 }
 
 // TODO:
-// - RgbaIntFormat
-// - RgbaUnsignedIntFormat
-// - RgbIntFormat
-// - RgbUnsignedIntFormat
-// - RgIntFormat
-// - RgUnsignedIntFormat
-// - RedIntFormat
-// - RedUnsignedIntFormat
 // - DepthStencilFormat
 
 /*
@@ -470,22 +281,36 @@ RED  - UNSIGNED_BYTE - R8
 RED  - BYTE          - R8_SNORM
 RED  - FLOAT         - R32F, R16F
 
-RGBA_INTEGER - UNSIGNED_BYTE - RGBA8UI
-RGBA_INTEGER - BYTE          - RGBA8I
-...
+RGBA - UNSIGNED_INT_2_10_10_10_REV  - RGB10_A2
+RGB  - UNSIGNED_INT_10F_11F_11F_REV - R11F_G11F_B10F
+RGB  - UNSIGNED_INT_5_9_9_9_REV     - RGB9_E5
+
+RGBA_INTEGER - UNSIGNED_BYTE  - RGBA8UI
+RGBA_INTEGER - BYTE           - RGBA8I
+RGBA_INTEGER - UNSIGNED_SHORT - RGBA16UI
+RGBA_INTEGER - SHORT          - RGBA16I
+RGBA_INTEGER - UNSIGNED_INT   - RGBA32UI
+RGBA_INTEGER - INT            - RGBA32I
+RGBA_INTEGER - UNSIGNED_INT_2_10_10_10_REV - RGB10_A2UI
 
 RGB_INTEGER  - UNSIGNED_BYTE  - RGB8UI
 RGB_INTEGER  - BYTE           - RGB8I
 ...
 
 RG_INTEGER   - UNSIGNED_BYTE  - RG8UI
-RG_INTEGER   - BYTE           - RG 8I
+RG_INTEGER   - BYTE           - RG8I
 ...
 
 RED_INTEGER  - UNSIGNED_BYTE  - R8UI
 RED_INTEGER  - BYTE           - R8I
 ...
 
+COMPRESSED_RGBA8_ETC2_EAC, COMPRESSED_SRGB8_ALPHA8_ETC2_EAC      - 16 bytes per block
+COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2, COMPRESSED_SRGB8_...   - 8 bytes per block
+COMPRESSED_RGB8_ETC2, COMPRESSED_SRGB8_ETC2                      - 8 bytes per block
+COMPRESSED_RG11_EAC, COMPRESSED_SIGNED_RG11_EAC                  - 16 bytes per block
+COMPRESSED_R11_EAC, COMPRESSED_SIGNED_R11_EAC                    - 8 bytes per block
+
 DEPTH_COMPONENT - UNSIGNED_SHORT - DEPTH_COMPONENT16
 DEPTH_COMPONENT - UNSIGNED_INT   - DEPTH_COMPONENT24
 DEPTH_COMPONENT - FLOAT          - DEPTH_COMPONENT32F
@@ -493,3 +318,62 @@ DEPTH_COMPONENT - FLOAT          - DEPTH_COMPONENT32F
 DEPTH_STENCIL   - UNSIGNED_INT_24_8 - DEPTH24_STENCIL8
 
 */
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        gl::{
+            raw::{ImageComponentType, ImageData, ImageInternalFormat},
+            Gamma, Integer, Pixel, Rgb10A2, F16,
+        },
+        sl::ColorSample,
+    };
+
+    use super::ColorImage;
+
+    fn assert_round_trip<P: Pixel + PartialEq + std::fmt::Debug>(pixels: &[P])
+    where
+        P::Sample: ColorSample,
+    {
+        let image = ColorImage::from_slice([pixels.len() as u32, 1], pixels).raw();
+
+        assert_eq!(image.internal_format, P::INTERNAL_FORMAT);
+        assert_eq!(image.ty, P::TYPE);
+
+        let ImageData::Slice(data) = image.data else {
+            panic!("expected slice data");
+        };
+
+        assert_eq!(data.len(), image.required_data_len());
+        assert_eq!(bytemuck::cast_slice::<u8, P>(data), pixels);
+    }
+
+    #[test]
+    fn from_slice_round_trips() {
+        assert_round_trip(&[[1u8, 2, 3, 4], [5, 6, 7, 8]]);
+        assert_round_trip(&[Gamma([1u8, 2, 3]), Gamma([4, 5, 6])]);
+        assert_round_trip(&[[-1i8, 1]]);
+        assert_round_trip(&[[F16::from_f32(0.5); 4], [F16::from_f32(-2.0); 4]]);
+        assert_round_trip(&[[1.0f32], [2.0], [3.0]]);
+        assert_round_trip(&[Integer([u32::MAX, 0, 1, 2])]);
+        assert_round_trip(&[Integer([-1i16; 2])]);
+        assert_round_trip(&[Rgb10A2(0xc000_03ff)]);
+    }
+
+    #[test]
+    fn zeroed_has_pixel_format() {
+        let image = ColorImage::zeroed::<[F16; 4]>([4, 2]).raw();
+
+        assert!(matches!(image.data, ImageData::Zeroed));
+        assert_eq!(image.size, [4, 2]);
+        assert_eq!(image.internal_format, ImageInternalFormat::RgbaF16);
+        assert_eq!(image.ty, ImageComponentType::F16);
+        assert_eq!(image.required_data_len(), 4 * 2 * 4 * 2);
+
+        let image = ColorImage::zeroed::<Integer<[u8; 1]>>([3, 3]).raw();
+
+        assert_eq!(image.internal_format, ImageInternalFormat::RU8Integer);
+        assert_eq!(image.ty, ImageComponentType::U8);
+        assert_eq!(image.required_data_len(), 3 * 3);
+    }
+}
@@ -0,0 +1,155 @@
+//! Conversions between images of the [`image`] crate and posh images.
+
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+
+use crate::sl;
+
+use super::{
+    ColorImage, Gamma, ImageComponentType, ImageFormat, ReadbackError, ReadbackImage, TextureError,
+};
+
+/// Reverses the order of rows in `data`.
+///
+/// The `image` crate stores rows top to bottom, whereas OpenGL stores them
+/// bottom to top.
+fn flip_rows(data: &[u8], row_len: usize) -> Vec<u8> {
+    if row_len == 0 {
+        return Vec::new();
+    }
+
+    data.chunks_exact(row_len)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Converts an image into an RGBA image for uploading to a texture.
+///
+/// Images with 8-bit components are assumed to be in the sRGB color space and
+/// are converted to `SRGB8_ALPHA8`. Images with 16-bit or floating-point
+/// components are assumed to be linear and are converted to `RGBA32F`.
+///
+/// Use [`ColorImage::from_linear_dynamic_image`] for 8-bit images that do not
+/// hold colors, such as normal maps.
+impl TryFrom<&DynamicImage> for ColorImage<'static, sl::Vec4> {
+    type Error = TextureError;
+
+    fn try_from(image: &DynamicImage) -> Result<Self, Self::Error> {
+        from_dynamic_image(image, true)
+    }
+}
+
+impl ColorImage<'static, sl::Vec4> {
+    /// Converts an image into an RGBA image for uploading to a texture,
+    /// assuming that all of its components are linear.
+    ///
+    /// Unlike the [`TryFrom`] conversion, this converts images with 8-bit
+    /// components to `RGBA8`, so that they are not decoded from sRGB when
+    /// sampled.
+    pub fn from_linear_dynamic_image(image: &DynamicImage) -> Result<Self, TextureError> {
+        from_dynamic_image(image, false)
+    }
+}
+
+fn from_dynamic_image(
+    image: &DynamicImage,
+    srgb: bool,
+) -> Result<ColorImage<'static, sl::Vec4>, TextureError> {
+    use DynamicImage::*;
+
+    let size = [image.width(), image.height()];
+    let width = image.width() as usize;
+
+    match image {
+        ImageLuma8(_) | ImageLumaA8(_) | ImageRgb8(_) | ImageRgba8(_) => {
+            let image = image.to_rgba8();
+            let data = flip_rows(image.as_raw(), width * 4);
+
+            if srgb {
+                Ok(ColorImage::from_owned::<Gamma<[u8; 4]>>(size, data))
+            } else {
+                Ok(ColorImage::from_owned::<[u8; 4]>(size, data))
+            }
+        }
+        ImageLuma16(_) | ImageLumaA16(_) | ImageRgb16(_) | ImageRgba16(_) | ImageRgb32F(_)
+        | ImageRgba32F(_) => {
+            let image = image.to_rgba32f();
+            let data = flip_rows(bytemuck::cast_slice(image.as_raw()), width * 4 * 4);
+
+            Ok(ColorImage::from_owned::<[f32; 4]>(size, data))
+        }
+        _ => Err(TextureError::UnsupportedColorType(format!(
+            "{:?}",
+            image.color()
+        ))),
+    }
+}
+
+impl TryFrom<&ReadbackImage> for RgbaImage {
+    type Error = ReadbackError;
+
+    fn try_from(image: &ReadbackImage) -> Result<Self, Self::Error> {
+        if image.format != ImageFormat::Rgba || image.ty != ImageComponentType::U8 {
+            return Err(ReadbackError::UnsupportedImageFormat {
+                format: image.format,
+                ty: image.ty,
+            });
+        }
+
+        let [width, height] = image.size;
+        let data = flip_rows(&image.data, width as usize * 4);
+
+        RgbaImage::from_raw(width, height, data)
+            .ok_or_else(|| ReadbackError::Unexpected("readback data is too small".to_string()))
+    }
+}
+
+impl TryFrom<&ReadbackImage> for Rgba32FImage {
+    type Error = ReadbackError;
+
+    fn try_from(image: &ReadbackImage) -> Result<Self, Self::Error> {
+        if image.format != ImageFormat::Rgba || image.ty != ImageComponentType::F32 {
+            return Err(ReadbackError::UnsupportedImageFormat {
+                format: image.format,
+                ty: image.ty,
+            });
+        }
+
+        let [width, height] = image.size;
+        let data = flip_rows(&image.data, width as usize * 4 * 4)
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect();
+
+        Rgba32FImage::from_raw(width, height, data)
+            .ok_or_else(|| ReadbackError::Unexpected("readback data is too small".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgb32FImage, RgbaImage};
+
+    use crate::gl::{ColorImage, ImageInternalFormat};
+
+    #[test]
+    fn eight_bit_images_are_srgb_unless_linear() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+
+        let srgb = ColorImage::try_from(&image).unwrap();
+        let linear = ColorImage::from_linear_dynamic_image(&image).unwrap();
+
+        assert_eq!(srgb.raw().internal_format, ImageInternalFormat::RgbaU8Gamma);
+        assert_eq!(linear.raw().internal_format, ImageInternalFormat::RgbaU8);
+    }
+
+    #[test]
+    fn float_images_are_linear() {
+        let image = DynamicImage::ImageRgb32F(Rgb32FImage::new(2, 2));
+
+        let image = ColorImage::try_from(&image).unwrap();
+
+        assert_eq!(image.raw().internal_format, ImageInternalFormat::RgbaF32);
+    }
+}
@@ -0,0 +1,299 @@
+use bytemuck::{Pod, Zeroable};
+use sealed::sealed;
+
+use crate::sl::{self, ColorSample};
+
+use super::raw::{ImageComponentType, ImageFormat, ImageInternalFormat};
+
+/// A pixel type that can be uploaded to a color texture.
+///
+/// The pixel type determines the internal format of the texture as well as
+/// the type that is produced when sampling from it in shaders:
+///
+/// - `[u8; N]` is normalized to `[0, 1]`, and `Gamma<[u8; N]>` additionally
+///   is in the sRGB color space.
+/// - `[i8; N]` is normalized to `[-1, 1]`.
+/// - `[F16; N]` and `[f32; N]` are floating-point.
+/// - `Integer<[T; N]>` is unnormalized and must be sampled with an integer
+///   sampler.
+/// - [`Rgb10A2`], [`R11G11B10F`] and [`Rgb9E5`] store a whole pixel packed
+///   into a `u32`.
+#[sealed]
+pub trait Pixel: Pod {
+    type Sample: ColorSample;
+
+    const INTERNAL_FORMAT: ImageInternalFormat;
+    const FORMAT: ImageFormat = Self::INTERNAL_FORMAT.to_format();
+    const TYPE: ImageComponentType;
+}
+
+/// A 16-bit floating-point number.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct F16(u16);
+
+impl F16 {
+    pub const fn from_bits(bits: u16) -> Self {
+        F16(bits)
+    }
+
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Converts an `f32` to the nearest `F16`, rounding ties to even.
+    pub fn from_f32(value: f32) -> Self {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exp = ((bits >> 23) & 0xff) as i32;
+        let man = bits & 0x7f_ffff;
+
+        // Infinity and NaN.
+        if exp == 0xff {
+            let nan = if man != 0 { 0x0200 } else { 0 };
+            return F16(sign | 0x7c00 | nan);
+        }
+
+        let exp = exp - 127 + 15;
+
+        // Too large, round to infinity.
+        if exp >= 0x1f {
+            return F16(sign | 0x7c00);
+        }
+
+        // Round to nearest, ties to even. A carry from the mantissa into the
+        // exponent yields the correct result.
+        let round = |value: u32, man: u32, shift: u32| {
+            let round_bit = 1 << (shift - 1);
+
+            if man & round_bit != 0 && man & (3 * round_bit - 1) != 0 {
+                value + 1
+            } else {
+                value
+            }
+        };
+
+        if exp <= 0 {
+            // Too small, round to zero.
+            if exp < -10 {
+                return F16(sign);
+            }
+
+            // Subnormal, including the implicit leading bit.
+            let man = man | 0x80_0000;
+            let shift = (14 - exp) as u32;
+
+            return F16(sign | round(man >> shift, man, shift) as u16);
+        }
+
+        let value = ((exp as u32) << 10) | (man >> 13);
+
+        F16(sign | round(value, man, 13) as u16)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        let bits = self.0 as u32;
+        let sign = (bits & 0x8000) << 16;
+        let exp = (bits >> 10) & 0x1f;
+        let man = bits & 0x3ff;
+
+        match exp {
+            0 => {
+                // Zero or subnormal.
+                let value = man as f32 / (1 << 24) as f32;
+
+                if sign != 0 {
+                    -value
+                } else {
+                    value
+                }
+            }
+            0x1f => f32::from_bits(sign | 0x7f80_0000 | (man << 13)),
+            _ => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (man << 13)),
+        }
+    }
+}
+
+impl From<f32> for F16 {
+    fn from(value: f32) -> Self {
+        F16::from_f32(value)
+    }
+}
+
+impl From<F16> for f32 {
+    fn from(value: F16) -> Self {
+        value.to_f32()
+    }
+}
+
+/// A pixel in the sRGB color space.
+///
+/// Sampling from a texture with sRGB pixels converts them to linear space.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Gamma<P>(pub P);
+
+unsafe impl<P: Zeroable> Zeroable for Gamma<P> {}
+unsafe impl<P: Pod> Pod for Gamma<P> {}
+
+/// A pixel with unnormalized integer components.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Integer<P>(pub P);
+
+unsafe impl<P: Zeroable> Zeroable for Integer<P> {}
+unsafe impl<P: Pod> Pod for Integer<P> {}
+
+/// An RGBA pixel packed as `UNSIGNED_INT_2_10_10_10_REV`.
+///
+/// Red is stored in the lowest 10 bits, alpha in the highest 2 bits.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Rgb10A2(pub u32);
+
+/// An RGB pixel packed as `UNSIGNED_INT_10F_11F_11F_REV`.
+///
+/// Red is stored in the lowest 11 bits, blue in the highest 10 bits.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct R11G11B10F(pub u32);
+
+/// An RGB pixel packed as `UNSIGNED_INT_5_9_9_9_REV`, with a shared exponent
+/// in the highest 5 bits.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Rgb9E5(pub u32);
+
+macro_rules! impl_pixel {
+    ($pixel:ty, $sample:ident, $format:ident, $ty:ident) => {
+        #[sealed]
+        impl Pixel for $pixel {
+            type Sample = sl::$sample;
+
+            const INTERNAL_FORMAT: ImageInternalFormat = ImageInternalFormat::$format;
+            const TYPE: ImageComponentType = ImageComponentType::$ty;
+        }
+    };
+}
+
+// OpenGL ES 3.0.6: Table 3.2
+impl_pixel!([u8; 4], Vec4, RgbaU8, U8);
+impl_pixel!(Gamma<[u8; 4]>, Vec4, RgbaU8Gamma, U8);
+impl_pixel!([i8; 4], Vec4, RgbaI8Snorm, I8);
+impl_pixel!([F16; 4], Vec4, RgbaF16, F16);
+impl_pixel!([f32; 4], Vec4, RgbaF32, F32);
+impl_pixel!([u8; 3], Vec3, RgbU8, U8);
+impl_pixel!(Gamma<[u8; 3]>, Vec3, RgbU8Gamma, U8);
+impl_pixel!([i8; 3], Vec3, RgbI8Snorm, I8);
+impl_pixel!([F16; 3], Vec3, RgbF16, F16);
+impl_pixel!([f32; 3], Vec3, RgbF32, F32);
+impl_pixel!([u8; 2], Vec2, RgU8, U8);
+impl_pixel!([i8; 2], Vec2, RgI8Snorm, I8);
+impl_pixel!([F16; 2], Vec2, RgF16, F16);
+impl_pixel!([f32; 2], Vec2, RgF32, F32);
+impl_pixel!([u8; 1], F32, RU8, U8);
+impl_pixel!([i8; 1], F32, RI8Snorm, I8);
+impl_pixel!([F16; 1], F32, RF16, F16);
+impl_pixel!([f32; 1], F32, RF32, F32);
+
+impl_pixel!(Integer<[u8; 4]>, UVec4, RgbaU8Integer, U8);
+impl_pixel!(Integer<[i8; 4]>, IVec4, RgbaI8Integer, I8);
+impl_pixel!(Integer<[u16; 4]>, UVec4, RgbaU16Integer, U16);
+impl_pixel!(Integer<[i16; 4]>, IVec4, RgbaI16Integer, I16);
+impl_pixel!(Integer<[u32; 4]>, UVec4, RgbaU32Integer, U32);
+impl_pixel!(Integer<[i32; 4]>, IVec4, RgbaI32Integer, I32);
+impl_pixel!(Integer<[u8; 3]>, UVec3, RgbU8Integer, U8);
+impl_pixel!(Integer<[i8; 3]>, IVec3, RgbI8Integer, I8);
+impl_pixel!(Integer<[u16; 3]>, UVec3, RgbU16Integer, U16);
+impl_pixel!(Integer<[i16; 3]>, IVec3, RgbI16Integer, I16);
+impl_pixel!(Integer<[u32; 3]>, UVec3, RgbU32Integer, U32);
+impl_pixel!(Integer<[i32; 3]>, IVec3, RgbI32Integer, I32);
+impl_pixel!(Integer<[u8; 2]>, UVec2, RgU8Integer, U8);
+impl_pixel!(Integer<[i8; 2]>, IVec2, RgI8Integer, I8);
+impl_pixel!(Integer<[u16; 2]>, UVec2, RgU16Integer, U16);
+impl_pixel!(Integer<[i16; 2]>, IVec2, RgI16Integer, I16);
+impl_pixel!(Integer<[u32; 2]>, UVec2, RgU32Integer, U32);
+impl_pixel!(Integer<[i32; 2]>, IVec2, RgI32Integer, I32);
+impl_pixel!(Integer<[u8; 1]>, U32, RU8Integer, U8);
+impl_pixel!(Integer<[i8; 1]>, I32, RI8Integer, I8);
+impl_pixel!(Integer<[u16; 1]>, U32, RU16Integer, U16);
+impl_pixel!(Integer<[i16; 1]>, I32, RI16Integer, I16);
+impl_pixel!(Integer<[u32; 1]>, U32, RU32Integer, U32);
+impl_pixel!(Integer<[i32; 1]>, I32, RI32Integer, I32);
+
+impl_pixel!(Rgb10A2, Vec4, Rgb10A2, UInt2_10_10_10Rev);
+impl_pixel!(Integer<Rgb10A2>, UVec4, Rgb10A2Integer, UInt2_10_10_10Rev);
+impl_pixel!(R11G11B10F, Vec3, R11G11B10F, UInt10F11F11FRev);
+impl_pixel!(Rgb9E5, Vec3, Rgb9E5, UInt5_9_9_9Rev);
+
+#[cfg(test)]
+mod tests {
+    use super::F16;
+
+    #[test]
+    fn f16_from_f32_is_exact_for_representable_values() {
+        assert_eq!(F16::from_f32(0.0).to_bits(), 0x0000);
+        assert_eq!(F16::from_f32(-0.0).to_bits(), 0x8000);
+        assert_eq!(F16::from_f32(1.0).to_bits(), 0x3c00);
+        assert_eq!(F16::from_f32(-2.0).to_bits(), 0xc000);
+        assert_eq!(F16::from_f32(0.5).to_bits(), 0x3800);
+        assert_eq!(F16::from_f32(65504.0).to_bits(), 0x7bff);
+
+        // The smallest normal and subnormal numbers.
+        assert_eq!(F16::from_f32(2.0f32.powi(-14)).to_bits(), 0x0400);
+        assert_eq!(F16::from_f32(2.0f32.powi(-24)).to_bits(), 0x0001);
+    }
+
+    #[test]
+    fn f16_from_f32_rounds_to_nearest_even() {
+        // 1 + 2^-11 lies halfway between 1 and the next F16, whose mantissa
+        // is odd.
+        assert_eq!(F16::from_f32(1.0 + 2.0f32.powi(-11)).to_bits(), 0x3c00);
+
+        // 1 + 3 * 2^-11 lies halfway between two F16s, the upper one of which
+        // is even.
+        assert_eq!(
+            F16::from_f32(1.0 + 3.0 * 2.0f32.powi(-11)).to_bits(),
+            0x3c02
+        );
+
+        // Slightly above halfway rounds up.
+        assert_eq!(
+            F16::from_f32(1.0 + 2.0f32.powi(-11) + 2.0f32.powi(-20)).to_bits(),
+            0x3c01
+        );
+
+        // Rounding can carry into the exponent.
+        assert_eq!(F16::from_f32(2.0 - 2.0f32.powi(-12)).to_bits(), 0x4000);
+
+        // Halfway between zero and the smallest subnormal rounds to zero.
+        assert_eq!(F16::from_f32(2.0f32.powi(-25)).to_bits(), 0x0000);
+        assert_eq!(F16::from_f32(1.5 * 2.0f32.powi(-24)).to_bits(), 0x0002);
+    }
+
+    #[test]
+    fn f16_from_f32_handles_out_of_range_values() {
+        assert_eq!(F16::from_f32(65520.0).to_bits(), 0x7c00);
+        assert_eq!(F16::from_f32(1e10).to_bits(), 0x7c00);
+        assert_eq!(F16::from_f32(-1e10).to_bits(), 0xfc00);
+        assert_eq!(F16::from_f32(f32::INFINITY).to_bits(), 0x7c00);
+        assert_eq!(F16::from_f32(f32::NEG_INFINITY).to_bits(), 0xfc00);
+        assert_eq!(F16::from_f32(1e-10).to_bits(), 0x0000);
+        assert_eq!(F16::from_f32(-1e-10).to_bits(), 0x8000);
+
+        assert!(F16::from_f32(f32::NAN).to_f32().is_nan());
+    }
+
+    #[test]
+    fn f16_round_trips_through_f32() {
+        for bits in 0..=u16::MAX {
+            let value = F16::from_bits(bits);
+
+            if value.to_f32().is_nan() {
+                continue;
+            }
+
+            assert_eq!(F16::from_f32(value.to_f32()), value, "bits {bits:#06x}");
+        }
+    }
+}
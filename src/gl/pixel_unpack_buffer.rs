@@ -0,0 +1,58 @@
+use std::rc::Rc;
+
+use super::{raw, BufferError, BufferMapMut, BufferMapSettings, BufferUsage};
+
+/// Stores pixel data in a buffer on the GPU, from which textures can be
+/// filled.
+///
+/// Instances of `PixelUnpackBuffer` can be created with
+/// [`Context::create_pixel_unpack_buffer`](crate::gl::Context::create_pixel_unpack_buffer).
+///
+/// The buffer is used as the source of a texture upload through
+/// [`ColorImage::with_unpack_buffer`](crate::gl::ColorImage::with_unpack_buffer).
+/// Filling the buffer by mapping it and then uploading from it avoids an
+/// extra copy of the pixel data in the driver.
+pub struct PixelUnpackBuffer {
+    raw: Rc<raw::Buffer>,
+}
+
+impl PixelUnpackBuffer {
+    pub(super) fn from_raw(raw: raw::Buffer) -> Self {
+        Self { raw: Rc::new(raw) }
+    }
+
+    pub(super) fn raw(&self) -> &raw::Buffer {
+        &self.raw
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.raw.usage()
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replaces the buffer's contents, reallocating it.
+    ///
+    /// Returns an error if the buffer is mapped.
+    pub fn set(&self, data: &[u8]) -> Result<(), BufferError> {
+        self.raw.set(data)
+    }
+
+    /// Maps the whole buffer for writing, invalidating its previous
+    /// contents.
+    ///
+    /// Buffer mapping is not available in WebGL, where this returns an error.
+    pub fn map(&self) -> Result<BufferMapMut<'_, u8>, BufferError> {
+        let raw = self
+            .raw
+            .map_write(0, self.raw.len(), BufferMapSettings::default())?;
+
+        BufferMapMut::from_raw(raw)
+    }
+}
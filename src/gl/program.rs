@@ -1,4 +1,6 @@
-use std::{marker::PhantomData, rc::Rc};
+use std::{cell::RefCell, marker::PhantomData, mem, ops::Range, rc::Rc};
+
+use crevice::std140::AsStd140;
 
 use crate::{
     interface::UniformVisitor,
@@ -7,8 +9,9 @@ use crate::{
 };
 
 use super::{
-    raw, ColorSampler2d, ComparisonSampler2d, DrawError, DrawSettings, Framebuffer,
-    UniformBufferBinding, VertexSpec,
+    command_list::Command, raw, uniform_buffer::UniformRing, vertex_spec::with_vertex_path,
+    ColorSampler2d, CommandList, ComparisonSampler2d, CreateError, DrawError, DrawSettings,
+    Framebuffer, Query, UniformBufferBinding, VertexSpec,
 };
 
 pub struct DrawBuilder<U, V, F>
@@ -19,6 +22,9 @@ where
 {
     pub(crate) raw: Rc<raw::Program>,
     pub(crate) settings: DrawSettings,
+    pub(crate) query: Option<Rc<raw::Query>>,
+    pub(crate) scratch: Rc<RefCell<DrawScratch>>,
+    pub(crate) uniform_ring: Rc<RefCell<UniformRing>>,
     pub(crate) _phantom: PhantomData<(U, V, F)>,
 }
 
@@ -34,6 +40,13 @@ where
         self
     }
 
+    /// Wraps each subsequent draw call in the given query.
+    #[must_use]
+    pub fn with_query(mut self, query: &Query) -> Self {
+        self.query = Some(query.raw().clone());
+        self
+    }
+
     #[must_use]
     pub fn with_uniforms(self, uniforms: U::Gl) -> DrawBuilderWithUniforms<U, V, F> {
         DrawBuilderWithUniforms {
@@ -42,6 +55,59 @@ where
         }
     }
 
+    /// Provides the program's uniform block as a value.
+    ///
+    /// The value is written into transient storage of the context, as with
+    /// [`Context::uniform_binding_from_value`](super::Context::uniform_binding_from_value).
+    ///
+    /// This is only available for programs whose uniform interface is a single
+    /// block. For interfaces that also contain samplers or further blocks, the
+    /// bindings of blocks can be created from values with
+    /// `uniform_binding_from_value` and passed to
+    /// [`with_uniforms`](DrawBuilder::with_uniforms):
+    ///
+    /// ```ignore
+    /// #[derive(Clone, Copy, Block)]
+    /// #[repr(C)]
+    /// struct Model<D: BlockDom> {
+    ///     world: D::Mat4,
+    /// }
+    ///
+    /// #[derive(UniformInterface)]
+    /// struct Uniforms<D: UniformInterfaceDom> {
+    ///     model: D::Block<Model<Sl>>,
+    ///     albedo: D::ColorSampler2d<sl::Vec4>,
+    /// }
+    ///
+    /// program
+    ///     .with_uniforms(Uniforms {
+    ///         model: gl.uniform_binding_from_value::<Model<Gl>>(Model { world })?,
+    ///         albedo: albedo.as_color_sampler(Default::default()),
+    ///     })
+    ///     .draw(vertices.as_vertex_spec(gl::PrimitiveMode::Triangles))?;
+    /// ```
+    pub fn with_uniform_values(
+        self,
+        value: <U as Block<Sl>>::Gl,
+    ) -> Result<DrawBuilderWithUniforms<U, V, F>, DrawError>
+    where
+        U: Block<Sl, Sl = U> + UniformInterface<Sl, Gl = UniformBufferBinding<U>>,
+    {
+        let uniforms = self.uniform_binding(&value)?;
+
+        Ok(self.with_uniforms(uniforms))
+    }
+
+    fn uniform_binding<B>(
+        &self,
+        value: &impl AsStd140,
+    ) -> Result<UniformBufferBinding<B>, DrawError> {
+        self.uniform_ring
+            .borrow_mut()
+            .push(value)
+            .map_err(|error| DrawError::Create(CreateError::Buffer(error)))
+    }
+
     #[must_use]
     pub fn with_framebuffer(
         self,
@@ -69,6 +135,23 @@ where
 
         Ok(inner)
     }
+
+    /// Records the draw call into `commands` instead of executing it.
+    pub fn record(
+        self,
+        commands: &mut CommandList,
+        vertex_spec: VertexSpec<V>,
+    ) -> Result<Self, DrawError> {
+        let DrawBuilderWithUniformsAndFramebuffer { inner, .. } =
+            DrawBuilderWithUniformsAndFramebuffer {
+                inner: self,
+                uniforms: (),
+                framebuffer: Framebuffer::default(),
+            }
+            .record(commands, vertex_spec)?;
+
+        Ok(inner)
+    }
 }
 
 pub struct DrawBuilderWithUniforms<U, V, F>
@@ -93,12 +176,31 @@ where
         self
     }
 
+    /// Wraps each subsequent draw call in the given query.
+    #[must_use]
+    pub fn with_query(mut self, query: &Query) -> Self {
+        self.inner.query = Some(query.raw().clone());
+        self
+    }
+
     #[must_use]
     pub fn with_uniforms(mut self, uniforms: U::Gl) -> Self {
         self.uniforms = uniforms;
         self
     }
 
+    /// Provides the program's uniform block as a value.
+    ///
+    /// See [`DrawBuilder::with_uniform_values`].
+    pub fn with_uniform_values(self, value: <U as Block<Sl>>::Gl) -> Result<Self, DrawError>
+    where
+        U: Block<Sl, Sl = U> + UniformInterface<Sl, Gl = UniformBufferBinding<U>>,
+    {
+        let uniforms = self.inner.uniform_binding(&value)?;
+
+        Ok(self.with_uniforms(uniforms))
+    }
+
     #[must_use]
     pub fn with_framebuffer(
         self,
@@ -129,6 +231,24 @@ where
 
         Ok(Self { inner, uniforms })
     }
+
+    /// Records the draw call into `commands` instead of executing it.
+    pub fn record(
+        self,
+        commands: &mut CommandList,
+        vertex_spec: VertexSpec<V>,
+    ) -> Result<Self, DrawError> {
+        let DrawBuilderWithUniformsAndFramebuffer {
+            inner, uniforms, ..
+        } = DrawBuilderWithUniformsAndFramebuffer {
+            inner: self.inner,
+            uniforms: self.uniforms,
+            framebuffer: Framebuffer::default(),
+        }
+        .record(commands, vertex_spec)?;
+
+        Ok(Self { inner, uniforms })
+    }
 }
 
 pub struct DrawBuilderWithFramebuffer<U, V, F>
@@ -153,6 +273,13 @@ where
         self
     }
 
+    /// Wraps each subsequent draw call in the given query.
+    #[must_use]
+    pub fn with_query(mut self, query: &Query) -> Self {
+        self.inner.query = Some(query.raw().clone());
+        self
+    }
+
     #[must_use]
     pub fn with_uniforms(self, uniforms: U::Gl) -> DrawBuilderWithUniformsAndFramebuffer<U, V, F> {
         DrawBuilderWithUniformsAndFramebuffer {
@@ -162,6 +289,21 @@ where
         }
     }
 
+    /// Provides the program's uniform block as a value.
+    ///
+    /// See [`DrawBuilder::with_uniform_values`].
+    pub fn with_uniform_values(
+        self,
+        value: <U as Block<Sl>>::Gl,
+    ) -> Result<DrawBuilderWithUniformsAndFramebuffer<U, V, F>, DrawError>
+    where
+        U: Block<Sl, Sl = U> + UniformInterface<Sl, Gl = UniformBufferBinding<U>>,
+    {
+        let uniforms = self.inner.uniform_binding(&value)?;
+
+        Ok(self.with_uniforms(uniforms))
+    }
+
     #[must_use]
     pub fn with_framebuffer(mut self, framebuffer: impl Into<Framebuffer<F>>) -> Self {
         self.framebuffer = framebuffer.into();
@@ -186,6 +328,24 @@ where
 
         Ok(Self { inner, framebuffer })
     }
+
+    /// Records the draw call into `commands` instead of executing it.
+    pub fn record(
+        self,
+        commands: &mut CommandList,
+        vertex_spec: VertexSpec<V>,
+    ) -> Result<Self, DrawError> {
+        let DrawBuilderWithUniformsAndFramebuffer {
+            inner, framebuffer, ..
+        } = DrawBuilderWithUniformsAndFramebuffer {
+            inner: self.inner,
+            uniforms: (),
+            framebuffer: self.framebuffer,
+        }
+        .record(commands, vertex_spec)?;
+
+        Ok(Self { inner, framebuffer })
+    }
 }
 
 pub struct DrawBuilderWithUniformsAndFramebuffer<U, V, F>
@@ -211,12 +371,31 @@ where
         self
     }
 
+    /// Wraps each subsequent draw call in the given query.
+    #[must_use]
+    pub fn with_query(mut self, query: &Query) -> Self {
+        self.inner.query = Some(query.raw().clone());
+        self
+    }
+
     #[must_use]
     pub fn with_uniforms(mut self, uniforms: U::Gl) -> Self {
         self.uniforms = uniforms;
         self
     }
 
+    /// Provides the program's uniform block as a value.
+    ///
+    /// See [`DrawBuilder::with_uniform_values`].
+    pub fn with_uniform_values(self, value: <U as Block<Sl>>::Gl) -> Result<Self, DrawError>
+    where
+        U: Block<Sl, Sl = U> + UniformInterface<Sl, Gl = UniformBufferBinding<U>>,
+    {
+        let uniforms = self.inner.uniform_binding(&value)?;
+
+        Ok(self.with_uniforms(uniforms))
+    }
+
     #[must_use]
     pub fn with_framebuffer(mut self, framebuffer: impl Into<Framebuffer<F>>) -> Self {
         self.framebuffer = framebuffer.into();
@@ -224,30 +403,103 @@ where
     }
 
     pub fn draw(self, vertex_spec: VertexSpec<V>) -> Result<Self, DrawError> {
-        // TODO: These allocations can be avoided once stable has allocators.
+        let mut scratch = self.inner.scratch.borrow_mut();
+
+        let result = self.draw_with_scratch(&vertex_spec, &mut scratch);
+
+        // Release the bindings, so that the scratch space does not keep
+        // buffers and textures alive.
+        scratch.clear();
+        drop(scratch);
+
+        result?;
+
+        Ok(self)
+    }
+
+    /// Records the draw call into `commands` instead of executing it.
+    ///
+    /// Errors in the vertex specification are reported here, while all other
+    /// errors are reported by [`CommandList::submit`].
+    pub fn record(
+        self,
+        commands: &mut CommandList,
+        vertex_spec: VertexSpec<V>,
+    ) -> Result<Self, DrawError> {
+        let mut uniform_buffers = Vec::new();
+        let mut samplers = Vec::new();
+
         // TODO: Remove hardcoded path names.
-        let mut uniform_visitor = CollectUniforms::default();
-        self.uniforms.visit("", &mut uniform_visitor);
+        self.uniforms.visit(
+            "",
+            &mut CollectUniforms {
+                uniform_buffers: &mut uniform_buffers,
+                samplers: &mut samplers,
+            },
+        );
+
+        commands.push(Command {
+            program: self.inner.raw.clone(),
+            uniform_buffers,
+            samplers,
+            vertex_spec: vertex_spec.raw(Vec::new())?,
+            vertex_paths: vertex_spec.vertex_paths(),
+            framebuffer: self.framebuffer.raw().clone(),
+            settings: self.inner.settings,
+            query: self.inner.query.clone(),
+        });
 
-        // FIXME: Safety: check that all vertex buffers are large enough for the
-        // values in the element buffer (if we have one).
+        Ok(self)
+    }
 
-        unsafe {
+    fn draw_with_scratch(
+        &self,
+        vertex_spec: &VertexSpec<V>,
+        scratch: &mut DrawScratch,
+    ) -> Result<(), DrawError> {
+        // TODO: Remove hardcoded path names.
+        self.uniforms.visit(
+            "",
+            &mut CollectUniforms {
+                uniform_buffers: &mut scratch.uniform_buffers,
+                samplers: &mut scratch.samplers,
+            },
+        );
+
+        let mut raw_vertex_spec = vertex_spec.raw(mem::take(&mut scratch.vertex_bindings))?;
+
+        if let Some(query) = &self.inner.query {
+            query.begin().map_err(DrawError::Query)?;
+        }
+
+        let result = unsafe {
             self.inner.raw.draw(
-                &uniform_visitor.raw_uniform_buffers,
-                &uniform_visitor.raw_samplers,
-                &vertex_spec.raw(),
-                &self.framebuffer.raw(),
+                &scratch.uniform_buffers,
+                &scratch.samplers,
+                &raw_vertex_spec,
+                self.framebuffer.raw(),
                 &self.inner.settings,
             )
-        }?;
+        };
 
-        Ok(self)
+        scratch.vertex_bindings = mem::take(&mut raw_vertex_spec.vertex_data);
+
+        // The query needs to be ended even if drawing failed, so that it does
+        // not stay active.
+        if let Some(query) = &self.inner.query {
+            query.end().map_err(DrawError::Query)?;
+        }
+
+        // Vertex buffer bindings are named by their paths only on failure, so
+        // that successful draw calls do not allocate.
+        result.map_err(|error| with_vertex_path(error, &vertex_spec.vertex_paths()))
     }
 }
 
 pub struct Program<U, V, F = sl::Vec4> {
     raw: Rc<raw::Program>,
+    scratch: Rc<RefCell<DrawScratch>>,
+    uniform_ring: Rc<RefCell<UniformRing>>,
     _phantom: PhantomData<(U, V, F)>,
 }
 
@@ -257,22 +509,34 @@ where
     V: VsInterface<Sl>,
     F: FsInterface<Sl>,
 {
-    pub(super) fn unchecked_from_raw(raw: Rc<raw::Program>) -> Self {
+    pub(super) fn unchecked_from_raw(
+        raw: Rc<raw::Program>,
+        uniform_ring: Rc<RefCell<UniformRing>>,
+    ) -> Self {
+        Self::unchecked_from_parts(raw, Default::default(), uniform_ring)
+    }
+
+    pub(super) fn unchecked_from_parts(
+        raw: Rc<raw::Program>,
+        scratch: Rc<RefCell<DrawScratch>>,
+        uniform_ring: Rc<RefCell<UniformRing>>,
+    ) -> Self {
         Program {
             raw,
+            scratch,
+            uniform_ring,
             _phantom: PhantomData,
         }
     }
 
-    pub(super) fn raw(&self) -> &Rc<raw::Program> {
-        &self.raw
-    }
-
     #[must_use]
     pub fn with_settings(&self, settings: DrawSettings) -> DrawBuilder<U, V, F> {
         DrawBuilder {
             raw: self.raw.clone(),
             settings,
+            query: None,
+            scratch: self.scratch.clone(),
+            uniform_ring: self.uniform_ring.clone(),
             _phantom: PhantomData,
         }
     }
@@ -285,6 +549,20 @@ where
         }
     }
 
+    /// Provides the program's uniform block as a value.
+    ///
+    /// See [`DrawBuilder::with_uniform_values`].
+    pub fn with_uniform_values(
+        &self,
+        value: <U as Block<Sl>>::Gl,
+    ) -> Result<DrawBuilderWithUniforms<U, V, F>, DrawError>
+    where
+        U: Block<Sl, Sl = U> + UniformInterface<Sl, Gl = UniformBufferBinding<U>>,
+    {
+        self.with_settings(DrawSettings::default())
+            .with_uniform_values(value)
+    }
+
     #[must_use]
     pub fn with_framebuffer(
         &self,
@@ -308,30 +586,79 @@ where
         self.with_settings(DrawSettings::default())
             .draw(vertex_spec)
     }
+
+    /// Records the draw call into `commands` instead of executing it.
+    pub fn record(
+        &self,
+        commands: &mut CommandList,
+        vertex_spec: VertexSpec<V>,
+    ) -> Result<DrawBuilder<(), V, sl::Vec4>, DrawError> {
+        self.with_settings(DrawSettings::default())
+            .record(commands, vertex_spec)
+    }
 }
 
+/// Reusable storage for the raw bindings of draw calls.
+///
+/// The storage is kept by programs across draw calls, so that draw calls do not
+/// allocate once the vectors have grown to their steady-state size.
 #[derive(Default)]
-struct CollectUniforms<'a> {
-    raw_uniform_buffers: Vec<&'a raw::Buffer>,
-    raw_samplers: Vec<raw::Sampler>,
+pub(crate) struct DrawScratch {
+    uniform_buffers: Vec<Option<(Rc<raw::Buffer>, Range<usize>)>>,
+    samplers: Vec<raw::Sampler>,
+    vertex_bindings: Vec<raw::VertexBufferBinding>,
 }
 
-impl<'a> UniformVisitor<'a, Gl> for CollectUniforms<'a> {
+impl DrawScratch {
+    fn clear(&mut self) {
+        self.uniform_buffers.clear();
+        self.samplers.clear();
+        self.vertex_bindings.clear();
+    }
+}
+
+struct CollectUniforms<'s> {
+    uniform_buffers: &'s mut Vec<Option<(Rc<raw::Buffer>, Range<usize>)>>,
+    samplers: &'s mut Vec<raw::Sampler>,
+}
+
+impl<'a, 's> UniformVisitor<'a, Gl> for CollectUniforms<'s> {
     fn accept_block<B: Block<Sl, Sl = B>>(
         &mut self,
         _: &str,
+        _: Option<usize>,
         uniform: &'a UniformBufferBinding<B>,
     ) {
-        self.raw_uniform_buffers.push(uniform.raw());
+        // Global bindings are given as `None`, just like global block fields.
+        self.uniform_buffers
+            .push(uniform.shared_raw().map(|raw| (raw, uniform.range())));
     }
 
-    fn accept_color_sampler_2d<S: ColorSample>(&mut self, _: &str, sampler: &ColorSampler2d<S>) {
-        self.raw_samplers
+    fn accept_color_sampler_2d<S: ColorSample>(
+        &mut self,
+        _: &str,
+        _: Option<usize>,
+        sampler: &ColorSampler2d<S>,
+    ) {
+        self.samplers
             .push(raw::Sampler::Sampler2d(sampler.raw().clone()))
     }
 
-    fn accept_comparison_sampler_2d(&mut self, _: &str, sampler: &ComparisonSampler2d) {
-        self.raw_samplers
+    fn accept_comparison_sampler_2d(
+        &mut self,
+        _: &str,
+        _: Option<usize>,
+        sampler: &ComparisonSampler2d,
+    ) {
+        self.samplers
             .push(raw::Sampler::Sampler2d(sampler.raw().clone()))
     }
+
+    fn accept_global_block<B: Block<Sl, Sl = B>>(&mut self, _: &str) {
+        self.uniform_buffers.push(None);
+    }
+
+    fn needs_paths(&self) -> bool {
+        false
+    }
 }
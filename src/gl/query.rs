@@ -0,0 +1,48 @@
+use std::rc::Rc;
+
+use super::{raw, QueryError, QueryResult, QueryType};
+
+/// A query object for measuring occlusion or GPU time.
+///
+/// Instances of `Query` can be created with
+/// [`Context::create_query`](crate::gl::Context::create_query).
+///
+/// A query can either wrap a single draw call through
+/// [`DrawBuilder::with_query`](crate::gl::DrawBuilder::with_query), or it can
+/// wrap a sequence of draw calls with [`Query::begin`] and [`Query::end`].
+/// Results are retrieved without stalling the pipeline with [`Query::poll`],
+/// which typically means that they become available a few frames later.
+#[derive(Clone)]
+pub struct Query {
+    raw: Rc<raw::Query>,
+}
+
+impl Query {
+    pub(super) fn from_raw(raw: raw::Query) -> Self {
+        Self { raw: Rc::new(raw) }
+    }
+
+    pub(super) fn raw(&self) -> &Rc<raw::Query> {
+        &self.raw
+    }
+
+    pub fn ty(&self) -> QueryType {
+        self.raw.ty()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.raw.is_active()
+    }
+
+    pub fn begin(&self) -> Result<(), QueryError> {
+        self.raw.begin()
+    }
+
+    pub fn end(&self) -> Result<(), QueryError> {
+        self.raw.end()
+    }
+
+    pub fn poll(&self) -> Option<QueryResult> {
+        self.raw.poll()
+    }
+}
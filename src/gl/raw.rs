@@ -1,26 +1,37 @@
+mod bindings;
 mod buffer;
 mod caps;
 mod context;
+mod element_indices;
 mod error;
+mod fence;
 mod framebuffer;
 mod image;
 mod program;
+mod query;
+mod readback;
 mod sampler_settings;
 mod settings;
 mod texture;
+mod vertex_array;
 mod vertex_layout;
 mod vertex_spec;
 
-pub use self::image::{Image, ImageComponentType, ImageFormat, ImageInternalFormat};
-pub use buffer::{Buffer, BufferUsage};
-pub use caps::Caps;
+pub use self::image::{Image, ImageComponentType, ImageData, ImageFormat, ImageInternalFormat};
+pub use buffer::{Buffer, BufferMap, BufferMapSettings, BufferUsage};
+pub use caps::{Caps, GlVersion};
 pub use context::Context;
+pub use element_indices::ElementIndices;
 pub use error::{
-    BufferError, ContextError, CreateError, DrawError, FramebufferError, ProgramError,
-    ProgramValidationError, TextureError, VertexArrayError,
+    BufferError, ContextError, CreateError, DrawError, FenceError, FramebufferError, GraphError,
+    ProgramError, ProgramValidationError, QueryError, ReadbackError, TextureError,
+    VertexArrayError,
 };
-pub use framebuffer::{Attachment, Framebuffer};
+pub use fence::Fence;
+pub use framebuffer::{Attachment, AttachmentList, Framebuffer};
 pub use program::Program;
+pub use query::{Query, QueryResult, QueryType};
+pub use readback::{PixelReadback, ReadbackImage};
 pub use sampler_settings::{Sampler2dSettings, SamplerMagFilter, SamplerMinFilter, SamplerWrap};
 pub use settings::{
     BlendEquation, BlendFunc, Blending, Comparison, CullFace, DrawSettings, Rect, StencilOp,
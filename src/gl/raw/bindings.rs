@@ -0,0 +1,244 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    ops::Range,
+};
+
+use glow::HasContext;
+
+use super::framebuffer::MAX_ATTACHMENTS;
+
+/// A texture attached to a framebuffer attachment point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct AttachmentBinding {
+    pub location: u32,
+    pub texture: glow::Texture,
+    pub level: i32,
+}
+
+/// Shadow state of the object bindings of a context.
+///
+/// Bindings are only changed in GL if they differ from the shadow state, so
+/// that consecutive draw calls with the same program, textures, uniform
+/// buffers or framebuffer do not rebind them. All binding changes of these
+/// kinds must go through this struct.
+///
+/// Objects must be forgotten before they are deleted, since their names may be
+/// reused for new objects afterwards.
+pub(super) struct Bindings {
+    draw_fbo: glow::Framebuffer,
+    draw_fbo_bound: Cell<bool>,
+    draw_fbo_attachments: RefCell<Vec<AttachmentBinding>>,
+    program: Cell<Option<glow::Program>>,
+    active_texture: Cell<u32>,
+    textures_2d: RefCell<Vec<Option<glow::Texture>>>,
+    uniform_buffers: RefCell<BTreeMap<u32, (glow::Buffer, Range<usize>)>>,
+}
+
+impl Bindings {
+    /// Creates the shadow state for a fresh context, in which nothing is
+    /// bound.
+    pub fn new(draw_fbo: glow::Framebuffer) -> Self {
+        Self {
+            draw_fbo,
+            draw_fbo_bound: Cell::new(false),
+            draw_fbo_attachments: Default::default(),
+            program: Cell::new(None),
+            active_texture: Cell::new(glow::TEXTURE0),
+            textures_2d: Default::default(),
+            uniform_buffers: Default::default(),
+        }
+    }
+
+    pub fn use_program(&self, gl: &glow::Context, program: Option<glow::Program>) {
+        if self.program.get() != program {
+            unsafe { gl.use_program(program) };
+            self.program.set(program);
+        }
+    }
+
+    /// Binds `texture` to `TEXTURE_2D` of the texture unit `unit`, which is
+    /// given as `TEXTURE0 + i`.
+    ///
+    /// This also makes `unit` the active texture unit.
+    pub fn bind_texture_2d(&self, gl: &glow::Context, unit: u32, texture: Option<glow::Texture>) {
+        if self.active_texture.get() != unit {
+            unsafe { gl.active_texture(unit) };
+            self.active_texture.set(unit);
+        }
+
+        let index = usize::try_from(unit - glow::TEXTURE0).unwrap();
+        let mut textures_2d = self.textures_2d.borrow_mut();
+
+        if textures_2d.len() <= index {
+            textures_2d.resize(index + 1, None);
+        }
+
+        if textures_2d[index] != texture {
+            unsafe { gl.bind_texture(glow::TEXTURE_2D, texture) };
+            textures_2d[index] = texture;
+        }
+    }
+
+    /// Binds `texture` to `TEXTURE_2D` of the active texture unit, so that it
+    /// can be modified.
+    pub fn bind_texture_2d_for_update(&self, gl: &glow::Context, texture: glow::Texture) {
+        self.bind_texture_2d(gl, self.active_texture.get(), Some(texture));
+    }
+
+    pub fn bind_uniform_buffer_range(
+        &self,
+        gl: &glow::Context,
+        location: u32,
+        buffer: glow::Buffer,
+        range: Range<usize>,
+    ) {
+        let mut uniform_buffers = self.uniform_buffers.borrow_mut();
+        let binding = (buffer, range.clone());
+
+        if uniform_buffers.get(&location) == Some(&binding) {
+            return;
+        }
+
+        let offset = i32::try_from(range.start).unwrap();
+        let size = i32::try_from(range.end - range.start).unwrap();
+
+        unsafe {
+            gl.bind_buffer_range(glow::UNIFORM_BUFFER, location, Some(buffer), offset, size);
+        }
+
+        uniform_buffers.insert(location, binding);
+    }
+
+    pub fn bind_default_framebuffer(&self, gl: &glow::Context) {
+        if self.draw_fbo_bound.get() {
+            unsafe { gl.bind_framebuffer(glow::FRAMEBUFFER, None) };
+            self.draw_fbo_bound.set(false);
+        }
+    }
+
+    /// Binds the context's framebuffer object with the given attachments.
+    ///
+    /// Returns `true` if the attachments were changed, in which case the
+    /// caller is responsible for setting the draw buffers.
+    pub fn bind_draw_framebuffer(
+        &self,
+        gl: &glow::Context,
+        attachments: impl Iterator<Item = AttachmentBinding> + Clone,
+    ) -> bool {
+        if !self.draw_fbo_bound.get() {
+            unsafe { gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.draw_fbo)) };
+            self.draw_fbo_bound.set(true);
+        }
+
+        let mut current = self.draw_fbo_attachments.borrow_mut();
+
+        if current.iter().copied().eq(attachments.clone()) {
+            return false;
+        }
+
+        for old in current.iter() {
+            if !attachments.clone().any(|new| new.location == old.location) {
+                unsafe {
+                    gl.framebuffer_texture_2d(
+                        glow::FRAMEBUFFER,
+                        old.location,
+                        glow::TEXTURE_2D,
+                        None,
+                        0,
+                    )
+                };
+            }
+        }
+
+        for new in attachments.clone() {
+            if !current.contains(&new) {
+                unsafe {
+                    gl.framebuffer_texture_2d(
+                        glow::FRAMEBUFFER,
+                        new.location,
+                        glow::TEXTURE_2D,
+                        Some(new.texture),
+                        new.level,
+                    )
+                };
+            }
+        }
+
+        current.clear();
+        current.extend(attachments);
+
+        true
+    }
+
+    /// Sets the draw buffers of the context's framebuffer object to its color
+    /// attachments.
+    ///
+    /// This must be called after [`Bindings::bind_draw_framebuffer`] has
+    /// changed the attachments.
+    pub fn set_draw_buffers(&self, gl: &glow::Context) {
+        let mut draw_buffers = [glow::NONE; MAX_ATTACHMENTS];
+        let mut num_draw_buffers = 0;
+
+        // OpenGL ES 3.0.6: 4.2.1 Selecting Buffers for Writing
+        // > If the GL is bound to a draw framebuffer object, the ith buffer
+        // > listed in `bufs` must be COLOR_ATTACHMENTi or NONE.
+        for attachment in self.draw_fbo_attachments.borrow().iter() {
+            let index = attachment.location.wrapping_sub(glow::COLOR_ATTACHMENT0) as usize;
+
+            if index < MAX_ATTACHMENTS {
+                draw_buffers[index] = attachment.location;
+                num_draw_buffers = num_draw_buffers.max(index + 1);
+            }
+        }
+
+        unsafe { gl.draw_buffers(&draw_buffers[..num_draw_buffers]) };
+    }
+
+    pub fn forget_program(&self, gl: &glow::Context, program: glow::Program) {
+        // A program object that is in use is only flagged for deletion, so we
+        // stop using it before it is deleted.
+        if self.program.get() == Some(program) {
+            self.use_program(gl, None);
+        }
+    }
+
+    pub fn forget_texture(&self, gl: &glow::Context, texture: glow::Texture) {
+        // OpenGL ES 3.0.6: 3.8.1 Texture Objects
+        // Deleting a texture resets the bindings of all texture units that it
+        // is bound to.
+        for binding in self.textures_2d.borrow_mut().iter_mut() {
+            if *binding == Some(texture) {
+                *binding = None;
+            }
+        }
+
+        // Deleting a texture only detaches it from the currently bound
+        // framebuffer object, so we detach it explicitly in case the context's
+        // framebuffer object is not bound.
+        let mut remaining = [None; MAX_ATTACHMENTS];
+        let mut num_remaining = 0;
+        let mut detached = false;
+
+        for &attachment in self.draw_fbo_attachments.borrow().iter() {
+            if attachment.texture == texture {
+                detached = true;
+            } else {
+                remaining[num_remaining] = Some(attachment);
+                num_remaining += 1;
+            }
+        }
+
+        if detached && self.bind_draw_framebuffer(gl, remaining.into_iter().flatten()) {
+            self.set_draw_buffers(gl);
+        }
+    }
+
+    pub fn forget_buffer(&self, buffer: glow::Buffer) {
+        // OpenGL ES 3.0.6: 2.10.1 Creating and Binding Buffer Objects
+        // Deleting a buffer resets all bindings to it in the current context.
+        self.uniform_buffers
+            .borrow_mut()
+            .retain(|_, (bound, _)| *bound != buffer);
+    }
+}
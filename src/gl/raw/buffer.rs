@@ -1,4 +1,8 @@
-use std::{cell::Cell, rc::Rc};
+use std::{
+    cell::Cell,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
 
 use glow::HasContext;
 
@@ -29,12 +33,84 @@ impl BufferUsage {
     }
 }
 
+/// Options for mapping a buffer range for writing.
+///
+/// See OpenGL ES 3.0.6: 2.10.3 Mapping and Unmapping Buffer Data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferMapSettings {
+    /// Discard the previous contents of the mapped range.
+    pub invalidate_range: bool,
+
+    /// Discard the previous contents of the whole buffer.
+    pub invalidate_buffer: bool,
+
+    /// Do not wait for pending GPU operations on the buffer. Writing to a range
+    /// that is still used by the GPU results in undefined contents.
+    pub unsynchronized: bool,
+
+    /// Only make modifications visible to the GPU when they are flushed
+    /// explicitly with [`BufferMap::flush`].
+    pub explicit_flush: bool,
+}
+
+impl Default for BufferMapSettings {
+    fn default() -> Self {
+        Self {
+            invalidate_range: true,
+            invalidate_buffer: false,
+            unsynchronized: false,
+            explicit_flush: false,
+        }
+    }
+}
+
+impl BufferMapSettings {
+    pub const fn to_gl_access(self) -> u32 {
+        let mut access = glow::MAP_WRITE_BIT;
+
+        if self.invalidate_range {
+            access |= glow::MAP_INVALIDATE_RANGE_BIT;
+        }
+
+        if self.invalidate_buffer {
+            access |= glow::MAP_INVALIDATE_BUFFER_BIT;
+        }
+
+        if self.unsynchronized {
+            access |= glow::MAP_UNSYNCHRONIZED_BIT;
+        }
+
+        if self.explicit_flush {
+            access |= glow::MAP_FLUSH_EXPLICIT_BIT;
+        }
+
+        access
+    }
+}
+
 pub struct Buffer {
     ctx: Rc<ContextShared>,
     id: glow::Buffer,
     target: u32,
     usage: BufferUsage,
     len: Cell<usize>,
+    mapped: Cell<bool>,
+}
+
+/// A mapping of a range of a [`Buffer`] into CPU memory.
+///
+/// The buffer is unmapped when the mapping is dropped.
+pub struct BufferMap<'a> {
+    buffer: &'a Buffer,
+    ptr: *mut u8,
+    offset: usize,
+    len: usize,
+    explicit_flush: bool,
+
+    /// Whether this mapping has mapped the buffer. Empty mappings do not map
+    /// the buffer, so they must not unmap it either, since another mapping
+    /// may have been created in the meantime.
+    unmap_on_drop: bool,
 }
 
 impl Buffer {
@@ -53,11 +129,45 @@ impl Buffer {
             target,
             usage,
             len: Cell::new(0),
+            mapped: Cell::new(false),
         };
 
-        buffer.set(data);
+        buffer.set(data)?;
 
-        check_gl_error(gl, "after new buffer").map_err(BufferError::Unexpected)?;
+        check_gl_error(gl, "after new buffer")?;
+
+        Ok(buffer)
+    }
+
+    /// Creates a buffer with `len` bytes of uninitialized storage.
+    pub(super) fn new_with_len(
+        ctx: Rc<ContextShared>,
+        len: usize,
+        target: u32,
+        usage: BufferUsage,
+    ) -> Result<Self, BufferError> {
+        let gl = ctx.gl();
+        let id = unsafe { gl.create_buffer() }.map_err(BufferError::ObjectCreation)?;
+
+        let buffer = Buffer {
+            ctx: ctx.clone(),
+            id,
+            target,
+            usage,
+            len: Cell::new(len),
+            mapped: Cell::new(false),
+        };
+
+        let size = len.try_into().expect("buffer length is out of i32 range");
+
+        buffer.bind(gl);
+
+        unsafe {
+            gl.buffer_data_size(target, size, usage.to_gl());
+            gl.bind_buffer(target, None);
+        }
+
+        check_gl_error(gl, "after new buffer with length")?;
 
         Ok(buffer)
     }
@@ -66,6 +176,19 @@ impl Buffer {
         &self.ctx
     }
 
+    /// Binds the buffer to its target.
+    fn bind(&self, gl: &glow::Context) {
+        // OpenGL ES 3.0.6: 2.10 Vertex Array Objects
+        // The element array buffer binding is part of the vertex array object
+        // state, so binding it would modify the vertex array object that was
+        // bound by the last draw call.
+        if self.target == glow::ELEMENT_ARRAY_BUFFER {
+            self.ctx.vertex_arrays().unbind(gl);
+        }
+
+        unsafe { gl.bind_buffer(self.target, Some(self.id)) };
+    }
+
     pub fn id(&self) -> glow::Buffer {
         self.id
     }
@@ -82,11 +205,26 @@ impl Buffer {
         self.len() != 0
     }
 
-    pub fn set(&self, data: &[u8]) {
+    pub fn is_mapped(&self) -> bool {
+        self.mapped.get()
+    }
+
+    /// Replaces the buffer's contents with `data`, reallocating the buffer.
+    pub fn set(&self, data: &[u8]) -> Result<(), BufferError> {
+        // OpenGL ES 3.0.6: 2.10.3 Mapping and Unmapping Buffer Data
+        // > If the buffer object is in a mapped state, the mapping is
+        // > implicitly unmapped by `BufferData`.
+        //
+        // This would leave existing mappings dangling.
+        if self.mapped.get() {
+            return Err(BufferError::Mapping("buffer is mapped".to_string()));
+        }
+
         let gl = self.ctx.gl();
 
+        self.bind(gl);
+
         unsafe {
-            gl.bind_buffer(self.target, Some(self.id));
             gl.buffer_data_u8_slice(self.target, data, self.usage.to_gl());
 
             // TODO: Could avoid unbinding here by using `ContextShared`.
@@ -96,7 +234,368 @@ impl Buffer {
         self.len.set(data.len());
 
         #[cfg(debug_assertions)]
-        check_gl_error(gl, "after buffer set").expect("OpenGL error after Buffer::set");
+        check_gl_error(gl, "after buffer set")?;
+
+        Ok(())
+    }
+
+    /// Overwrites the buffer's contents starting at `offset` with `data`,
+    /// without reallocating the buffer.
+    pub fn set_range(&self, offset: usize, data: &[u8]) -> Result<(), BufferError> {
+        let in_bounds = offset
+            .checked_add(data.len())
+            .is_some_and(|end| end <= self.len());
+
+        if !in_bounds {
+            return Err(BufferError::OutOfBounds {
+                offset,
+                len: data.len(),
+                buffer_len: self.len(),
+            });
+        }
+
+        if self.mapped.get() {
+            return Err(BufferError::Mapping("buffer is mapped".to_string()));
+        }
+
+        let gl = self.ctx.gl();
+        let offset = offset
+            .try_into()
+            .expect("buffer offset is out of i32 range");
+
+        self.bind(gl);
+
+        unsafe {
+            gl.buffer_sub_data_u8_slice(self.target, offset, data);
+            gl.bind_buffer(self.target, None);
+        }
+
+        #[cfg(debug_assertions)]
+        check_gl_error(gl, "after buffer set_range")?;
+
+        Ok(())
+    }
+
+    /// Reallocates the buffer's storage without changing its length.
+    ///
+    /// The previous contents become undefined. Draw calls that are still
+    /// pending on the GPU keep using the old storage, so writing to the buffer
+    /// afterwards does not need to wait for them.
+    pub fn orphan(&self) -> Result<(), BufferError> {
+        if self.mapped.get() {
+            return Err(BufferError::Mapping("buffer is mapped".to_string()));
+        }
+
+        let gl = self.ctx.gl();
+        let size = self
+            .len()
+            .try_into()
+            .expect("buffer length is out of i32 range");
+
+        self.bind(gl);
+
+        unsafe {
+            gl.buffer_data_size(self.target, size, self.usage.to_gl());
+            gl.bind_buffer(self.target, None);
+        }
+
+        #[cfg(debug_assertions)]
+        check_gl_error(gl, "after buffer orphan")?;
+
+        Ok(())
+    }
+
+    /// Creates a buffer with `len` bytes of storage and the same target and
+    /// usage as this buffer, copying the first `keep` bytes of this buffer
+    /// into it.
+    pub fn resized(&self, len: usize, keep: usize) -> Result<Buffer, BufferError> {
+        if keep > self.len().min(len) {
+            return Err(BufferError::OutOfBounds {
+                offset: 0,
+                len: keep,
+                buffer_len: self.len().min(len),
+            });
+        }
+
+        if self.mapped.get() {
+            return Err(BufferError::Mapping("buffer is mapped".to_string()));
+        }
+
+        let buffer = Buffer::new_with_len(self.ctx.clone(), len, self.target, self.usage)?;
+
+        if keep == 0 {
+            return Ok(buffer);
+        }
+
+        let gl = self.ctx.gl();
+        let size = keep.try_into().expect("buffer length is out of i32 range");
+
+        // OpenGL ES 3.0.6: 2.10.5 Copying Between Buffers
+        unsafe {
+            gl.bind_buffer(glow::COPY_READ_BUFFER, Some(self.id));
+            gl.bind_buffer(glow::COPY_WRITE_BUFFER, Some(buffer.id));
+            gl.copy_buffer_sub_data(glow::COPY_READ_BUFFER, glow::COPY_WRITE_BUFFER, 0, 0, size);
+            gl.bind_buffer(glow::COPY_READ_BUFFER, None);
+            gl.bind_buffer(glow::COPY_WRITE_BUFFER, None);
+        }
+
+        check_gl_error(gl, "after buffer copy")?;
+
+        Ok(buffer)
+    }
+
+    /// Copies the buffer's contents starting at `offset` into `data`.
+    ///
+    /// This blocks until the GPU has finished writing to the buffer.
+    pub fn read(&self, offset: usize, data: &mut [u8]) -> Result<(), BufferError> {
+        let in_bounds = offset
+            .checked_add(data.len())
+            .is_some_and(|end| end <= self.len());
+
+        if !in_bounds {
+            return Err(BufferError::OutOfBounds {
+                offset,
+                len: data.len(),
+                buffer_len: self.len(),
+            });
+        }
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        if self.mapped.get() {
+            return Err(BufferError::Mapping("buffer is already mapped".to_string()));
+        }
+
+        // OpenGL ES 3.0 does not have `GetBufferSubData`, so we need to go
+        // through buffer mapping there. WebGL 2, on the other hand, does not
+        // support buffer mapping, but it has `getBufferSubData`.
+        #[cfg(target_family = "wasm")]
+        {
+            let gl = self.ctx.gl();
+            let offset = offset
+                .try_into()
+                .expect("buffer offset is out of i32 range");
+
+            self.bind(gl);
+
+            unsafe {
+                gl.get_buffer_sub_data(self.target, offset, data);
+                gl.bind_buffer(self.target, None);
+            }
+        }
+
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let map = self.map_range(offset, data.len(), glow::MAP_READ_BIT)?;
+
+            data.copy_from_slice(&map);
+        }
+
+        Ok(())
+    }
+
+    /// Maps `len` bytes starting at `offset` for writing.
+    ///
+    /// Buffer mapping is not available in WebGL.
+    pub fn map_write(
+        &self,
+        offset: usize,
+        len: usize,
+        settings: BufferMapSettings,
+    ) -> Result<BufferMap<'_>, BufferError> {
+        self.check_map(offset, len)?;
+        self.map_range(offset, len, settings.to_gl_access())
+    }
+
+    /// Maps `len` bytes starting at `offset` for reading.
+    ///
+    /// This blocks until the GPU has finished writing to the buffer. Buffer
+    /// mapping is not available in WebGL.
+    pub fn map_read(&self, offset: usize, len: usize) -> Result<BufferMap<'_>, BufferError> {
+        self.check_map(offset, len)?;
+        self.map_range(offset, len, glow::MAP_READ_BIT)
+    }
+
+    fn check_map(&self, offset: usize, len: usize) -> Result<(), BufferError> {
+        let in_bounds = offset.checked_add(len).is_some_and(|end| end <= self.len());
+
+        if !in_bounds {
+            return Err(BufferError::OutOfBounds {
+                offset,
+                len,
+                buffer_len: self.len(),
+            });
+        }
+
+        if self.mapped.get() {
+            return Err(BufferError::Mapping("buffer is already mapped".to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn map_range(&self, _: usize, _: usize, _: u32) -> Result<BufferMap<'_>, BufferError> {
+        Err(BufferError::Mapping(
+            "buffer mapping is not supported in WebGL".to_string(),
+        ))
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn map_range(
+        &self,
+        offset: usize,
+        len: usize,
+        access: u32,
+    ) -> Result<BufferMap<'_>, BufferError> {
+        let gl = self.ctx.gl();
+
+        // OpenGL ES 3.0.6: 2.10.3 Mapping and Unmapping Buffer Data
+        // > An INVALID_VALUE error is generated if [...] `length` is zero.
+        //
+        // We allow empty mappings by not mapping at all.
+        if len == 0 {
+            return Ok(BufferMap {
+                buffer: self,
+                ptr: std::ptr::NonNull::dangling().as_ptr(),
+                offset,
+                len,
+                explicit_flush: false,
+                unmap_on_drop: false,
+            });
+        }
+
+        self.bind(gl);
+
+        let ptr = unsafe {
+            gl.map_buffer_range(
+                self.target,
+                offset
+                    .try_into()
+                    .expect("buffer offset is out of i32 range"),
+                len.try_into().expect("mapping length is out of i32 range"),
+                access,
+            )
+        };
+
+        if ptr.is_null() {
+            let error = check_gl_error(gl, "after buffer map").err().map_or_else(
+                || "map_buffer_range returned null".to_string(),
+                |error| error.to_string(),
+            );
+
+            unsafe {
+                gl.bind_buffer(self.target, None);
+            }
+
+            return Err(BufferError::Mapping(error));
+        }
+
+        self.mapped.set(true);
+
+        Ok(BufferMap {
+            buffer: self,
+            ptr,
+            offset,
+            len,
+            explicit_flush: access & glow::MAP_FLUSH_EXPLICIT_BIT != 0,
+            unmap_on_drop: true,
+        })
+    }
+}
+
+impl<'a> BufferMap<'a> {
+    /// Returns the offset of the mapping in the buffer, in bytes.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Makes modifications to `len` bytes starting at `offset`, relative to
+    /// the start of the mapping, visible to the GPU.
+    ///
+    /// This requires the mapping to have been created with
+    /// [`BufferMapSettings::explicit_flush`].
+    pub fn flush(&self, offset: usize, len: usize) -> Result<(), BufferError> {
+        let in_bounds = offset.checked_add(len).is_some_and(|end| end <= self.len);
+
+        if !in_bounds {
+            return Err(BufferError::OutOfBounds {
+                offset,
+                len,
+                buffer_len: self.len,
+            });
+        }
+
+        if !self.unmap_on_drop {
+            // Empty mappings do not map the buffer.
+            return Ok(());
+        }
+
+        // OpenGL ES 3.0.6: 2.10.3 Mapping and Unmapping Buffer Data
+        // > An INVALID_OPERATION error is generated if the buffer bound to
+        // > `target` is not mapped, or is mapped without the
+        // > MAP_FLUSH_EXPLICIT_BIT flag.
+        if !self.explicit_flush {
+            return Err(BufferError::Mapping(
+                "buffer is not mapped with explicit flushing".to_string(),
+            ));
+        }
+
+        let gl = self.buffer.ctx.gl();
+        let target = self.buffer.target;
+
+        self.buffer.bind(gl);
+
+        unsafe {
+            gl.flush_mapped_buffer_range(
+                target,
+                offset.try_into().expect("flush offset is out of i32 range"),
+                len.try_into().expect("flush length is out of i32 range"),
+            );
+            gl.bind_buffer(target, None);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Deref for BufferMap<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // Safety: `ptr` points to `len` mapped bytes, which stay valid until
+        // the buffer is unmapped in `drop`.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a> DerefMut for BufferMap<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // Safety: see `deref`.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<'a> Drop for BufferMap<'a> {
+    fn drop(&mut self) {
+        if !self.unmap_on_drop {
+            return;
+        }
+
+        let gl = self.buffer.ctx.gl();
+        let target = self.buffer.target;
+
+        // The buffer might have been unbound from its target in the meantime.
+        self.buffer.bind(gl);
+
+        unsafe {
+            gl.unmap_buffer(target);
+            gl.bind_buffer(target, None);
+        }
+
+        self.buffer.mapped.set(false);
     }
 }
 
@@ -104,6 +603,9 @@ impl Drop for Buffer {
     fn drop(&mut self) {
         let gl = self.ctx.gl();
 
+        self.ctx.vertex_arrays().forget_buffer(gl, self.id);
+        self.ctx.bindings().forget_buffer(self.id);
+
         unsafe {
             gl.delete_buffer(self.id);
         }
@@ -1,26 +1,102 @@
+use std::collections::HashSet;
+
 use glow::HasContext;
 
-#[derive(Debug, Copy, Clone)]
+/// The version of the OpenGL implementation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GlVersion {
+    pub major: u32,
+    pub minor: u32,
+
+    /// Whether this is OpenGL ES or WebGL, as opposed to desktop OpenGL.
+    pub is_embedded: bool,
+}
+
+#[derive(Debug, Clone)]
 pub struct Caps {
     pub max_texture_size: u32,
     pub max_color_attachments: u32,
     pub max_draw_buffers: u32,
+    pub max_vertex_attribs: u32,
+    pub max_uniform_buffer_bindings: u32,
+    pub max_uniform_block_size: u32,
+    pub uniform_buffer_offset_alignment: u32,
+    pub max_combined_texture_image_units: u32,
+    pub max_vertex_texture_image_units: u32,
+    pub max_texture_image_units: u32,
+    pub max_varying_vectors: u32,
+    pub max_samples: u32,
+
+    pub version: GlVersion,
+
+    /// The names of the supported extensions.
+    pub extensions: HashSet<String>,
+
+    /// Whether `EXT_disjoint_timer_query` is supported, which is required for
+    /// [`QueryType::TimeElapsed`](super::QueryType::TimeElapsed).
+    pub disjoint_timer_query: bool,
+
+    /// Whether 16-bit and 32-bit float color formats are color-renderable.
+    pub color_buffer_float: bool,
+
+    /// Whether 16-bit float color formats are color-renderable.
+    pub color_buffer_half_float: bool,
 }
 
 impl Caps {
     pub fn new(gl: &glow::Context) -> Self {
-        let max_texture_size = unsafe { gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) };
-        let max_color_attachments = unsafe { gl.get_parameter_i32(glow::MAX_COLOR_ATTACHMENTS) };
-        let max_draw_buffers = unsafe { gl.get_parameter_i32(glow::MAX_DRAW_BUFFERS) };
+        let get = |parameter| {
+            let value = unsafe { gl.get_parameter_i32(parameter) };
+
+            assert!(value > 0);
+
+            u32::try_from(value).unwrap()
+        };
+
+        let version = gl.version();
+        let version = GlVersion {
+            major: version.major,
+            minor: version.minor,
+            is_embedded: version.is_embedded,
+        };
 
-        assert!(max_texture_size > 0);
-        assert!(max_color_attachments > 0);
-        assert!(max_draw_buffers > 0);
+        let extensions = gl.supported_extensions().clone();
+
+        // WebGL 2 exposes the extension under a different name.
+        let disjoint_timer_query = extensions.contains("GL_EXT_disjoint_timer_query")
+            || extensions.contains("EXT_disjoint_timer_query_webgl2");
+
+        // Desktop OpenGL can always render to float color formats.
+        let color_buffer_float = !version.is_embedded
+            || extensions.contains("GL_EXT_color_buffer_float")
+            || extensions.contains("EXT_color_buffer_float");
+        let color_buffer_half_float = !version.is_embedded
+            || extensions.contains("GL_EXT_color_buffer_half_float")
+            || extensions.contains("EXT_color_buffer_half_float");
 
         Caps {
-            max_texture_size: max_texture_size.try_into().unwrap(),
-            max_color_attachments: max_color_attachments.try_into().unwrap(),
-            max_draw_buffers: max_draw_buffers.try_into().unwrap(),
+            max_texture_size: get(glow::MAX_TEXTURE_SIZE),
+            max_color_attachments: get(glow::MAX_COLOR_ATTACHMENTS),
+            max_draw_buffers: get(glow::MAX_DRAW_BUFFERS),
+            max_vertex_attribs: get(glow::MAX_VERTEX_ATTRIBS),
+            max_uniform_buffer_bindings: get(glow::MAX_UNIFORM_BUFFER_BINDINGS),
+            max_uniform_block_size: get(glow::MAX_UNIFORM_BLOCK_SIZE),
+            uniform_buffer_offset_alignment: get(glow::UNIFORM_BUFFER_OFFSET_ALIGNMENT),
+            max_combined_texture_image_units: get(glow::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+            max_vertex_texture_image_units: get(glow::MAX_VERTEX_TEXTURE_IMAGE_UNITS),
+            max_texture_image_units: get(glow::MAX_TEXTURE_IMAGE_UNITS),
+            max_varying_vectors: get(glow::MAX_VARYING_VECTORS),
+            max_samples: get(glow::MAX_SAMPLES),
+            version,
+            extensions,
+            disjoint_timer_query,
+            color_buffer_float,
+            color_buffer_half_float,
         }
     }
+
+    /// Returns whether the extension with the given name is supported.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
 }
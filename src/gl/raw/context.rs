@@ -1,4 +1,9 @@
-use std::{cell::Cell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    ops::Range,
+    rc::Rc,
+};
 
 use glow::HasContext;
 
@@ -7,14 +12,22 @@ use crate::{
     sl::program_def::ProgramDef,
 };
 
-use super::{Buffer, Caps, ContextError, DrawSettings, Image, Program, Texture2d, TextureError};
+use super::{
+    bindings::Bindings, vertex_array::VertexArrays, Buffer, Caps, ContextError, DrawSettings,
+    Fence, FenceError, Framebuffer, Image, ImageInternalFormat, PixelReadback, Program, Query,
+    QueryError, QueryType, ReadbackError, Rect, Texture2d, TextureError,
+};
 
 pub(super) struct ContextShared {
     gl: glow::Context,
     caps: Caps,
     draw_settings: Cell<DrawSettings>,
-    draw_fbo: glow::Framebuffer,
+    bindings: Bindings,
     default_framebuffer_size: Cell<[u32; 2]>,
+    occlusion_query_active: Cell<bool>,
+    timer_query_active: Cell<bool>,
+    global_uniform_buffers: RefCell<BTreeMap<usize, (glow::Buffer, Range<usize>)>>,
+    vertex_arrays: VertexArrays,
 }
 
 pub struct Context {
@@ -42,29 +55,81 @@ impl ContextShared {
         self.draw_settings.set(*new);
     }
 
-    pub(super) fn draw_fbo(&self) -> glow::Framebuffer {
-        self.draw_fbo
+    pub(super) fn vertex_arrays(&self) -> &VertexArrays {
+        &self.vertex_arrays
+    }
+
+    pub(super) fn bindings(&self) -> &Bindings {
+        &self.bindings
     }
 
     pub(super) fn default_framebuffer_size(&self) -> [u32; 2] {
         self.default_framebuffer_size.get()
     }
+
+    /// Returns the buffer that is globally bound to the given uniform buffer
+    /// binding, if any.
+    pub(super) fn global_uniform_buffer(
+        &self,
+        location: usize,
+    ) -> Option<(glow::Buffer, Range<usize>)> {
+        self.global_uniform_buffers.borrow().get(&location).cloned()
+    }
+
+    /// Binds `range` of `buffer` to the uniform buffer binding `location`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is not aligned to `UNIFORM_BUFFER_OFFSET_ALIGNMENT`
+    /// or out of bounds.
+    pub(super) fn bind_uniform_buffer_range(
+        &self,
+        location: usize,
+        buffer: glow::Buffer,
+        range: Range<usize>,
+    ) {
+        // OpenGL ES 3.0.6: 2.10.1.1 Binding Buffer Objects to Indexed Targets
+        // > An `INVALID_VALUE` error is generated if `offset` is not a
+        // > multiple of the value of `UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+        let alignment = usize::try_from(self.caps.uniform_buffer_offset_alignment).unwrap();
+        assert!(range.start.is_multiple_of(alignment));
+        assert!(range.start < range.end);
+
+        let location = u32::try_from(location).unwrap();
+
+        self.bindings
+            .bind_uniform_buffer_range(&self.gl, location, buffer, range);
+    }
+
+    /// Returns whether a query of the given type's target is active.
+    pub(super) fn query_slot(&self, ty: QueryType) -> &Cell<bool> {
+        if ty.is_occlusion() {
+            &self.occlusion_query_active
+        } else {
+            &self.timer_query_active
+        }
+    }
+}
+
+impl Drop for ContextShared {
+    fn drop(&mut self) {
+        self.vertex_arrays.delete(&self.gl);
+    }
 }
 
 impl Context {
     pub fn new(gl: glow::Context) -> Result<Self, ContextError> {
         let caps = Caps::new(&gl);
 
-        // All vertex bindings are made through a single vertex array object
-        // that is bound at the start. The vertex array object binding must not
-        // be changed during the lifetime of a context.
-        let vao = unsafe { gl.create_vertex_array() }.map_err(ContextError::ObjectCreation)?;
-
-        unsafe { gl.bind_vertex_array(Some(vao)) };
+        // Vertex array objects are created and cached per vertex buffer
+        // layout. Buffer operations on `ELEMENT_ARRAY_BUFFER` bind a default
+        // vertex array object first.
+        let vertex_arrays = VertexArrays::new(&gl)?;
 
         // All framebuffer attachments are made with a single framebuffer object
         // that is created at the start.
         let draw_fbo = unsafe { gl.create_framebuffer() }.map_err(ContextError::ObjectCreation)?;
+        let bindings = Bindings::new(draw_fbo);
 
         let default_framebuffer_size = {
             let mut viewport = [0, 0, 0, 0];
@@ -81,8 +146,12 @@ impl Context {
             gl,
             caps,
             draw_settings: Cell::new(DrawSettings::default()),
-            draw_fbo,
+            bindings,
             default_framebuffer_size: Cell::new(default_framebuffer_size),
+            occlusion_query_active: Cell::new(false),
+            timer_query_active: Cell::new(false),
+            global_uniform_buffers: Default::default(),
+            vertex_arrays,
         });
 
         Ok(Self { shared })
@@ -92,6 +161,26 @@ impl Context {
         &self.shared.caps
     }
 
+    /// Binds `range` of `buffer` to the uniform buffer binding `location` for
+    /// all subsequent draw calls.
+    ///
+    /// Draw calls that are not given a buffer for a uniform block at this
+    /// location use the global buffer instead. Draw calls that are given a
+    /// buffer do not affect the global buffer. The caller must keep `buffer`
+    /// alive for as long as it is bound.
+    pub fn set_global_uniform_buffer(&self, location: usize, buffer: &Buffer, range: Range<usize>) {
+        assert!(buffer.context().ref_eq(&self.shared));
+        assert!(range.end <= buffer.len());
+
+        self.shared
+            .bind_uniform_buffer_range(location, buffer.id(), range.clone());
+
+        self.shared
+            .global_uniform_buffers
+            .borrow_mut()
+            .insert(location, (buffer.id(), range));
+    }
+
     pub fn create_buffer(
         &self,
         data: &[u8],
@@ -101,6 +190,15 @@ impl Context {
         Buffer::new(self.shared.clone(), data, target, usage)
     }
 
+    pub fn create_buffer_with_len(
+        &self,
+        len: usize,
+        target: u32,
+        usage: BufferUsage,
+    ) -> Result<Buffer, BufferError> {
+        Buffer::new_with_len(self.shared.clone(), len, target, usage)
+    }
+
     pub fn create_texture_2d(&self, image: Image) -> Result<Texture2d, TextureError> {
         Texture2d::new(self.shared.clone(), image)
     }
@@ -109,10 +207,42 @@ impl Context {
         Texture2d::new_with_mipmap(self.shared.clone(), image)
     }
 
+    pub fn create_texture_2d_uninitialized(
+        &self,
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        levels: usize,
+    ) -> Result<Texture2d, TextureError> {
+        Texture2d::new_uninitialized(self.shared.clone(), size, internal_format, levels)
+    }
+
+    pub fn create_texture_2d_with_level_images(
+        &self,
+        images: Vec<Image>,
+    ) -> Result<Texture2d, TextureError> {
+        Texture2d::new_with_level_images(self.shared.clone(), images)
+    }
+
     pub fn create_program(&self, def: ProgramDef) -> Result<Program, ProgramError> {
         Program::new(self.shared.clone(), def)
     }
 
+    pub fn create_query(&self, ty: QueryType) -> Result<Query, QueryError> {
+        Query::new(self.shared.clone(), ty)
+    }
+
+    pub fn create_fence(&self) -> Result<Fence, FenceError> {
+        Fence::new(self.shared.clone())
+    }
+
+    pub fn read_pixels_async(
+        &self,
+        framebuffer: &Framebuffer,
+        rect: Rect,
+    ) -> Result<PixelReadback, ReadbackError> {
+        PixelReadback::new(self.shared.clone(), framebuffer, rect)
+    }
+
     pub fn finish(&self) {
         unsafe { self.shared.gl.finish() };
     }
@@ -0,0 +1,257 @@
+use std::{cell::RefCell, ops::Range};
+
+/// A CPU-side record of the indices in an element buffer, which is used to
+/// check that draw calls only use indices that are in bounds of the vertex
+/// buffers.
+///
+/// Instead of the indices themselves, only the largest index of each upload
+/// is kept. The largest index of a draw call's range of elements is bounded by
+/// the largest indices of the uploads that the range overlaps. This is exact
+/// for buffers that hold the elements of many meshes if each mesh is uploaded
+/// separately, and conservative otherwise.
+///
+/// The indices of a range may become unknown, e.g. when the range is written
+/// through a mapping, in which case draw calls that use the range can not
+/// check them.
+pub struct ElementIndices {
+    runs: RefCell<Vec<IndexRun>>,
+}
+
+/// The largest index of a range of elements, or `None` if it is unknown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexRun {
+    range: Range<usize>,
+    max: Option<u32>,
+}
+
+impl Default for ElementIndices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ElementIndices {
+    /// The number of runs at which adjacent runs are merged. This bounds the
+    /// memory use for buffers that are written in many small pieces, at the
+    /// cost of looser bounds.
+    const MAX_RUNS: usize = 1024;
+
+    pub fn new() -> Self {
+        Self {
+            runs: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Replaces all indices.
+    pub fn set(&self, indices: impl ExactSizeIterator<Item = u32>) {
+        self.clear();
+        self.write(0, indices);
+    }
+
+    /// Overwrites the indices starting at `offset`, extending the indices if
+    /// needed.
+    ///
+    /// `offset` must not be larger than the number of indices.
+    pub fn write(&self, offset: usize, indices: impl ExactSizeIterator<Item = u32>) {
+        let range = offset..offset + indices.len();
+        let max = indices.max().unwrap_or(0);
+
+        self.insert(IndexRun {
+            range,
+            max: Some(max),
+        });
+    }
+
+    /// Marks the indices in `range` as unknown until they are written again.
+    ///
+    /// `range.start` must not be larger than the number of indices.
+    pub fn forget(&self, range: Range<usize>) {
+        self.insert(IndexRun { range, max: None });
+    }
+
+    /// Removes all indices.
+    pub fn clear(&self) {
+        self.runs.borrow_mut().clear();
+    }
+
+    /// Returns the number of indices.
+    fn len(&self) -> usize {
+        self.runs.borrow().last().map_or(0, |run| run.range.end)
+    }
+
+    /// Returns whether all indices are known.
+    pub fn is_known(&self) -> bool {
+        self.runs.borrow().iter().all(|run| run.max.is_some())
+    }
+
+    /// Returns an upper bound of the largest index in `range`, or `None` if
+    /// some of the indices in `range` are unknown or `range` is empty or out
+    /// of bounds.
+    pub fn max(&self, range: Range<usize>) -> Option<usize> {
+        if range.start >= range.end || range.end > self.len() {
+            return None;
+        }
+
+        let runs = self.runs.borrow();
+        let first = runs.partition_point(|run| run.range.end <= range.start);
+
+        runs[first..]
+            .iter()
+            .take_while(|run| run.range.start < range.end)
+            .try_fold(0, |max, run| run.max.map(|run_max| max.max(run_max)))
+            .map(|max| max as usize)
+    }
+
+    fn insert(&self, new: IndexRun) {
+        let mut runs = self.runs.borrow_mut();
+        let len = runs.last().map_or(0, |run| run.range.end);
+
+        assert!(new.range.start <= len);
+
+        if new.range.is_empty() {
+            return;
+        }
+
+        // The runs that overlap the new run are replaced. Their parts outside
+        // of the new run keep their bound, which is still an upper bound.
+        let first = runs.partition_point(|run| run.range.end <= new.range.start);
+        let last = runs.partition_point(|run| run.range.start < new.range.end);
+
+        let before = runs[first..last]
+            .first()
+            .filter(|run| run.range.start < new.range.start)
+            .map(|run| IndexRun {
+                range: run.range.start..new.range.start,
+                max: run.max,
+            });
+        let after = runs[first..last]
+            .last()
+            .filter(|run| run.range.end > new.range.end)
+            .map(|run| IndexRun {
+                range: new.range.end..run.range.end,
+                max: run.max,
+            });
+
+        runs.splice(first..last, before.into_iter().chain([new]).chain(after));
+
+        if runs.len() > Self::MAX_RUNS {
+            merge_pairs(&mut runs);
+        }
+    }
+}
+
+/// Merges each pair of adjacent runs into one.
+fn merge_pairs(runs: &mut Vec<IndexRun>) {
+    let merged = runs
+        .chunks(2)
+        .map(|pair| IndexRun {
+            range: pair[0].range.start..pair[pair.len() - 1].range.end,
+            max: pair
+                .iter()
+                .try_fold(0, |max, run| run.max.map(|m| max.max(m))),
+        })
+        .collect();
+
+    *runs = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ElementIndices;
+
+    #[test]
+    fn max_is_bounded_by_the_overlapped_uploads() {
+        let indices = ElementIndices::new();
+
+        indices.write(0, [3, 1, 2].into_iter());
+        indices.write(3, [7, 9].into_iter());
+        indices.write(5, [4, 5, 6].into_iter());
+
+        assert_eq!(indices.len(), 8);
+
+        // Draw calls that cover whole uploads get their exact maximum.
+        assert_eq!(indices.max(0..3), Some(3));
+        assert_eq!(indices.max(3..5), Some(9));
+        assert_eq!(indices.max(5..8), Some(6));
+
+        // Draw calls that cover parts of uploads get an upper bound.
+        assert_eq!(indices.max(1..2), Some(3));
+        assert_eq!(indices.max(2..4), Some(9));
+        assert_eq!(indices.max(0..8), Some(9));
+
+        assert_eq!(indices.max(0..9), None);
+        assert_eq!(indices.max(2..2), None);
+    }
+
+    #[test]
+    fn writes_overwrite_overlapped_uploads() {
+        let indices = ElementIndices::new();
+
+        indices.set([1, 2, 3, 4, 5, 6].into_iter());
+        indices.write(2, [20, 30].into_iter());
+
+        // The remaining parts of the first upload keep its bound.
+        assert_eq!(indices.max(0..2), Some(6));
+        assert_eq!(indices.max(2..4), Some(30));
+        assert_eq!(indices.max(4..6), Some(6));
+
+        indices.write(0, [0; 6].into_iter());
+
+        assert_eq!(indices.max(0..6), Some(0));
+
+        // Writes can extend the indices.
+        indices.write(6, [8].into_iter());
+
+        assert_eq!(indices.max(5..7), Some(8));
+    }
+
+    #[test]
+    fn set_replaces_all_indices() {
+        let indices = ElementIndices::new();
+
+        indices.set([5, 6, 7].into_iter());
+        indices.set([1].into_iter());
+
+        assert_eq!(indices.len(), 1);
+        assert_eq!(indices.max(0..1), Some(1));
+        assert_eq!(indices.max(0..2), None);
+    }
+
+    #[test]
+    fn forgotten_ranges_are_unknown_until_written() {
+        let indices = ElementIndices::new();
+
+        indices.set([1, 2, 3, 4].into_iter());
+        indices.forget(1..3);
+
+        assert!(!indices.is_known());
+        assert_eq!(indices.max(0..1), Some(4));
+        assert_eq!(indices.max(3..4), Some(4));
+        assert_eq!(indices.max(0..2), None);
+
+        indices.write(1, [7, 8].into_iter());
+
+        assert!(indices.is_known());
+        assert_eq!(indices.max(1..3), Some(8));
+    }
+
+    #[test]
+    fn many_small_writes_are_merged() {
+        let indices = ElementIndices::new();
+
+        for i in 0..10_000 {
+            indices.write(i as usize, [i].into_iter());
+        }
+
+        assert!(indices.runs.borrow().len() <= ElementIndices::MAX_RUNS);
+        assert_eq!(indices.len(), 10_000);
+
+        for range in [0..1, 500..600, 9_999..10_000, 0..10_000] {
+            let max = indices.max(range.clone()).unwrap();
+
+            assert!(max >= range.end - 1, "{range:?}");
+        }
+
+        assert_eq!(indices.max(0..10_000), Some(9_999));
+    }
+}
@@ -1,7 +1,19 @@
 use glow::HasContext;
 use thiserror::Error;
 
-pub(super) fn check_gl_error(gl: &glow::Context, name: &str) -> Result<(), String> {
+use super::{ImageComponentType, ImageFormat, ImageInternalFormat, QueryType, Rect};
+
+/// An error reported by OpenGL through `glGetError`.
+#[derive(Debug, Clone, Error)]
+pub enum GlError {
+    #[error("OpenGL ran out of memory ({0})")]
+    OutOfMemory(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+pub(super) fn check_gl_error(gl: &glow::Context, name: &str) -> Result<(), GlError> {
     let error_info = unsafe { gl.get_error() };
 
     if error_info == glow::NO_ERROR {
@@ -13,14 +25,17 @@ pub(super) fn check_gl_error(gl: &glow::Context, name: &str) -> Result<(), Strin
             glow::INVALID_OPERATION => "INVALID_OPERATION".to_string(),
             glow::INVALID_FRAMEBUFFER_OPERATION => "INVALID_FRAMEBUFFER_OPERATION".to_string(),
             glow::OUT_OF_MEMORY => {
-                // Results of OpenGL operations are undefined if it ran out of
-                // memory. I'm not sure if we can do better than panicking here.
-                panic!("OpenGL ran out of memory");
+                // OpenGL ES 3.0.6: 2.5 GL Errors
+                // > If the GL runs out of memory, [...] the state of the GL is
+                // > undefined, except for the state of the error flags.
+                //
+                // We leave it to the caller to decide whether to recover.
+                return Err(GlError::OutOfMemory(name.to_string()));
             }
             _ => format!("unknown OpenGL error: {error_info}"),
         };
 
-        Err(format!("{s} ({name})"))
+        Err(GlError::Other(format!("{s} ({name})")))
     }
 }
 
@@ -34,17 +49,48 @@ pub enum ContextError {
     Unexpected(String),
 }
 
-/// An error that occurred while creating a buffer.
+/// An error that occurred while creating or accessing a buffer.
 #[derive(Debug, Clone, Error)]
 pub enum BufferError {
     #[error("could not create buffer object: {0}")]
     ObjectCreation(String),
 
+    #[error("buffer access out of bounds: {len} bytes at offset {offset}, but the buffer has {buffer_len} bytes")]
+    OutOfBounds {
+        offset: usize,
+        len: usize,
+        buffer_len: usize,
+    },
+
+    #[error(
+        "too many global uniform blocks: the maximum number of uniform buffer bindings is {0}"
+    )]
+    TooManyGlobalUniformBlocks(u32),
+
+    #[error("could not map buffer: {0}")]
+    Mapping(String),
+
+    #[error("mapped buffer range at offset {offset} is not aligned to {alignment} bytes")]
+    MisalignedMapping { offset: usize, alignment: usize },
+
+    /// OpenGL ran out of memory while allocating or writing buffer storage.
+    #[error("out of memory: {0}")]
+    OutOfMemory(String),
+
     #[error("unexpected error while creating buffer: {0}")]
     Unexpected(String),
 }
 
-/// An error that occurred while creating a texture.
+impl From<GlError> for BufferError {
+    fn from(error: GlError) -> Self {
+        match error {
+            GlError::OutOfMemory(name) => Self::OutOfMemory(name),
+            GlError::Other(error) => Self::Unexpected(error),
+        }
+    }
+}
+
+/// An error that occurred while creating or uploading to a texture.
 #[derive(Debug, Clone, Error)]
 pub enum TextureError {
     #[error("could not create texture object: {0}")]
@@ -59,6 +105,61 @@ pub enum TextureError {
     #[error("invalid data size: expected {expected} bytes, but got {got}")]
     DataSizeMismatch { expected: usize, got: usize },
 
+    #[error("pixel unpack buffer too small: need {len} bytes at offset {offset}, but the buffer has {buffer_len} bytes")]
+    UnpackBufferOutOfBounds {
+        offset: usize,
+        len: usize,
+        buffer_len: usize,
+    },
+
+    #[error(
+        "pixel unpack buffer offset {offset} is not a multiple of the component size {alignment}"
+    )]
+    UnpackBufferMisaligned { offset: usize, alignment: usize },
+
+    #[error("pixel unpack buffer is mapped")]
+    UnpackBufferMapped,
+
+    #[error("texture level out of range: requested {level}, but the texture has {levels} levels")]
+    LevelOutOfRange { level: usize, levels: usize },
+
+    #[error("image format mismatch: expected {expected:?}, but got {got:?}")]
+    FormatMismatch {
+        expected: ImageInternalFormat,
+        got: ImageInternalFormat,
+    },
+
+    #[error("region size {region:?} does not match image size {image:?}")]
+    RegionSizeMismatch { region: [u32; 2], image: [u32; 2] },
+
+    #[error("unsupported image color type: {0}")]
+    UnsupportedColorType(String),
+
+    #[error(
+        "region {rect:?} is not aligned to 4x4 blocks in texture level of size {level_size:?}"
+    )]
+    RegionNotBlockAligned { rect: Rect, level_size: [u32; 2] },
+
+    #[error("too many texture levels: requested {levels}, but the maximum is {max}")]
+    TooManyLevels { levels: usize, max: usize },
+
+    #[error("texture level {level} has size {got:?}, but expected {expected:?}")]
+    LevelSizeMismatch {
+        level: usize,
+        expected: [u32; 2],
+        got: [u32; 2],
+    },
+
+    #[error("region {rect:?} is out of bounds for texture level of size {level_size:?}")]
+    RegionOutOfBounds { rect: Rect, level_size: [u32; 2] },
+
+    #[error("cannot generate mipmaps for texture format {0:?}, since it is not both color-renderable and filterable")]
+    NotMipmappable(ImageInternalFormat),
+
+    /// OpenGL ran out of memory while allocating texture storage.
+    #[error("out of memory: {0}")]
+    OutOfMemory(String),
+
     #[error("unexpected error while creating texture: {0}")]
     Unexpected(String),
 }
@@ -72,6 +173,10 @@ pub enum VertexArrayError {
     #[error("invalid vertex attribute: {0}")]
     InvalidVertexAttribute(String),
 
+    /// OpenGL ran out of memory while creating a vertex array.
+    #[error("out of memory: {0}")]
+    OutOfMemory(String),
+
     #[error("unexpected error while creating vertex array: {0}")]
     Unexpected(String),
 }
@@ -118,7 +223,8 @@ pub(super) fn check_framebuffer_completeness(
 
         // OpenGL ES 3.0.6: 4.4.4.2 Whole Framebuffer Completeness
         // > If *CheckFramebufferStatus* generates an error, zero is returned.
-        0 => check_gl_error(gl, "framebuffer completeness status").map_err(Error),
+        0 => check_gl_error(gl, "framebuffer completeness status")
+            .map_err(|error| Error(error.to_string())),
 
         // This should not be reachable.
         error => Err(Unknown(error)),
@@ -134,6 +240,9 @@ pub enum FramebufferError {
     #[error("texture level is too large: requested {requested}, but the maximum level is {max}")]
     LevelTooLarge { requested: u32, max: u32 },
 
+    #[error("attachment format is not renderable: {0:?}")]
+    NotRenderable(ImageInternalFormat),
+
     #[error("too many color attachments: requested {requested}, but the maximum number of color attachments is {max}")]
     TooManyColorAttachments { requested: u32, max: u32 },
 
@@ -167,6 +276,34 @@ pub enum ProgramValidationError {
 
     #[error("duplicate uniform block location: {0}")]
     DuplicateUniformBlockLocation(usize),
+
+    #[error("duplicate vertex attribute location: {0}")]
+    DuplicateVertexAttributeLocation(usize),
+
+    #[error(
+        "too many vertex attributes: requested {requested} locations, but the maximum is {max}"
+    )]
+    TooManyVertexAttributes { requested: u32, max: u32 },
+
+    #[error("uniform block location {location} is out of range: the maximum number of uniform buffer bindings is {max}")]
+    UniformBlockLocationOutOfRange { location: usize, max: u32 },
+
+    #[error(
+        "uniform block {name} is too large: its size is {size} bytes, but the maximum is {max}"
+    )]
+    UniformBlockTooLarge { name: String, size: usize, max: u32 },
+
+    #[error("sampler texture unit {texture_unit} is out of range: the maximum number of texture units is {max}")]
+    TextureUnitOutOfRange { texture_unit: usize, max: u32 },
+
+    #[error("too many samplers: requested {requested}, but the maximum is {max}")]
+    TooManySamplers { requested: u32, max: u32 },
+
+    #[error("too many varyings: requested {requested} vectors, but the maximum is {max}")]
+    TooManyVaryingVectors { requested: u32, max: u32 },
+
+    #[error("too many fragment outputs: requested {requested}, but the maximum number of draw buffers is {max}")]
+    TooManyFragmentOutputs { requested: u32, max: u32 },
 }
 
 /// An error that occurred while creating a program.
@@ -191,10 +328,79 @@ pub enum ProgramError {
     #[error("invalid vertex attribute: {0}")]
     InvalidVertexAttribute(String),
 
+    /// OpenGL ran out of memory while compiling or linking the program.
+    #[error("out of memory: {0}")]
+    OutOfMemory(String),
+
     #[error("unexpected error while creating program: {0}")]
     Unexpected(String),
 }
 
+/// An error that occurred while creating or using a query.
+#[derive(Debug, Clone, Error)]
+pub enum QueryError {
+    #[error("could not create query object: {0}")]
+    ObjectCreation(String),
+
+    #[error("query type is not supported by the context: {0:?}")]
+    Unsupported(QueryType),
+
+    #[error("a query with the same target as {0:?} is already active")]
+    AlreadyActive(QueryType),
+
+    #[error("query of type {0:?} is not active")]
+    NotActive(QueryType),
+
+    #[error("unexpected error in query: {0}")]
+    Unexpected(String),
+}
+
+/// An error that occurred while creating or waiting for a fence.
+#[derive(Debug, Clone, Error)]
+pub enum FenceError {
+    #[error("could not create fence sync object: {0}")]
+    ObjectCreation(String),
+
+    #[error("waiting for fence failed: {0}")]
+    WaitFailed(String),
+
+    #[error("unexpected error in fence: {0}")]
+    Unexpected(String),
+}
+
+/// An error that occurred while reading pixels back from a framebuffer.
+#[derive(Debug, Clone, Error)]
+pub enum ReadbackError {
+    #[error("framebuffer has no color attachment to read from")]
+    NoColorAttachment,
+
+    #[error("the first color attachment has format {0:?}, which can not be read back")]
+    UnreadableColorAttachment(ImageInternalFormat),
+
+    #[error("read rectangle is out of bounds: {rect:?} exceeds framebuffer size {size:?}")]
+    OutOfBounds { rect: Rect, size: [u32; 2] },
+
+    #[error(
+        "pixels with format {format:?} and type {ty:?} can not be converted to this image type"
+    )]
+    UnsupportedImageFormat {
+        format: ImageFormat,
+        ty: ImageComponentType,
+    },
+
+    #[error("buffer error: {0}")]
+    Buffer(#[from] BufferError),
+
+    #[error("framebuffer error: {0}")]
+    Framebuffer(#[from] FramebufferError),
+
+    #[error("fence error: {0}")]
+    Fence(#[from] FenceError),
+
+    #[error("unexpected error while reading pixels: {0}")]
+    Unexpected(String),
+}
+
 /// An error that occurred while creating an object.
 #[derive(Debug, Clone, Error)]
 pub enum CreateError {
@@ -212,6 +418,9 @@ pub enum CreateError {
 
     #[error("vertex array error: {0}")]
     VertexArray(#[from] VertexArrayError),
+
+    #[error("query error: {0}")]
+    Query(#[from] QueryError),
 }
 
 /// An error that occurred while drawing.
@@ -220,10 +429,159 @@ pub enum DrawError {
     #[error("create error: {0}")]
     Create(#[from] CreateError),
 
+    #[error("query error: {0}")]
+    Query(QueryError),
+
+    #[error(
+        "uniform block {0} has no buffer and no global uniform buffer is bound at its location"
+    )]
+    MissingGlobalUniformBlock(String),
+
+    #[error("expected {expected} uniform buffers, but got {got}")]
+    UniformBlockCountMismatch { expected: usize, got: usize },
+
+    #[error("expected {expected} samplers, but got {got}")]
+    SamplerCountMismatch { expected: usize, got: usize },
+
+    #[error("expected {expected} vertex buffers, but got {got}")]
+    VertexBufferCountMismatch { expected: usize, got: usize },
+
+    /// The attributes of the program's vertex block at index `binding` do not
+    /// fit into the stride of its vertex buffer.
+    #[error("vertex buffer {path} is not compatible with the program's vertex block")]
+    IncompatibleVertexSpec { binding: usize, path: String },
+
+    /// The sampler at path `sampler` in the program's uniforms samples from a
+    /// texture level that is attached to the framebuffer.
+    #[error("sampler {sampler} samples from the framebuffer's {attachment}")]
+    FeedbackLoop { sampler: String, attachment: String },
+
+    #[error("{0} belongs to a different context")]
+    ContextMismatch(String),
+
+    #[error("vertex buffer {path} belongs to a different context")]
+    VertexBufferContextMismatch { binding: usize, path: String },
+
+    /// Mapped buffers can not be used by draw calls.
+    #[error("vertex buffer {path} is mapped")]
+    VertexBufferMapped { binding: usize, path: String },
+
+    #[error("element buffer is mapped")]
+    ElementBufferMapped,
+
+    #[error("uniform buffer for block {0} is mapped")]
+    UniformBufferMapped(String),
+
+    #[error("uniform buffer range {start}..{end} for block {block} is out of bounds for a buffer with {buffer_len} bytes")]
+    UniformBufferOutOfBounds {
+        block: String,
+        start: usize,
+        end: usize,
+        buffer_len: usize,
+    },
+
+    #[error("uniform buffer offset {offset} for block {block} is not a multiple of the required alignment {alignment}")]
+    UniformBufferMisaligned {
+        block: String,
+        offset: usize,
+        alignment: usize,
+    },
+
+    #[error(
+        "vertex buffer {path} has {len} vertices, but vertex buffer {other_path} has {other_len}"
+    )]
+    VertexBufferLengthMismatch {
+        path: String,
+        len: usize,
+        other_path: String,
+        other_len: usize,
+    },
+
+    #[error("element range {start}..{end} is out of bounds for an element buffer with {num_elements} elements")]
+    ElementRangeOutOfBounds {
+        start: usize,
+        end: usize,
+        num_elements: usize,
+    },
+
+    #[error("element buffer contains index {max_index}, but vertex buffer {path} has only {num_vertices} vertices")]
+    ElementOutOfBounds {
+        binding: usize,
+        path: String,
+        max_index: usize,
+        num_vertices: usize,
+    },
+
+    #[error("byte range {start}..{end} of vertex buffer {path} is not a multiple of its stride {stride}")]
+    VertexRangeMisaligned {
+        binding: usize,
+        path: String,
+        start: usize,
+        end: usize,
+        stride: usize,
+    },
+
+    #[error("vertex range {start}..{end} is out of bounds for a vertex buffer with {num_vertices} vertices")]
+    VertexRangeOutOfBounds {
+        start: usize,
+        end: usize,
+        num_vertices: usize,
+    },
+
+    #[error("drawing {num_instances} instances, but an instance buffer has data for only {num_available} instances")]
+    InstanceRangeOutOfBounds {
+        num_instances: usize,
+        num_available: usize,
+    },
+
+    /// A count or offset of the draw call does not fit into the integer type
+    /// that OpenGL takes for it.
+    #[error("{name} {value} is out of range for OpenGL")]
+    ParameterOutOfRange { name: &'static str, value: usize },
+
+    /// OpenGL ran out of memory while drawing.
+    #[error("out of memory: {0}")]
+    OutOfMemory(String),
+
     #[error("general OpenGL error: {0}")]
     Error(String),
 }
 
+impl DrawError {
+    /// Returns the index and path of the vertex buffer binding that the error
+    /// refers to.
+    ///
+    /// The raw layer names bindings by their index in
+    /// [`VertexSpec::vertex_data`](super::VertexSpec::vertex_data), which the
+    /// typed layer replaces with the path of the binding in the vertex data.
+    pub(crate) fn vertex_binding_mut(&mut self) -> Option<(usize, &mut String)> {
+        use DrawError::*;
+
+        match self {
+            IncompatibleVertexSpec { binding, path }
+            | VertexBufferContextMismatch { binding, path }
+            | VertexBufferMapped { binding, path }
+            | ElementOutOfBounds { binding, path, .. }
+            | VertexRangeMisaligned { binding, path, .. } => Some((*binding, path)),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the raw layer's name for the vertex buffer binding at `index`.
+pub(super) fn vertex_binding_path(index: usize) -> String {
+    format!("vertex_data[{index}]")
+}
+
+impl From<GlError> for DrawError {
+    fn from(error: GlError) -> Self {
+        match error {
+            GlError::OutOfMemory(name) => Self::OutOfMemory(name),
+            GlError::Other(error) => Self::Error(error),
+        }
+    }
+}
+
 impl From<BufferError> for DrawError {
     fn from(value: BufferError) -> Self {
         Self::Create(value.into())
@@ -253,3 +611,68 @@ impl From<VertexArrayError> for DrawError {
         Self::Create(value.into())
     }
 }
+
+macro_rules! impl_from_gl_error {
+    ($($ty:ident),*) => {
+        $(
+            impl From<GlError> for $ty {
+                fn from(error: GlError) -> Self {
+                    Self::Unexpected(error.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_gl_error!(
+    ContextError,
+    FramebufferError,
+    QueryError,
+    FenceError,
+    ReadbackError
+);
+
+macro_rules! impl_from_gl_error_with_out_of_memory {
+    ($($ty:ident),*) => {
+        $(
+            impl From<GlError> for $ty {
+                fn from(error: GlError) -> Self {
+                    match error {
+                        GlError::OutOfMemory(name) => Self::OutOfMemory(name),
+                        GlError::Other(error) => Self::Unexpected(error),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_gl_error_with_out_of_memory!(TextureError, VertexArrayError, ProgramError);
+
+/// An error that occurred while executing a render graph.
+#[derive(Debug, Clone, Error)]
+pub enum GraphError {
+    #[error("pass {pass} reads and writes texture {texture}")]
+    FeedbackLoop { pass: String, texture: String },
+
+    #[error("pass {pass} reads transient texture {texture}, which is not written by any pass")]
+    UnwrittenTexture { pass: String, texture: String },
+
+    #[error("passes form a dependency cycle: {}", passes.join(", "))]
+    Cycle { passes: Vec<String> },
+
+    #[error("pass {pass} renders into both the screen and textures")]
+    ScreenAndTextureTargets { pass: String },
+
+    #[error("pass {pass} renders into more than one depth texture")]
+    MultipleDepthTargets { pass: String },
+
+    #[error("outputs of pass {pass} do not match its declared color textures")]
+    OutputMismatch { pass: String },
+
+    #[error("texture error: {0}")]
+    Texture(#[from] TextureError),
+
+    #[error("draw error in pass {pass}: {error}")]
+    Draw { pass: String, error: DrawError },
+}
@@ -0,0 +1,87 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use glow::HasContext;
+
+use super::{context::ContextShared, error::check_gl_error, FenceError};
+
+pub struct Fence {
+    ctx: Rc<ContextShared>,
+    id: glow::Fence,
+    signaled: Cell<bool>,
+}
+
+impl Fence {
+    pub(super) fn new(ctx: Rc<ContextShared>) -> Result<Self, FenceError> {
+        let gl = ctx.gl();
+
+        // OpenGL ES 3.0.6: 5.2 Sync Objects and Fences
+        // > `condition` must be `SYNC_GPU_COMMANDS_COMPLETE`, and `flags` must
+        // > be zero.
+        let id = unsafe { gl.fence_sync(glow::SYNC_GPU_COMMANDS_COMPLETE, 0) }
+            .map_err(FenceError::ObjectCreation)?;
+
+        let fence = Self {
+            ctx: ctx.clone(),
+            id,
+            signaled: Cell::new(false),
+        };
+
+        check_gl_error(gl, "after new fence")?;
+
+        Ok(fence)
+    }
+
+    /// Returns `true` if all commands issued before the fence have completed,
+    /// without waiting for the GPU.
+    pub fn poll(&self) -> Result<bool, FenceError> {
+        self.wait(Duration::ZERO)
+    }
+
+    /// Waits for the fence to become signaled for at most `timeout`.
+    ///
+    /// Returns `true` if the fence was signaled within the timeout. Note that
+    /// WebGL does not allow waiting with a nonzero timeout.
+    pub fn wait(&self, timeout: Duration) -> Result<bool, FenceError> {
+        if self.signaled.get() {
+            return Ok(true);
+        }
+
+        let gl = self.ctx.gl();
+
+        let timeout = timeout.as_nanos().min(i32::MAX as u128) as i32;
+
+        // OpenGL ES 3.0.6: 5.2.1 Waiting for Sync Objects
+        // > If the `SYNC_FLUSH_COMMANDS_BIT` bit is set in `flags`, and `sync`
+        // > is unsignaled when *ClientWaitSync* is called, then the equivalent
+        // > of *Flush* will be performed before blocking on `sync`.
+        //
+        // Without the flush, the fence might never become signaled.
+        let status =
+            unsafe { gl.client_wait_sync(self.id, glow::SYNC_FLUSH_COMMANDS_BIT, timeout) };
+
+        match status {
+            glow::ALREADY_SIGNALED | glow::CONDITION_SATISFIED => {
+                self.signaled.set(true);
+
+                Ok(true)
+            }
+            glow::TIMEOUT_EXPIRED => Ok(false),
+            _ => Err(FenceError::WaitFailed(
+                check_gl_error(gl, "after fence wait").err().map_or_else(
+                    || format!("unexpected wait status: {status}"),
+                    |error| error.to_string(),
+                ),
+            )),
+        }
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        let gl = self.ctx.gl();
+
+        unsafe {
+            gl.delete_sync(self.id);
+        }
+    }
+}
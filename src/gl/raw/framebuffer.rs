@@ -1,11 +1,11 @@
 use std::rc::Rc;
 
-use glow::HasContext;
-
 use super::{
+    bindings::AttachmentBinding,
     context::ContextShared,
     error::{check_framebuffer_completeness, check_gl_error, FramebufferError},
-    Caps, Comparison, ImageInternalFormat, Sampler2d, Sampler2dSettings, Texture2d,
+    Caps, Comparison, ImageInternalFormat, Sampler, Sampler2d, Sampler2dSettings, Texture2d,
+    TextureError,
 };
 
 #[derive(Clone)]
@@ -30,6 +30,14 @@ impl Attachment {
         }
     }
 
+    pub fn generate_mipmaps(&self) -> Result<(), TextureError> {
+        use Attachment::*;
+
+        match self {
+            Texture2d { texture, .. } => texture.generate_mipmaps(),
+        }
+    }
+
     pub fn sampler(
         &self,
         settings: Sampler2dSettings,
@@ -47,10 +55,71 @@ impl Attachment {
     }
 }
 
+/// The maximum number of attachments of a framebuffer.
+///
+/// This allows for eight color attachments, which is the most that common
+/// implementations support, and a depth and a stencil attachment.
+pub const MAX_ATTACHMENTS: usize = 10;
+
+/// The attachments of a framebuffer.
+///
+/// The attachments are stored inline, so that creating a framebuffer for a
+/// draw call does not allocate.
+#[derive(Clone, Default)]
+pub struct AttachmentList {
+    attachments: [Option<Attachment>; MAX_ATTACHMENTS],
+    len: usize,
+}
+
+impl AttachmentList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an attachment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list already has [`MAX_ATTACHMENTS`] attachments.
+    pub fn push(&mut self, attachment: Attachment) {
+        assert!(
+            self.len < MAX_ATTACHMENTS,
+            "framebuffers can have at most {MAX_ATTACHMENTS} attachments"
+        );
+
+        self.attachments[self.len] = Some(attachment);
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Attachment> + Clone {
+        self.attachments[..self.len].iter().flatten()
+    }
+}
+
+impl FromIterator<Attachment> for AttachmentList {
+    fn from_iter<T: IntoIterator<Item = Attachment>>(iter: T) -> Self {
+        let mut list = Self::new();
+
+        for attachment in iter {
+            list.push(attachment);
+        }
+
+        list
+    }
+}
+
 #[derive(Clone)]
 pub enum Framebuffer {
     Default,
-    Attachments { attachments: Vec<Attachment> },
+    Attachments { attachments: AttachmentList },
 }
 
 impl Framebuffer {
@@ -67,10 +136,49 @@ impl Framebuffer {
     }
 }
 
-fn with_locations(attachments: &[Attachment]) -> impl Iterator<Item = (u32, &Attachment)> {
+impl Framebuffer {
+    /// Returns the name of the attachment that `sampler` can sample from, if
+    /// any.
+    pub(super) fn sampled_attachment(&self, sampler: &Sampler) -> Option<String> {
+        let Framebuffer::Attachments { attachments } = self else {
+            return None;
+        };
+
+        let (sampled_texture, sampled_levels) = match sampler {
+            Sampler::Sampler2d(sampler) => (
+                &sampler.texture,
+                sampler.settings.sampled_levels(sampler.texture.levels()),
+            ),
+        };
+
+        with_locations(attachments).find_map(|(location, attachment)| match attachment {
+            Attachment::Texture2d { texture, level } => {
+                let sampled =
+                    Rc::ptr_eq(texture, sampled_texture) && sampled_levels.contains(level);
+
+                sampled.then(|| attachment_name(location, *level))
+            }
+        })
+    }
+}
+
+fn attachment_name(location: u32, level: u32) -> String {
+    let name = match location {
+        glow::DEPTH_STENCIL_ATTACHMENT => "depth-stencil attachment".to_string(),
+        glow::DEPTH_ATTACHMENT => "depth attachment".to_string(),
+        glow::STENCIL_ATTACHMENT => "stencil attachment".to_string(),
+        _ => format!("color attachment {}", location - glow::COLOR_ATTACHMENT0),
+    };
+
+    format!("{name} at level {level}")
+}
+
+fn with_locations(
+    attachments: &AttachmentList,
+) -> impl Iterator<Item = (u32, &Attachment)> + Clone {
     attachments.iter().scan(0, |num_color, attachment| {
         let format = attachment.internal_format();
-        let location = if format.is_color_renderable() {
+        let location = if format.is_color() {
             let location = glow::COLOR_ATTACHMENT0 + *num_color as u32;
             *num_color += 1;
             location
@@ -81,13 +189,7 @@ fn with_locations(attachments: &[Attachment]) -> impl Iterator<Item = (u32, &Att
         } else if format.is_stencil_renderable() {
             glow::STENCIL_ATTACHMENT
         } else {
-            // FIXME: This does not actually hold! Also, `is_color_renderable`
-            // depends on available extensions. All of this needs to be checked
-            // here.
-            panic!(
-                "every ImageInternalFormat must satisfy at least one of color renderable, depth \
-                 renderable, or stencil renderable"
-            );
+            unreachable!("attachments are validated to be renderable");
         };
 
         Some((location, attachment))
@@ -96,104 +198,69 @@ fn with_locations(attachments: &[Attachment]) -> impl Iterator<Item = (u32, &Att
 
 fn bind_attachments(
     ctx: &ContextShared,
-    attachments: &[Attachment],
+    attachments: &AttachmentList,
 ) -> Result<(), FramebufferError> {
     let gl = ctx.gl();
 
-    for (location, attachment) in with_locations(attachments) {
-        match attachment {
-            Attachment::Texture2d { texture, level } => {
-                let level = (*level).try_into().expect("level is out of i32 range");
-
-                unsafe {
-                    gl.framebuffer_texture_2d(
-                        glow::FRAMEBUFFER,
-                        location,
-                        glow::TEXTURE_2D,
-                        Some(texture.id()),
-                        level,
-                    )
-                };
-            }
-        };
+    let bindings = with_locations(attachments).map(|(location, attachment)| match attachment {
+        Attachment::Texture2d { texture, level } => AttachmentBinding {
+            location,
+            texture: texture.id(),
+            level: (*level).try_into().expect("level is out of i32 range"),
+        },
+    });
+
+    // Draw buffers only need to be set if the attachments have changed.
+    if !ctx.bindings().bind_draw_framebuffer(gl, bindings) {
+        return Ok(());
     }
 
-    let draw_buffers: Vec<_> = with_locations(attachments)
-        .filter(|(_, attachment)| attachment.internal_format().is_color_renderable())
-        .map(|(location, _)| location)
-        .collect();
-
-    unsafe { gl.draw_buffers(&draw_buffers) };
+    ctx.bindings().set_draw_buffers(gl);
 
     #[cfg(debug_assertions)]
     check_framebuffer_completeness(gl).map_err(FramebufferError::Incomplete)?;
 
     #[cfg(debug_assertions)]
-    check_gl_error(gl, "after binding attachments").map_err(FramebufferError::Unexpected)?;
+    check_gl_error(gl, "after binding attachments")?;
 
     Ok(())
 }
 
-fn unbind_attachments(ctx: &ContextShared, attachments: &[Attachment]) {
-    let gl = ctx.gl();
-
-    for (location, attachment) in with_locations(attachments) {
-        match attachment {
-            Attachment::Texture2d { level, .. } => {
-                let level = (*level).try_into().expect("level is out of i32 range");
-
-                unsafe {
-                    gl.framebuffer_texture_2d(
-                        glow::FRAMEBUFFER,
-                        location,
-                        glow::TEXTURE_2D,
-                        None,
-                        level,
-                    )
-                };
-            }
-        };
-    }
-}
-
 impl Framebuffer {
     pub(super) fn bind(&self, ctx: &ContextShared) -> Result<(), FramebufferError> {
         use Framebuffer::*;
 
         match self {
-            Framebuffer::Default => Ok(()),
+            Framebuffer::Default => {
+                ctx.bindings().bind_default_framebuffer(ctx.gl());
+
+                Ok(())
+            }
             Attachments { attachments } => {
                 validate_attachments(ctx.caps(), attachments)?;
 
-                unsafe {
-                    ctx.gl()
-                        .bind_framebuffer(glow::FRAMEBUFFER, Some(ctx.draw_fbo()))
-                };
-
                 bind_attachments(ctx, attachments)
             }
         }
     }
+}
 
-    pub(super) fn unbind(&self, ctx: &ContextShared) {
-        use Framebuffer::*;
+fn validate_attachments(caps: &Caps, attachments: &AttachmentList) -> Result<(), FramebufferError> {
+    for attachment in attachments.iter() {
+        use Attachment::*;
 
-        match self {
-            Framebuffer::Default => {}
-            Attachments { attachments } => {
-                // TODO: Remove overly conservative unbinding.
-                unbind_attachments(ctx, attachments);
+        // OpenGL ES 3.0.6: 4.4.4.2 Whole Framebuffer Completeness
+        // > All attachments must be attachment complete.
+        let format = attachment.internal_format();
+        let renderable = if format.is_color() {
+            format.is_color_renderable(caps)
+        } else {
+            format.is_depth_renderable() || format.is_stencil_renderable()
+        };
 
-                // TODO: Remove overly conservative unbinding.
-                unsafe { ctx.gl().bind_framebuffer(glow::FRAMEBUFFER, None) };
-            }
+        if !renderable {
+            return Err(FramebufferError::NotRenderable(format));
         }
-    }
-}
-
-fn validate_attachments(caps: &Caps, attachments: &[Attachment]) -> Result<(), FramebufferError> {
-    for attachment in attachments {
-        use Attachment::*;
 
         match attachment {
             Texture2d { level, .. } => {
@@ -229,9 +296,9 @@ fn validate_attachments(caps: &Caps, attachments: &[Attachment]) -> Result<(), F
     // > An `INVALID_OPERATION` is generated if `attachment` is
     // > `COLOR_ATTACHMENTm` where `m` is greater than or equal to the value of
     // > `MAX_COLOR_ATTACHMENTS`.
-    if count(ImageInternalFormat::is_color_renderable) > caps.max_color_attachments {
+    if count(ImageInternalFormat::is_color) > caps.max_color_attachments {
         return Err(FramebufferError::TooManyColorAttachments {
-            requested: count(ImageInternalFormat::is_color_renderable),
+            requested: count(ImageInternalFormat::is_color),
             max: caps.max_color_attachments,
         });
     }
@@ -239,9 +306,9 @@ fn validate_attachments(caps: &Caps, attachments: &[Attachment]) -> Result<(), F
     // OpenGL ES 3.0.6: 4.2.1 Selecting Buffers for Writing
     // > An `INVALID_VALUE` error is generated if `n` is negative, or greater
     // > than the value of `MAX_DRAW_BUFFERS`.
-    if count(ImageInternalFormat::is_color_renderable) > caps.max_draw_buffers {
+    if count(ImageInternalFormat::is_color) > caps.max_draw_buffers {
         return Err(FramebufferError::TooManyDrawBuffers {
-            requested: count(ImageInternalFormat::is_color_renderable),
+            requested: count(ImageInternalFormat::is_color),
             max: caps.max_draw_buffers,
         });
     }
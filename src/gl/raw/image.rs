@@ -1,3 +1,7 @@
+use std::rc::Rc;
+
+use super::{Buffer, Caps};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ImageFormat {
     Rgba,
@@ -55,8 +59,15 @@ impl ImageFormat {
 pub enum ImageComponentType {
     U8,
     I8,
+    U16,
+    I16,
+    U32,
+    I32,
     F16,
     F32,
+    UInt2_10_10_10Rev,
+    UInt10F11F11FRev,
+    UInt5_9_9_9Rev,
 }
 
 impl ImageComponentType {
@@ -66,21 +77,38 @@ impl ImageComponentType {
         match self {
             U8 => glow::UNSIGNED_BYTE,
             I8 => glow::BYTE,
+            U16 => glow::UNSIGNED_SHORT,
+            I16 => glow::SHORT,
+            U32 => glow::UNSIGNED_INT,
+            I32 => glow::INT,
             F16 => glow::HALF_FLOAT,
             F32 => glow::FLOAT,
+            UInt2_10_10_10Rev => glow::UNSIGNED_INT_2_10_10_10_REV,
+            UInt10F11F11FRev => glow::UNSIGNED_INT_10F_11F_11F_REV,
+            UInt5_9_9_9Rev => glow::UNSIGNED_INT_5_9_9_9_REV,
         }
     }
 
+    /// Returns the size in bytes of one component, or of one whole pixel for
+    /// packed types.
     pub const fn size_of(self) -> usize {
         use ImageComponentType::*;
 
         match self {
-            U8 => 1,
-            I8 => 1,
-            F16 => 2,
-            F32 => 4,
+            U8 | I8 => 1,
+            U16 | I16 | F16 => 2,
+            U32 | I32 | F32 => 4,
+            UInt2_10_10_10Rev | UInt10F11F11FRev | UInt5_9_9_9Rev => 4,
         }
     }
+
+    /// Returns true if all components of a pixel are packed into a single
+    /// value of this type.
+    pub const fn is_packed(self) -> bool {
+        use ImageComponentType::*;
+
+        matches!(self, UInt2_10_10_10Rev | UInt10F11F11FRev | UInt5_9_9_9Rev)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -103,7 +131,47 @@ pub enum ImageInternalFormat {
     RI8Snorm,
     RF16,
     RF32,
+    RgbaU8Integer,
+    RgbaI8Integer,
+    RgbaU16Integer,
+    RgbaI16Integer,
+    RgbaU32Integer,
+    RgbaI32Integer,
+    RgbU8Integer,
+    RgbI8Integer,
+    RgbU16Integer,
+    RgbI16Integer,
+    RgbU32Integer,
+    RgbI32Integer,
+    RgU8Integer,
+    RgI8Integer,
+    RgU16Integer,
+    RgI16Integer,
+    RgU32Integer,
+    RgI32Integer,
+    RU8Integer,
+    RI8Integer,
+    RU16Integer,
+    RI16Integer,
+    RU32Integer,
+    RI32Integer,
+    Rgb10A2,
+    Rgb10A2Integer,
+    R11G11B10F,
+    Rgb9E5,
+    RgbaEtc2,
+    RgbaEtc2Gamma,
+    RgbA1Etc2,
+    RgbA1Etc2Gamma,
+    RgbEtc2,
+    RgbEtc2Gamma,
+    RgEac,
+    RgEacSnorm,
+    REac,
+    REacSnorm,
     DepthF32,
+    DepthU16,
+    DepthU24,
 }
 
 impl ImageInternalFormat {
@@ -129,7 +197,47 @@ impl ImageInternalFormat {
             RI8Snorm => glow::R8_SNORM,
             RF16 => glow::R16F,
             RF32 => glow::R32F,
+            RgbaU8Integer => glow::RGBA8UI,
+            RgbaI8Integer => glow::RGBA8I,
+            RgbaU16Integer => glow::RGBA16UI,
+            RgbaI16Integer => glow::RGBA16I,
+            RgbaU32Integer => glow::RGBA32UI,
+            RgbaI32Integer => glow::RGBA32I,
+            RgbU8Integer => glow::RGB8UI,
+            RgbI8Integer => glow::RGB8I,
+            RgbU16Integer => glow::RGB16UI,
+            RgbI16Integer => glow::RGB16I,
+            RgbU32Integer => glow::RGB32UI,
+            RgbI32Integer => glow::RGB32I,
+            RgU8Integer => glow::RG8UI,
+            RgI8Integer => glow::RG8I,
+            RgU16Integer => glow::RG16UI,
+            RgI16Integer => glow::RG16I,
+            RgU32Integer => glow::RG32UI,
+            RgI32Integer => glow::RG32I,
+            RU8Integer => glow::R8UI,
+            RI8Integer => glow::R8I,
+            RU16Integer => glow::R16UI,
+            RI16Integer => glow::R16I,
+            RU32Integer => glow::R32UI,
+            RI32Integer => glow::R32I,
+            Rgb10A2 => glow::RGB10_A2,
+            Rgb10A2Integer => glow::RGB10_A2UI,
+            R11G11B10F => glow::R11F_G11F_B10F,
+            Rgb9E5 => glow::RGB9_E5,
+            RgbaEtc2 => glow::COMPRESSED_RGBA8_ETC2_EAC,
+            RgbaEtc2Gamma => glow::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            RgbA1Etc2 => glow::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            RgbA1Etc2Gamma => glow::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            RgbEtc2 => glow::COMPRESSED_RGB8_ETC2,
+            RgbEtc2Gamma => glow::COMPRESSED_SRGB8_ETC2,
+            RgEac => glow::COMPRESSED_RG11_EAC,
+            RgEacSnorm => glow::COMPRESSED_SIGNED_RG11_EAC,
+            REac => glow::COMPRESSED_R11_EAC,
+            REacSnorm => glow::COMPRESSED_SIGNED_R11_EAC,
             DepthF32 => glow::DEPTH_COMPONENT32F,
+            DepthU16 => glow::DEPTH_COMPONENT16,
+            DepthU24 => glow::DEPTH_COMPONENT24,
         }
     }
 
@@ -137,51 +245,176 @@ impl ImageInternalFormat {
         use ImageInternalFormat::*;
 
         match self {
-            RgbaU8 | RgbaU8Gamma | RgbaI8Snorm | RgbaF16 | RgbaF32 => ImageFormat::Rgba,
-            RgbU8 | RgbU8Gamma | RgbI8Snorm | RgbF16 | RgbF32 => ImageFormat::Rgb,
+            RgbaU8 | RgbaU8Gamma | RgbaI8Snorm | RgbaF16 | RgbaF32 | Rgb10A2 => ImageFormat::Rgba,
+            RgbU8 | RgbU8Gamma | RgbI8Snorm | RgbF16 | RgbF32 | R11G11B10F | Rgb9E5 => {
+                ImageFormat::Rgb
+            }
             RgU8 | RgI8Snorm | RgF16 | RgF32 => ImageFormat::Rg,
             RU8 | RI8Snorm | RF16 | RF32 => ImageFormat::R,
-            DepthF32 => ImageFormat::Depth,
+            RgbaU8Integer | RgbaI8Integer | RgbaU16Integer | RgbaI16Integer | RgbaU32Integer
+            | RgbaI32Integer | Rgb10A2Integer => ImageFormat::RgbaInteger,
+            RgbU8Integer | RgbI8Integer | RgbU16Integer | RgbI16Integer | RgbU32Integer
+            | RgbI32Integer => ImageFormat::RgbInteger,
+            RgU8Integer | RgI8Integer | RgU16Integer | RgI16Integer | RgU32Integer
+            | RgI32Integer => ImageFormat::RgInteger,
+            RU8Integer | RI8Integer | RU16Integer | RI16Integer | RU32Integer | RI32Integer => {
+                ImageFormat::RedInteger
+            }
+            RgbaEtc2 | RgbaEtc2Gamma | RgbA1Etc2 | RgbA1Etc2Gamma => ImageFormat::Rgba,
+            RgbEtc2 | RgbEtc2Gamma => ImageFormat::Rgb,
+            RgEac | RgEacSnorm => ImageFormat::Rg,
+            REac | REacSnorm => ImageFormat::R,
+            DepthF32 | DepthU16 | DepthU24 => ImageFormat::Depth,
+        }
+    }
+
+    /// Returns true if this is a block-compressed format.
+    pub const fn is_compressed(self) -> bool {
+        self.block_size().is_some()
+    }
+
+    /// Returns the number of bytes in one 4x4 block of a compressed format,
+    /// or `None` if the format is not compressed.
+    pub const fn block_size(self) -> Option<usize> {
+        use ImageInternalFormat::*;
+
+        // OpenGL ES 3.0.6: Table 3.19
+        match self {
+            RgbEtc2 | RgbEtc2Gamma | RgbA1Etc2 | RgbA1Etc2Gamma | REac | REacSnorm => Some(8),
+            RgbaEtc2 | RgbaEtc2Gamma | RgEac | RgEacSnorm => Some(16),
+            _ => None,
         }
     }
 
     // FIXME: Remove this and infer it, similar to `to_format`.
     pub fn matches_type(self, ty: ImageComponentType) -> bool {
+        use ImageComponentType as Ty;
         use ImageInternalFormat::*;
 
+        // OpenGL ES 3.0.6: Table 3.2
         match self {
-            RgbaU8 | RgbU8 | RgU8 | RU8 => ty == ImageComponentType::U8,
-            RgbaU8Gamma | RgbU8Gamma => ty == ImageComponentType::U8,
-            RgbaI8Snorm | RgbI8Snorm | RgI8Snorm | RI8Snorm => ty == ImageComponentType::I8,
-            RgbaF16 | RgbF16 | RgF16 | RF16 => ty == ImageComponentType::F16,
-            RgbaF32 | RgbF32 | RgF32 | RF32 => ty == ImageComponentType::F32,
-            DepthF32 => ty == ImageComponentType::F32,
+            RgbaU8 | RgbU8 | RgU8 | RU8 => ty == Ty::U8,
+            RgbaU8Gamma | RgbU8Gamma => ty == Ty::U8,
+            RgbaI8Snorm | RgbI8Snorm | RgI8Snorm | RI8Snorm => ty == Ty::I8,
+            RgbaF16 | RgbF16 | RgF16 | RF16 => ty == Ty::F16,
+            RgbaF32 | RgbF32 | RgF32 | RF32 => ty == Ty::F32,
+            RgbaU8Integer | RgbU8Integer | RgU8Integer | RU8Integer => ty == Ty::U8,
+            RgbaI8Integer | RgbI8Integer | RgI8Integer | RI8Integer => ty == Ty::I8,
+            RgbaU16Integer | RgbU16Integer | RgU16Integer | RU16Integer => ty == Ty::U16,
+            RgbaI16Integer | RgbI16Integer | RgI16Integer | RI16Integer => ty == Ty::I16,
+            RgbaU32Integer | RgbU32Integer | RgU32Integer | RU32Integer => ty == Ty::U32,
+            RgbaI32Integer | RgbI32Integer | RgI32Integer | RI32Integer => ty == Ty::I32,
+            Rgb10A2 | Rgb10A2Integer => ty == Ty::UInt2_10_10_10Rev,
+            R11G11B10F => ty == Ty::UInt10F11F11FRev,
+            Rgb9E5 => ty == Ty::UInt5_9_9_9Rev,
+            // Compressed data is passed as raw bytes.
+            RgbaEtc2 | RgbaEtc2Gamma | RgbA1Etc2 | RgbA1Etc2Gamma | RgbEtc2 | RgbEtc2Gamma
+            | RgEac | RgEacSnorm | REac | REacSnorm => ty == Ty::U8,
+            DepthF32 => ty == Ty::F32,
+            DepthU16 => ty == Ty::U16 || ty == Ty::U32,
+            DepthU24 => ty == Ty::U32,
         }
     }
 
-    pub fn is_color_renderable(&self) -> bool {
+    /// Returns true if this is a color format, as opposed to a depth or
+    /// stencil format.
+    pub fn is_color(&self) -> bool {
+        !matches!(
+            self.to_format(),
+            ImageFormat::Depth | ImageFormat::DepthStencil
+        )
+    }
+
+    /// Returns true if this is an unnormalized integer format, which must be
+    /// sampled with an integer sampler.
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self.to_format(),
+            ImageFormat::RgbaInteger
+                | ImageFormat::RgbInteger
+                | ImageFormat::RgInteger
+                | ImageFormat::RedInteger
+        )
+    }
+
+    pub fn is_color_renderable(&self, caps: &Caps) -> bool {
         use ImageInternalFormat::*;
 
+        // OpenGL ES 3.0.6: Table 3.13
         match self {
             RgbaU8 | RgbU8 | RgU8 | RU8 => true,
             RgbaU8Gamma => true,
-            RgbU8Gamma => {
-                // FIXME: Does this always hold?
-                false
-            }
+            RgbU8Gamma => false,
             RgbaI8Snorm | RgbI8Snorm | RgI8Snorm | RI8Snorm => false,
-            RgbaF16 | RgbF16 | RgF16 | RF16 => {
-                // FIXME: This should rely on caps. Default OpenGL ES 3.0 does
-                // not support rendering to half color textures!
-                true
+            RgbaU8Integer | RgbaI8Integer | RgbaU16Integer | RgbaI16Integer | RgbaU32Integer
+            | RgbaI32Integer => true,
+            RgU8Integer | RgI8Integer | RgU16Integer | RgI16Integer | RgU32Integer
+            | RgI32Integer => true,
+            RU8Integer | RI8Integer | RU16Integer | RI16Integer | RU32Integer | RI32Integer => true,
+            RgbU8Integer | RgbI8Integer | RgbU16Integer | RgbI16Integer | RgbU32Integer
+            | RgbI32Integer => false,
+            Rgb10A2 | Rgb10A2Integer => true,
+            Rgb9E5 => false,
+            RgbaEtc2 | RgbaEtc2Gamma | RgbA1Etc2 | RgbA1Etc2Gamma | RgbEtc2 | RgbEtc2Gamma
+            | RgEac | RgEacSnorm | REac | REacSnorm => false,
+            // `EXT_color_buffer_float` covers the float formats except for
+            // RGB ones. `EXT_color_buffer_half_float` additionally covers
+            // RGB16F.
+            RgbaF16 | RgF16 | RF16 => caps.color_buffer_half_float || caps.color_buffer_float,
+            RgbF16 => caps.color_buffer_half_float,
+            RgbaF32 | RgF32 | RF32 | R11G11B10F => caps.color_buffer_float,
+            RgbF32 => false,
+            DepthF32 | DepthU16 | DepthU24 => false,
+        }
+    }
+
+    pub fn is_texture_filterable(&self) -> bool {
+        use ImageInternalFormat::*;
+
+        // OpenGL ES 3.0.6: Table 3.13
+        //
+        // FIXME: This should rely on caps. `OES_texture_float_linear` makes
+        // 32-bit float formats filterable.
+        !self.is_integer()
+            && !self.is_depth_renderable()
+            && !matches!(self, RgbaF32 | RgbF32 | RgF32 | RF32)
+    }
+
+    /// Returns the format and type with which pixels of this color format
+    /// can be read back, or `None` if the format can not be read back.
+    pub fn read_format(&self) -> Option<(ImageFormat, ImageComponentType)> {
+        use ImageInternalFormat::*;
+
+        // OpenGL ES 3.0.6: 4.3.1 Reading Pixels
+        // > For normalized fixed-point rendering surfaces, the combination
+        // > format RGBA and type UNSIGNED_BYTE is accepted. For signed integer
+        // > rendering surfaces, the combination format RGBA_INTEGER and type
+        // > INT is accepted. For unsigned integer rendering surfaces, the
+        // > combination format RGBA_INTEGER and type UNSIGNED_INT is
+        // > accepted.
+        //
+        // The floating-point combination format RGBA and type FLOAT is added
+        // by `EXT_color_buffer_float`.
+        if !self.is_color() {
+            return None;
+        }
+
+        match self {
+            RgbaU8 | RgbaU8Gamma | RgbU8 | RgU8 | RU8 | Rgb10A2 => {
+                Some((ImageFormat::Rgba, ImageComponentType::U8))
             }
-            RgbaF32 | RgF32 | RF32 => {
-                // FIXME: This should rely on caps. Default OpenGL ES 3.0 does
-                // not support rendering to float color textures!
-                true
+            RgbaF16 | RgbF16 | RgF16 | RF16 | RgbaF32 | RgF32 | RF32 | R11G11B10F => {
+                Some((ImageFormat::Rgba, ImageComponentType::F32))
             }
-            RgbF32 => false,
-            DepthF32 => false,
+            RgbaU8Integer | RgbaU16Integer | RgbaU32Integer | RgU8Integer | RgU16Integer
+            | RgU32Integer | RU8Integer | RU16Integer | RU32Integer | Rgb10A2Integer => {
+                Some((ImageFormat::RgbaInteger, ImageComponentType::U32))
+            }
+            RgbaI8Integer | RgbaI16Integer | RgbaI32Integer | RgI8Integer | RgI16Integer
+            | RgI32Integer | RI8Integer | RI16Integer | RI32Integer => {
+                Some((ImageFormat::RgbaInteger, ImageComponentType::I32))
+            }
+            _ => None,
         }
     }
 
@@ -190,10 +423,7 @@ impl ImageInternalFormat {
 
         // FIXME: This should rely on caps (maybe).
 
-        match self {
-            DepthF32 => true,
-            _ => false,
-        }
+        matches!(self, DepthF32 | DepthU16 | DepthU24)
     }
 
     pub fn is_stencil_renderable(&self) -> bool {
@@ -203,21 +433,54 @@ impl ImageInternalFormat {
     }
 }
 
+#[doc(hidden)]
+#[derive(Clone)]
+pub enum ImageData<'a> {
+    /// The image is filled with zeros.
+    Zeroed,
+
+    /// The image data is given by a slice in CPU memory.
+    Slice(&'a [u8]),
+
+    /// The image data is owned by the image.
+    Owned(Rc<[u8]>),
+
+    /// The image data is read from a pixel unpack buffer, starting at byte
+    /// `offset`.
+    UnpackBuffer { buffer: &'a Buffer, offset: usize },
+}
+
 #[doc(hidden)]
 #[derive(Clone)]
 pub struct Image<'a> {
     pub size: [u32; 2],
     pub ty: ImageComponentType,
     pub internal_format: ImageInternalFormat,
-    pub data: Option<&'a [u8]>,
+    pub data: ImageData<'a>,
 }
 
 impl<'a> Image<'a> {
     pub fn required_data_len(&self) -> usize {
         let width = self.size[0] as usize;
         let height = self.size[1] as usize;
+
+        // OpenGL ES 3.0.6: 3.8.6 Compressed Texture Images
+        // > `imageSize = ceil(width / 4) * ceil(height / 4) * blockSize`
+        if let Some(block_size) = self.internal_format.block_size() {
+            return width
+                .div_ceil(4)
+                .checked_mul(height.div_ceil(4))
+                .unwrap()
+                .checked_mul(block_size)
+                .unwrap();
+        }
+
         let bytes = self.ty.size_of();
-        let num_components = self.internal_format.to_format().size();
+        let num_components = if self.ty.is_packed() {
+            1
+        } else {
+            self.internal_format.to_format().size()
+        };
 
         width
             .checked_mul(height)
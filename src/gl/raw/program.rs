@@ -1,37 +1,51 @@
-use std::{collections::BTreeSet, rc::Rc};
+use std::{collections::BTreeSet, ops::Range, rc::Rc};
 
 use glow::HasContext;
 
-use crate::sl::program_def::{ProgramDef, UniformSamplerDef};
+use crate::sl::{
+    dag::{BuiltInType, Type},
+    program_def::{ProgramDef, UniformSamplerDef},
+    transpile::uniform_path,
+};
 
 use super::{
-    context::ContextShared, error::check_gl_error, vertex_layout::VertexAttributeLayout, Buffer,
-    DrawError, DrawSettings, Framebuffer, ProgramError, ProgramValidationError, Sampler,
-    VertexSpec,
+    context::ContextShared,
+    error::check_gl_error,
+    vertex_layout::{vertex_block_locations, vertex_block_num_locations, VertexAttributeLayout},
+    Buffer, Caps, DrawError, DrawSettings, Framebuffer, ProgramError, ProgramValidationError,
+    Sampler, VertexSpec,
 };
 
 pub struct Program {
     ctx: Rc<ContextShared>,
     def: ProgramDef,
     id: glow::Program,
+
+    /// The first attribute location of each vertex block.
+    vertex_block_locations: Vec<usize>,
 }
 
 impl Program {
     pub(super) fn new(ctx: Rc<ContextShared>, def: ProgramDef) -> Result<Self, ProgramError> {
-        validate_program_def(&def)?;
+        validate_program_def(&def, ctx.caps())?;
 
         let gl = ctx.gl();
 
-        check_gl_error(gl, "before creating program").map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "before creating program")?;
+
+        // Overlapping locations have already been rejected by validation.
+        let vertex_block_locations = vertex_block_locations(def.vertex_block_defs.iter())
+            .expect("overlapping vertex attribute locations");
 
         let id = unsafe { gl.create_program() }.map_err(ProgramError::ProgramCreation)?;
         let program = Program {
             ctx: ctx.clone(),
             def,
             id,
+            vertex_block_locations,
         };
 
-        check_gl_error(gl, "after creating program").map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "after creating program")?;
 
         // Compile and attach shaders.
         let vertex_shader = Shader::new(
@@ -41,7 +55,7 @@ impl Program {
         )?
         .attach(program.id);
 
-        check_gl_error(gl, "after compiling vertex shader").map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "after compiling vertex shader")?;
 
         let fragment_shader = Shader::new(
             ctx.clone(),
@@ -50,14 +64,16 @@ impl Program {
         )?
         .attach(program.id);
 
-        check_gl_error(gl, "after compiling fragment shader").map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "after compiling fragment shader")?;
 
         // Bind vertex attributes. This needs to be done before linking the
         // program.
         {
-            let mut index = 0;
+            let locations = &program.vertex_block_locations;
+
+            for (block_def, &location) in program.def.vertex_block_defs.iter().zip(locations) {
+                let mut index = location;
 
-            for block_def in &program.def.vertex_block_defs {
                 for attribute in &block_def.attributes {
                     unsafe {
                         gl.bind_attrib_location(
@@ -67,7 +83,7 @@ impl Program {
                         );
                     }
 
-                    let attribute_info = VertexAttributeLayout::new(attribute.ty)
+                    let attribute_info = VertexAttributeLayout::new(attribute.ty, attribute.format)
                         .map_err(ProgramError::InvalidVertexAttribute)?;
 
                     // Some attributes (e.g. matrices) take up multiple
@@ -78,7 +94,7 @@ impl Program {
             }
         }
 
-        check_gl_error(gl, "after binding vertex attributes").map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "after binding vertex attributes")?;
 
         // Link the program.
         let link_status = unsafe {
@@ -90,7 +106,7 @@ impl Program {
             gl.get_program_link_status(program.id)
         };
 
-        check_gl_error(gl, "after linking the program").map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "after linking the program")?;
 
         if !link_status {
             let vertex_shader_info = unsafe { gl.get_shader_info_log(vertex_shader.shader.id) };
@@ -105,9 +121,7 @@ impl Program {
         }
 
         // Set texture units.
-        unsafe {
-            gl.use_program(Some(program.id));
-        }
+        program.ctx.bindings().use_program(gl, Some(program.id));
 
         for sampler_def in &program.def.uniform_sampler_defs {
             let location = unsafe { gl.get_uniform_location(program.id, &sampler_def.name) };
@@ -123,11 +137,7 @@ impl Program {
             }
         }
 
-        check_gl_error(gl, "after setting texture units").map_err(ProgramError::Unexpected)?;
-
-        unsafe {
-            gl.use_program(None);
-        }
+        check_gl_error(gl, "after setting texture units")?;
 
         // Set uniform block locations.
         for uniform_def in &program.def.uniform_block_defs {
@@ -146,26 +156,34 @@ impl Program {
             }
         }
 
-        check_gl_error(gl, "after setting uniform block locations")
-            .map_err(ProgramError::Unexpected)?;
+        check_gl_error(gl, "after setting uniform block locations")?;
 
         Ok(program)
     }
 
-    /// # Panics
+    /// Uniform blocks that are provided by a global uniform buffer are given
+    /// as `None` in `uniform_buffers`.
     ///
-    /// Panics under any of the following conditions:
+    /// # Errors
+    ///
+    /// Returns an error under any of the following conditions, without
+    /// drawing anything:
     /// 1. The supplied objects do not belong to the same `glow::Context`.
     /// 2. The wrong number of uniform buffers is supplied.
-    /// 3. The wrong number of samplers is supplied.
-    /// 4. The vertex stream is not compatible with the program.
+    /// 3. A uniform buffer range is out of bounds or not aligned to
+    ///    `UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+    /// 4. The wrong number of samplers is supplied.
+    /// 5. The vertex stream is not compatible with the program, or accesses
+    ///    vertices or instances that are out of bounds.
+    /// 6. A count or offset of the vertex stream does not fit into the integer
+    ///    type that OpenGL takes for it.
     ///
     /// # Safety
     ///
     /// TODO
     pub unsafe fn draw(
         &self,
-        uniform_buffers: &[&Buffer],
+        uniform_buffers: &[Option<(Rc<Buffer>, Range<usize>)>],
         samplers: &[Sampler],
         vertex_spec: &VertexSpec,
         framebuffer: &Framebuffer,
@@ -175,77 +193,124 @@ impl Program {
         let gl = ctx.gl();
         let def = &self.def;
 
-        assert_eq!(uniform_buffers.len(), def.uniform_block_defs.len());
-        assert_eq!(samplers.len(), def.uniform_sampler_defs.len());
-        assert!(vertex_spec.is_compatible(&self.def.vertex_block_defs));
-
-        framebuffer.bind(&self.ctx)?;
+        if uniform_buffers.len() != def.uniform_block_defs.len() {
+            return Err(DrawError::UniformBlockCountMismatch {
+                expected: def.uniform_block_defs.len(),
+                got: uniform_buffers.len(),
+            });
+        }
 
-        let framebuffer_size = framebuffer.size(&self.ctx);
+        if samplers.len() != def.uniform_sampler_defs.len() {
+            return Err(DrawError::SamplerCountMismatch {
+                expected: def.uniform_sampler_defs.len(),
+                got: samplers.len(),
+            });
+        }
 
-        // `set_draw_settings` also clears the screen, so it must come after
-        // binding the framebuffer.
-        ctx.set_draw_settings(settings, framebuffer_size);
+        vertex_spec.check_compatible(&self.def.vertex_block_defs)?;
 
-        unsafe {
-            gl.use_program(Some(self.id));
-        }
+        // OpenGL ES 3.0.6: 2.10.1.1 Binding Buffer Objects to Indexed Targets
+        // > An `INVALID_VALUE` error is generated if `offset` is not a
+        // > multiple of the value of `UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+        let alignment = usize::try_from(ctx.caps().uniform_buffer_offset_alignment).unwrap();
 
         for (buffer, block_def) in uniform_buffers.iter().zip(&def.uniform_block_defs) {
-            assert!(buffer.context().ref_eq(ctx));
+            let Some((buffer, range)) = buffer else {
+                if ctx.global_uniform_buffer(block_def.location).is_none() {
+                    return Err(DrawError::MissingGlobalUniformBlock(
+                        block_def.block_name.clone(),
+                    ));
+                }
 
-            let location = u32::try_from(block_def.location).unwrap();
+                continue;
+            };
 
-            unsafe {
-                gl.bind_buffer_base(glow::UNIFORM_BUFFER, location, Some(buffer.id()));
+            if !buffer.context().ref_eq(ctx) {
+                return Err(DrawError::ContextMismatch(format!(
+                    "uniform buffer for block {}",
+                    block_def.block_name
+                )));
+            }
+
+            if buffer.is_mapped() {
+                return Err(DrawError::UniformBufferMapped(block_def.block_name.clone()));
+            }
+
+            if range.start >= range.end || range.end > buffer.len() {
+                return Err(DrawError::UniformBufferOutOfBounds {
+                    block: block_def.block_name.clone(),
+                    start: range.start,
+                    end: range.end,
+                    buffer_len: buffer.len(),
+                });
+            }
+
+            if !range.start.is_multiple_of(alignment) {
+                return Err(DrawError::UniformBufferMisaligned {
+                    block: block_def.block_name.clone(),
+                    offset: range.start,
+                    alignment,
+                });
             }
         }
 
         for (sampler, sampler_def) in samplers.iter().zip(&def.uniform_sampler_defs) {
-            assert!(sampler.context().ref_eq(ctx));
+            if !sampler.context().ref_eq(ctx) {
+                return Err(DrawError::ContextMismatch(format!(
+                    "sampler {}",
+                    sampler_def.name
+                )));
+            }
 
-            let unit = texture_unit_gl(sampler_def);
-            unsafe {
-                gl.active_texture(unit);
+            // OpenGL ES 3.0.6: 4.4.3 Feedback Loops Between Textures and the
+            // Framebuffer
+            // Sampling a texture level that is attached to the bound
+            // framebuffer leads to undefined results.
+            if let Some(attachment) = framebuffer.sampled_attachment(sampler) {
+                return Err(DrawError::FeedbackLoop {
+                    sampler: uniform_path(&sampler_def.name).to_string(),
+                    attachment,
+                });
             }
-            sampler.bind();
         }
 
-        vertex_spec.draw(ctx);
+        vertex_spec.validate(ctx)?;
 
-        // TODO: Remove overly conservative unbinding.
-        for (sampler, sampler_def) in samplers.iter().zip(&def.uniform_sampler_defs) {
-            let unit = texture_unit_gl(sampler_def);
+        framebuffer.bind(&self.ctx)?;
 
-            unsafe {
-                gl.active_texture(unit);
-            }
+        let framebuffer_size = framebuffer.size(&self.ctx);
 
-            sampler.unbind();
-        }
+        // `set_draw_settings` also clears the screen, so it must come after
+        // binding the framebuffer.
+        ctx.set_draw_settings(settings, framebuffer_size);
 
-        // TODO: Remove overly conservative unbinding.
-        for block_def in &def.uniform_block_defs {
-            let location = u32::try_from(block_def.location).unwrap();
+        ctx.bindings().use_program(gl, Some(self.id));
 
-            unsafe {
-                gl.bind_buffer_base(glow::UNIFORM_BUFFER, location, None);
-            }
+        for (buffer, block_def) in uniform_buffers.iter().zip(&def.uniform_block_defs) {
+            // Blocks that are not given a buffer use the global buffer, which
+            // may have been replaced by a previous draw call.
+            let (buffer, range) = match buffer {
+                Some((buffer, range)) => (buffer.id(), range.clone()),
+                None => ctx
+                    .global_uniform_buffer(block_def.location)
+                    .expect("global uniform buffer was checked above"),
+            };
+
+            ctx.bind_uniform_buffer_range(block_def.location, buffer, range);
         }
 
-        // TODO: Remove overly conservative unbinding.
-        gl.bind_buffer(glow::UNIFORM_BUFFER, None);
-
-        // TODO: Remove overly conservative unbinding.
-        unsafe {
-            gl.use_program(None);
+        for (sampler, sampler_def) in samplers.iter().zip(&def.uniform_sampler_defs) {
+            sampler.bind(texture_unit_gl(sampler_def));
         }
 
-        // TODO: Remove overly conservative unbinding.
-        framebuffer.unbind(&self.ctx);
+        vertex_spec.draw(
+            ctx,
+            &self.def.vertex_block_defs,
+            &self.vertex_block_locations,
+        )?;
 
         #[cfg(debug_assertions)]
-        check_gl_error(gl, "after draw").map_err(DrawError::Error)?;
+        check_gl_error(gl, "after draw")?;
 
         Ok(())
     }
@@ -255,6 +320,8 @@ impl Drop for Program {
     fn drop(&mut self) {
         let gl = self.ctx.gl();
 
+        self.ctx.bindings().forget_program(gl, self.id);
+
         unsafe {
             gl.delete_program(self.id);
         }
@@ -318,7 +385,7 @@ impl Drop for AttachedShader {
     }
 }
 
-fn validate_program_def(def: &ProgramDef) -> Result<(), ProgramValidationError> {
+fn validate_program_def(def: &ProgramDef, caps: &Caps) -> Result<(), ProgramValidationError> {
     {
         let mut names: BTreeSet<_> = BTreeSet::new();
 
@@ -341,6 +408,17 @@ fn validate_program_def(def: &ProgramDef) -> Result<(), ProgramValidationError>
         }
     }
 
+    // OpenGL ES 3.0.6: 2.11.5 Vertex Attributes
+    // > Binding more than one attribute name to the same location is referred
+    // > to as aliasing, and is not permitted in OpenGL ES Shading Language
+    // > 3.00 vertex shaders. `LinkProgram` will fail when this condition
+    // > exists.
+    if let Err(location) = vertex_block_locations(def.vertex_block_defs.iter()) {
+        return Err(ProgramValidationError::DuplicateVertexAttributeLocation(
+            location,
+        ));
+    }
+
     {
         let mut names: BTreeSet<_> = BTreeSet::new();
 
@@ -365,14 +443,509 @@ fn validate_program_def(def: &ProgramDef) -> Result<(), ProgramValidationError>
         }
     }
 
-    // FIXME: Check that the number of fragment fields is <= MAX_DRAW_BUFFERS.
+    validate_program_def_against_caps(def, caps)
+}
+
+fn validate_program_def_against_caps(
+    def: &ProgramDef,
+    caps: &Caps,
+) -> Result<(), ProgramValidationError> {
+    let to_u32 = |count: usize| u32::try_from(count).unwrap_or(u32::MAX);
+
+    {
+        let locations = vertex_block_locations(def.vertex_block_defs.iter())
+            .expect("overlapping vertex attribute locations");
+
+        // The number of used locations is given by the end of the highest
+        // block, since user-specified locations can leave gaps.
+        let locations = def
+            .vertex_block_defs
+            .iter()
+            .zip(locations)
+            .map(|(block_def, location)| location + vertex_block_num_locations(block_def))
+            .max()
+            .unwrap_or(0);
+
+        if to_u32(locations) > caps.max_vertex_attribs {
+            return Err(ProgramValidationError::TooManyVertexAttributes {
+                requested: to_u32(locations),
+                max: caps.max_vertex_attribs,
+            });
+        }
+    }
+
+    for block_def in &def.uniform_block_defs {
+        // OpenGL ES 3.0.6: 2.12.6 Uniform Variables
+        // > If `uniformBlockBinding` is greater than or equal to the value of
+        // > `MAX_UNIFORM_BUFFER_BINDINGS`, the error `INVALID_VALUE` is
+        // > generated.
+        if to_u32(block_def.location) >= caps.max_uniform_buffer_bindings {
+            return Err(ProgramValidationError::UniformBlockLocationOutOfRange {
+                location: block_def.location,
+                max: caps.max_uniform_buffer_bindings,
+            });
+        }
+
+        if to_u32(block_def.size) > caps.max_uniform_block_size {
+            return Err(ProgramValidationError::UniformBlockTooLarge {
+                name: block_def.block_name.clone(),
+                size: block_def.size,
+                max: caps.max_uniform_block_size,
+            });
+        }
+    }
+
+    for sampler_def in &def.uniform_sampler_defs {
+        // OpenGL ES 3.0.6: 2.12.7 Samplers
+        // > Setting a sampler's value to `i` selects texture image unit number
+        // > `i`. The values of `i` range from zero to the implementation-
+        // > dependent maximum supported number of texture image units minus
+        // > one.
+        if to_u32(sampler_def.texture_unit) >= caps.max_combined_texture_image_units {
+            return Err(ProgramValidationError::TextureUnitOutOfRange {
+                texture_unit: sampler_def.texture_unit,
+                max: caps.max_combined_texture_image_units,
+            });
+        }
+    }
+
+    // Samplers are declared in both shader stages, but the vertex shader
+    // typically does not use them, so we only check the fragment shader's
+    // limit here.
+    if to_u32(def.uniform_sampler_defs.len()) > caps.max_texture_image_units {
+        return Err(ProgramValidationError::TooManySamplers {
+            requested: to_u32(def.uniform_sampler_defs.len()),
+            max: caps.max_texture_image_units,
+        });
+    }
+
+    {
+        let varying_vectors = count_varying_vectors(def.varying_defs.iter().map(|def| &def.ty));
+
+        if to_u32(varying_vectors) > caps.max_varying_vectors {
+            return Err(ProgramValidationError::TooManyVaryingVectors {
+                requested: to_u32(varying_vectors),
+                max: caps.max_varying_vectors,
+            });
+        }
+    }
+
+    // OpenGL ES 3.0.6: 4.2.1 Selecting Buffers for Writing
+    // > An `INVALID_VALUE` error is generated if `n` is negative, or greater
+    // > than the value of `MAX_DRAW_BUFFERS`.
+    if to_u32(def.fragment_output_defs.len()) > caps.max_draw_buffers {
+        return Err(ProgramValidationError::TooManyFragmentOutputs {
+            requested: to_u32(def.fragment_output_defs.len()),
+            max: caps.max_draw_buffers,
+        });
+    }
 
     Ok(())
 }
 
+/// Returns the number of vectors that the given varying types occupy.
+///
+/// This approximates the packing rules of the OpenGL ES Shading Language:
+/// every vector of three or four components occupies a row on its own,
+/// vectors of two components share rows in pairs, and scalars fill up the
+/// remaining space.
+fn count_varying_vectors<'a>(types: impl Iterator<Item = &'a Type>) -> usize {
+    // The number of columns of width 1, 2, 3 and 4 respectively.
+    fn count_columns(ty: &Type, columns: &mut [usize; 4]) {
+        use BuiltInType::*;
+
+        match ty {
+            Type::BuiltIn(ty) => match ty {
+                F32 | I32 | U32 | Bool => columns[0] += 1,
+                Vec2 | IVec2 | UVec2 | BVec2 => columns[1] += 1,
+                Vec3 | IVec3 | UVec3 | BVec3 => columns[2] += 1,
+                Vec4 | IVec4 | UVec4 | BVec4 => columns[3] += 1,
+                Mat2 => columns[1] += 2,
+                Mat3 => columns[2] += 3,
+                Mat4 => columns[3] += 4,
+                Sampler(_) => {}
+            },
+            Type::Struct(ty) => {
+                for (_, field_ty) in &ty.fields {
+                    count_columns(field_ty, columns);
+                }
+            }
+            Type::Array(ty) => {
+                for _ in 0..ty.len {
+                    count_columns(&ty.ty, columns);
+                }
+            }
+        }
+    }
+
+    let mut columns = [0; 4];
+
+    for ty in types {
+        count_columns(ty, &mut columns);
+    }
+
+    let [scalars, vec2s, vec3s, vec4s] = columns;
+
+    // Scalars can use the last component of rows holding a vector of three
+    // components, and the unused half of a row holding a single vector of two
+    // components.
+    let free_components = vec3s + (vec2s % 2) * 2;
+
+    vec4s + vec3s + vec2s.div_ceil(2) + scalars.saturating_sub(free_components).div_ceil(4)
+}
+
 fn texture_unit_gl(sampler_def: &UniformSamplerDef) -> u32 {
     u32::try_from(sampler_def.texture_unit)
         .unwrap()
         .checked_add(glow::TEXTURE0)
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, rc::Rc};
+
+    use crate::{
+        gl::raw::{Caps, GlVersion, ProgramValidationError},
+        sl::{
+            dag::{ArrayType, BuiltInType, SamplerType, StructType, Type},
+            program_def::{
+                FragmentOutputDef, InterpolationQualifier, ProgramDef, UniformBlockDef,
+                UniformSamplerDef, VaryingDef, VertexAttributeDef, VertexAttributeFormat,
+                VertexBlockDef,
+            },
+        },
+    };
+
+    use super::{count_varying_vectors, validate_program_def, validate_program_def_against_caps};
+
+    fn caps() -> Caps {
+        Caps {
+            max_texture_size: 4096,
+            max_color_attachments: 4,
+            max_draw_buffers: 4,
+            max_vertex_attribs: 16,
+            max_uniform_buffer_bindings: 24,
+            max_uniform_block_size: 16384,
+            uniform_buffer_offset_alignment: 256,
+            max_combined_texture_image_units: 32,
+            max_vertex_texture_image_units: 16,
+            max_texture_image_units: 16,
+            max_varying_vectors: 15,
+            max_samples: 4,
+            version: GlVersion {
+                major: 3,
+                minor: 0,
+                is_embedded: true,
+            },
+            extensions: Default::default(),
+            disjoint_timer_query: false,
+            color_buffer_float: false,
+            color_buffer_half_float: false,
+        }
+    }
+
+    fn vertex_block_def(num_attributes: usize, location: Option<usize>) -> VertexBlockDef {
+        let attributes = (0..num_attributes)
+            .map(|i| VertexAttributeDef {
+                name: format!("attribute_{i}"),
+                ty: BuiltInType::Vec4,
+                offset: i * 16,
+                format: VertexAttributeFormat::Native,
+            })
+            .collect();
+
+        VertexBlockDef {
+            attributes,
+            location,
+        }
+    }
+
+    fn uniform_block_def(location: usize, size: usize) -> UniformBlockDef {
+        UniformBlockDef {
+            block_name: format!("block_{location}"),
+            arg_name: format!("arg_{location}"),
+            ty: Type::BuiltIn(BuiltInType::Vec4),
+            location,
+            size,
+            type_id: TypeId::of::<()>(),
+        }
+    }
+
+    fn sampler_def(texture_unit: usize) -> UniformSamplerDef {
+        UniformSamplerDef {
+            name: format!("sampler_{texture_unit}"),
+            ty: SamplerType::ColorSampler2d,
+            texture_unit,
+        }
+    }
+
+    fn varying_def(ty: Type) -> VaryingDef {
+        VaryingDef {
+            name: "varying".to_string(),
+            ty,
+            interpolation: InterpolationQualifier::Smooth,
+        }
+    }
+
+    fn fragment_output_def(location: usize) -> FragmentOutputDef {
+        FragmentOutputDef {
+            name: format!("output_{location}"),
+            ty: Type::BuiltIn(BuiltInType::Vec4),
+            location,
+        }
+    }
+
+    fn validate(def: ProgramDef) -> Result<(), ProgramValidationError> {
+        validate_program_def_against_caps(&def, &caps())
+    }
+
+    #[test]
+    fn program_within_limits_is_valid() {
+        let def = ProgramDef {
+            uniform_block_defs: vec![uniform_block_def(23, 16384)],
+            uniform_sampler_defs: (16..32).map(sampler_def).collect(),
+            vertex_block_defs: vec![vertex_block_def(8, None), vertex_block_def(8, None)],
+            varying_defs: vec![varying_def(Type::BuiltIn(BuiltInType::Mat3))],
+            fragment_output_defs: (0..4).map(fragment_output_def).collect(),
+            ..Default::default()
+        };
+
+        assert!(validate(def).is_ok());
+    }
+
+    #[test]
+    fn duplicate_uniform_block_locations() {
+        let def = ProgramDef {
+            uniform_block_defs: vec![
+                uniform_block_def(1, 16),
+                UniformBlockDef {
+                    block_name: "other_block".to_string(),
+                    ..uniform_block_def(1, 16)
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate_program_def(&def, &caps()),
+            Err(ProgramValidationError::DuplicateUniformBlockLocation(1))
+        ));
+    }
+
+    #[test]
+    fn duplicate_sampler_texture_units() {
+        let def = ProgramDef {
+            uniform_sampler_defs: vec![
+                sampler_def(3),
+                UniformSamplerDef {
+                    name: "other_sampler".to_string(),
+                    ..sampler_def(3)
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate_program_def(&def, &caps()),
+            Err(ProgramValidationError::DuplicateSamplerTextureUnit(3))
+        ));
+    }
+
+    #[test]
+    fn overlapping_vertex_block_locations() {
+        let def = ProgramDef {
+            vertex_block_defs: vec![vertex_block_def(2, Some(0)), vertex_block_def(1, Some(1))],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate_program_def(&def, &caps()),
+            Err(ProgramValidationError::DuplicateVertexAttributeLocation(1))
+        ));
+    }
+
+    #[test]
+    fn too_many_vertex_attributes() {
+        let def = ProgramDef {
+            vertex_block_defs: vec![vertex_block_def(8, None), vertex_block_def(9, None)],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::TooManyVertexAttributes {
+                requested: 17,
+                max: 16,
+            })
+        ));
+    }
+
+    #[test]
+    fn vertex_attribute_gaps_count_towards_limit() {
+        let def = ProgramDef {
+            vertex_block_defs: vec![vertex_block_def(1, Some(16))],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::TooManyVertexAttributes {
+                requested: 17,
+                max: 16,
+            })
+        ));
+    }
+
+    #[test]
+    fn uniform_block_location_out_of_range() {
+        let def = ProgramDef {
+            uniform_block_defs: vec![uniform_block_def(24, 16)],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::UniformBlockLocationOutOfRange {
+                location: 24,
+                max: 24,
+            })
+        ));
+    }
+
+    #[test]
+    fn uniform_block_too_large() {
+        let def = ProgramDef {
+            uniform_block_defs: vec![uniform_block_def(0, 16400)],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::UniformBlockTooLarge {
+                name,
+                size: 16400,
+                max: 16384,
+            }) if name == "block_0"
+        ));
+    }
+
+    #[test]
+    fn texture_unit_out_of_range() {
+        let def = ProgramDef {
+            uniform_sampler_defs: vec![sampler_def(32)],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::TextureUnitOutOfRange {
+                texture_unit: 32,
+                max: 32,
+            })
+        ));
+    }
+
+    #[test]
+    fn too_many_samplers() {
+        let def = ProgramDef {
+            uniform_sampler_defs: (0..17).map(sampler_def).collect(),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::TooManySamplers {
+                requested: 17,
+                max: 16,
+            })
+        ));
+    }
+
+    #[test]
+    fn too_many_varying_vectors() {
+        let ty = Type::Array(ArrayType {
+            ty: Box::new(Type::BuiltIn(BuiltInType::Vec4)),
+            len: 16,
+        });
+
+        let def = ProgramDef {
+            varying_defs: vec![varying_def(ty)],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::TooManyVaryingVectors {
+                requested: 16,
+                max: 15,
+            })
+        ));
+    }
+
+    #[test]
+    fn too_many_fragment_outputs() {
+        let def = ProgramDef {
+            fragment_output_defs: (0..5).map(fragment_output_def).collect(),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            validate(def),
+            Err(ProgramValidationError::TooManyFragmentOutputs {
+                requested: 5,
+                max: 4,
+            })
+        ));
+    }
+
+    #[test]
+    fn varying_vectors_are_packed() {
+        use BuiltInType::*;
+
+        let count = |types: &[BuiltInType]| {
+            let types: Vec<_> = types.iter().copied().map(Type::BuiltIn).collect();
+
+            count_varying_vectors(types.iter())
+        };
+
+        assert_eq!(count(&[]), 0);
+        assert_eq!(count(&[Vec4, Vec4]), 2);
+        assert_eq!(count(&[Mat4]), 4);
+
+        // Two `vec2`s share a row.
+        assert_eq!(count(&[Vec2, Vec2]), 1);
+        assert_eq!(count(&[Vec2, Vec2, Vec2]), 2);
+
+        // Scalars fill up the last component of `vec3` rows.
+        assert_eq!(count(&[Vec3, F32]), 1);
+        assert_eq!(count(&[Vec3, F32, F32]), 2);
+
+        // Scalars fill up the unused half of a `vec2` row.
+        assert_eq!(count(&[Vec2, F32, I32]), 1);
+
+        // The remaining scalars are packed four to a row.
+        assert_eq!(count(&[F32, F32, F32, F32, U32]), 2);
+    }
+
+    #[test]
+    fn varying_struct_and_array_fields_are_counted() {
+        let ty = Type::Struct(Rc::new(StructType {
+            name: "Varyings".to_string(),
+            fields: vec![
+                ("position".to_string(), Type::BuiltIn(BuiltInType::Vec3)),
+                (
+                    "weights".to_string(),
+                    Type::Array(ArrayType {
+                        ty: Box::new(Type::BuiltIn(BuiltInType::F32)),
+                        len: 3,
+                    }),
+                ),
+            ],
+        }));
+
+        // The first weight fills up the `vec3` row, the other two take a
+        // row of their own.
+        assert_eq!(count_varying_vectors([ty].iter()), 2);
+    }
+}
@@ -0,0 +1,246 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use glow::HasContext;
+
+use super::{context::ContextShared, error::check_gl_error, QueryError};
+
+// Not exported by `glow`.
+//
+// EXT_disjoint_timer_query: New Tokens
+const GPU_DISJOINT_EXT: u32 = 0x8FBB;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QueryType {
+    /// Checks whether any samples passed the depth and stencil tests.
+    AnySamplesPassed,
+
+    /// Like `AnySamplesPassed`, but allows the implementation to use a less
+    /// precise test, which may result in false positives.
+    AnySamplesPassedConservative,
+
+    /// Measures the time that the GPU takes to execute commands.
+    ///
+    /// This requires [`Caps::disjoint_timer_query`](super::Caps).
+    TimeElapsed,
+}
+
+impl QueryType {
+    pub const fn to_gl(self) -> u32 {
+        use QueryType::*;
+
+        match self {
+            AnySamplesPassed => glow::ANY_SAMPLES_PASSED,
+            AnySamplesPassedConservative => glow::ANY_SAMPLES_PASSED_CONSERVATIVE,
+            TimeElapsed => glow::TIME_ELAPSED,
+        }
+    }
+
+    pub const fn is_occlusion(self) -> bool {
+        use QueryType::*;
+
+        matches!(self, AnySamplesPassed | AnySamplesPassedConservative)
+    }
+}
+
+/// The result of a query that has become available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QueryResult {
+    /// Whether any samples passed in an occlusion query.
+    AnySamplesPassed(bool),
+
+    /// The GPU time elapsed in a timer query.
+    TimeElapsed(Duration),
+
+    /// The GPU timer was interrupted while the timer query was active, so the
+    /// measured time is meaningless.
+    Disjoint,
+}
+
+pub struct Query {
+    ctx: Rc<ContextShared>,
+    id: glow::Query,
+    ty: QueryType,
+    active: Cell<bool>,
+    pending: Cell<bool>,
+}
+
+impl Query {
+    pub(super) fn new(ctx: Rc<ContextShared>, ty: QueryType) -> Result<Self, QueryError> {
+        if ty == QueryType::TimeElapsed && !ctx.caps().disjoint_timer_query {
+            return Err(QueryError::Unsupported(ty));
+        }
+
+        let gl = ctx.gl();
+        let id = unsafe { gl.create_query() }.map_err(QueryError::ObjectCreation)?;
+
+        check_gl_error(gl, "after new query")?;
+
+        Ok(Self {
+            ctx,
+            id,
+            ty,
+            active: Cell::new(false),
+            pending: Cell::new(false),
+        })
+    }
+
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+
+    /// Starts the query, discarding any result that has not been polled yet.
+    pub fn begin(&self) -> Result<(), QueryError> {
+        // OpenGL ES 3.0.6: 2.14 Asynchronous Queries
+        // > *BeginQuery* generates an INVALID_OPERATION error if any of the
+        // > following conditions hold: [...] there is already an active query
+        // > object for target
+        //
+        // The two occlusion query targets share a single slot.
+        let slot = self.ctx.query_slot(self.ty);
+
+        if slot.get() {
+            return Err(QueryError::AlreadyActive(self.ty));
+        }
+
+        let gl = self.ctx.gl();
+
+        unsafe {
+            gl.begin_query(self.ty.to_gl(), self.id);
+        }
+
+        slot.set(true);
+        self.active.set(true);
+        self.pending.set(false);
+
+        #[cfg(debug_assertions)]
+        check_gl_error(gl, "after begin query")?;
+
+        Ok(())
+    }
+
+    pub fn end(&self) -> Result<(), QueryError> {
+        if !self.active.get() {
+            return Err(QueryError::NotActive(self.ty));
+        }
+
+        let gl = self.ctx.gl();
+
+        unsafe {
+            gl.end_query(self.ty.to_gl());
+        }
+
+        self.ctx.query_slot(self.ty).set(false);
+        self.active.set(false);
+        self.pending.set(true);
+
+        #[cfg(debug_assertions)]
+        check_gl_error(gl, "after end query")?;
+
+        Ok(())
+    }
+
+    /// Returns the result of the query if it is available, without waiting
+    /// for the GPU.
+    ///
+    /// Returns `None` while the query is active, if it has never been ended,
+    /// or if the GPU has not finished executing the commands yet. Once a
+    /// result has been returned, subsequent calls return `None` until the
+    /// query is run again.
+    pub fn poll(&self) -> Option<QueryResult> {
+        if self.active.get() || !self.pending.get() {
+            return None;
+        }
+
+        let gl = self.ctx.gl();
+
+        let available =
+            unsafe { gl.get_query_parameter_u32(self.id, glow::QUERY_RESULT_AVAILABLE) } != 0;
+
+        if !available {
+            return None;
+        }
+
+        self.pending.set(false);
+
+        let result = match self.ty {
+            QueryType::AnySamplesPassed | QueryType::AnySamplesPassedConservative => {
+                let value = unsafe { gl.get_query_parameter_u32(self.id, glow::QUERY_RESULT) };
+
+                QueryResult::AnySamplesPassed(value != 0)
+            }
+            QueryType::TimeElapsed => {
+                let value = unsafe { query_result_u64(gl, self.id) };
+
+                // EXT_disjoint_timer_query: Overview
+                // > [...] the query object results may be invalid if a
+                // > disjoint operation occurred. Reading GPU_DISJOINT_EXT
+                // > also clears the flag.
+                let disjoint = unsafe { gl.get_parameter_i32(GPU_DISJOINT_EXT) } != 0;
+
+                if disjoint {
+                    QueryResult::Disjoint
+                } else {
+                    QueryResult::TimeElapsed(Duration::from_nanos(value))
+                }
+            }
+        };
+
+        Some(result)
+    }
+}
+
+/// Returns the 64-bit result of a query, which must be available.
+///
+/// 32-bit results of timer queries wrap after about 4.29 seconds.
+///
+/// EXT_disjoint_timer_query: New Procedures and Functions
+/// > void GetQueryObjectui64vEXT(uint id, enum pname, uint64 *params);
+unsafe fn query_result_u64(gl: &glow::Context, id: glow::Query) -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut value: u64 = 0;
+
+        // There is no query buffer target in OpenGL ES, so the offset is a
+        // pointer to the result.
+        gl.get_query_parameter_u64_with_offset(
+            id,
+            glow::QUERY_RESULT,
+            &mut value as *mut u64 as usize,
+        );
+
+        value
+    }
+
+    // WebGL returns query results as numbers, which glow only reads as `u32`,
+    // saturating larger values.
+    #[cfg(target_arch = "wasm32")]
+    {
+        gl.get_query_parameter_u32(id, glow::QUERY_RESULT).into()
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        let gl = self.ctx.gl();
+
+        // OpenGL ES 3.0.6: 2.14 Asynchronous Queries
+        // > If an active query object is deleted its name immediately becomes
+        // > unused, but the underlying object is not deleted until it is no
+        // > longer active.
+        if self.active.get() {
+            unsafe {
+                gl.end_query(self.ty.to_gl());
+            }
+
+            self.ctx.query_slot(self.ty).set(false);
+        }
+
+        unsafe {
+            gl.delete_query(self.id);
+        }
+    }
+}
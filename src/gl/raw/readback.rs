@@ -0,0 +1,157 @@
+use std::rc::Rc;
+
+use glow::HasContext;
+
+use super::{
+    context::ContextShared, error::check_gl_error, Buffer, BufferUsage, Fence, Framebuffer,
+    ImageComponentType, ImageFormat, ReadbackError, Rect,
+};
+
+/// Pixels that have been read back from a framebuffer.
+///
+/// Rows are stored bottom to top, following OpenGL conventions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadbackImage {
+    pub size: [u32; 2],
+    pub format: ImageFormat,
+    pub ty: ImageComponentType,
+    pub data: Vec<u8>,
+}
+
+pub struct PixelReadback {
+    buffer: Buffer,
+    fence: Fence,
+    size: [u32; 2],
+    format: ImageFormat,
+    ty: ImageComponentType,
+}
+
+impl PixelReadback {
+    pub(super) fn new(
+        ctx: Rc<ContextShared>,
+        framebuffer: &Framebuffer,
+        rect: Rect,
+    ) -> Result<Self, ReadbackError> {
+        let (format, ty) = match framebuffer {
+            Framebuffer::Default => (ImageFormat::Rgba, ImageComponentType::U8),
+            Framebuffer::Attachments { attachments } => {
+                // OpenGL ES 3.0.6: 4.3.1 Reading Pixels
+                // The read buffer of a framebuffer object is initially
+                // `COLOR_ATTACHMENT0`, which is the first color attachment.
+                let internal_format = attachments
+                    .iter()
+                    .map(|attachment| attachment.internal_format())
+                    .find(|internal_format| internal_format.is_color())
+                    .ok_or(ReadbackError::NoColorAttachment)?;
+
+                internal_format
+                    .read_format()
+                    .ok_or(ReadbackError::UnreadableColorAttachment(internal_format))?
+            }
+        };
+
+        let framebuffer_size = framebuffer.size(&ctx);
+
+        let in_bounds = (0..2).all(|i| {
+            rect.lower_left_corner[i]
+                .checked_add(rect.size[i])
+                .is_some_and(|end| end <= framebuffer_size[i])
+        });
+
+        if !in_bounds {
+            return Err(ReadbackError::OutOfBounds {
+                rect,
+                size: framebuffer_size,
+            });
+        }
+
+        let len = (rect.size[0] as usize)
+            .checked_mul(rect.size[1] as usize)
+            .and_then(|n| n.checked_mul(format.size()))
+            .and_then(|n| n.checked_mul(ty.size_of()))
+            .expect("readback size is out of usize range");
+
+        let buffer = Buffer::new_with_len(
+            ctx.clone(),
+            len,
+            glow::PIXEL_PACK_BUFFER,
+            BufferUsage::StreamRead,
+        )?;
+
+        framebuffer.bind(&ctx)?;
+
+        let gl = ctx.gl();
+
+        // OpenGL ES 3.0.6: 4.3.1 Reading Pixels
+        // > If a pixel pack buffer is bound (as indicated by a non-zero value
+        // > of PIXEL_PACK_BUFFER_BINDING), `data` is an offset into the pixel
+        // > pack buffer and the pixels are packed into the buffer relative to
+        // > this offset.
+        unsafe {
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(buffer.id()));
+            gl.read_pixels(
+                rect.lower_left_corner[0].try_into().unwrap(),
+                rect.lower_left_corner[1].try_into().unwrap(),
+                rect.size[0].try_into().unwrap(),
+                rect.size[1].try_into().unwrap(),
+                format.to_gl(),
+                ty.to_gl(),
+                glow::PixelPackData::BufferOffset(0),
+            );
+            gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+        }
+
+        check_gl_error(gl, "after read pixels")?;
+
+        let fence = Fence::new(ctx.clone())?;
+
+        Ok(Self {
+            buffer,
+            fence,
+            size: rect.size,
+            format,
+            ty,
+        })
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn ty(&self) -> ImageComponentType {
+        self.ty
+    }
+
+    /// Returns `true` if the pixels can be read without stalling.
+    pub fn is_ready(&self) -> Result<bool, ReadbackError> {
+        Ok(self.fence.poll()?)
+    }
+
+    /// Returns the pixels if the GPU has finished copying them, without
+    /// waiting.
+    pub fn try_read(&self) -> Result<Option<ReadbackImage>, ReadbackError> {
+        if self.is_ready()? {
+            self.read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the pixels, waiting for the GPU if necessary.
+    pub fn read(&self) -> Result<ReadbackImage, ReadbackError> {
+        let mut data = vec![0; self.buffer.len()];
+
+        self.buffer.read(0, &mut data)?;
+
+        Ok(ReadbackImage {
+            size: self.size,
+            format: self.format,
+            ty: self.ty,
+            data,
+        })
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::RangeInclusive;
+
 use glow::HasContext;
 
 use super::Comparison;
@@ -31,6 +33,12 @@ pub struct Sampler2dSettings {
     pub min_filter: SamplerMinFilter,
     pub wrap_s: SamplerWrap,
     pub wrap_t: SamplerWrap,
+
+    /// The lowest mipmap level that can be sampled.
+    base_level: u32,
+
+    /// The highest mipmap level that can be sampled.
+    max_level: u32,
 }
 
 impl SamplerMagFilter {
@@ -59,6 +67,12 @@ impl SamplerMinFilter {
     }
 }
 
+impl SamplerMinFilter {
+    pub const fn uses_mipmaps(self) -> bool {
+        !matches!(self, SamplerMinFilter::Nearest | SamplerMinFilter::Linear)
+    }
+}
+
 impl SamplerWrap {
     pub const fn to_gl(self) -> u32 {
         use SamplerWrap::*;
@@ -78,17 +92,27 @@ impl Default for Sampler2dSettings {
             min_filter: SamplerMinFilter::NearestMipmapLinear,
             wrap_s: SamplerWrap::Repeat,
             wrap_t: SamplerWrap::Repeat,
+            base_level: 0,
+            max_level: Self::DEFAULT_MAX_LEVEL,
         }
     }
 }
 
 impl Sampler2dSettings {
+    /// The initial value of `TEXTURE_MAX_LEVEL`, which does not restrict the
+    /// sampled levels.
+    ///
+    /// OpenGL ES 3.0.6: 3.8.15 Texture State
+    pub const DEFAULT_MAX_LEVEL: u32 = 1000;
+
     pub fn linear() -> Self {
         Self {
             mag_filter: SamplerMagFilter::Linear,
             min_filter: SamplerMinFilter::LinearMipmapLinear,
             wrap_s: SamplerWrap::Repeat,
             wrap_t: SamplerWrap::Repeat,
+            base_level: 0,
+            max_level: Self::DEFAULT_MAX_LEVEL,
         }
     }
 
@@ -98,6 +122,8 @@ impl Sampler2dSettings {
             min_filter: SamplerMinFilter::NearestMipmapNearest,
             wrap_s: SamplerWrap::Repeat,
             wrap_t: SamplerWrap::Repeat,
+            base_level: 0,
+            max_level: Self::DEFAULT_MAX_LEVEL,
         }
     }
 
@@ -107,6 +133,56 @@ impl Sampler2dSettings {
         self
     }
 
+    /// Restricts sampling to the mipmap levels `base_level..=max_level`.
+    #[must_use]
+    pub fn with_levels(mut self, base_level: u32, max_level: u32) -> Self {
+        self.base_level = base_level;
+        self.max_level = max_level;
+        self
+    }
+
+    pub fn base_level(&self) -> u32 {
+        self.base_level
+    }
+
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+
+    /// Replaces the filters with the closest ones that only sample the
+    /// nearest texel, keeping whether mipmaps are used.
+    pub(super) fn with_nearest_filters(mut self) -> Self {
+        use SamplerMinFilter::*;
+
+        self.mag_filter = SamplerMagFilter::Nearest;
+        self.min_filter = match self.min_filter {
+            Nearest | Linear => Nearest,
+            NearestMipmapNearest | NearestMipmapLinear | LinearMipmapNearest
+            | LinearMipmapLinear => NearestMipmapNearest,
+        };
+        self
+    }
+
+    /// Returns the levels that can be sampled from a texture with `levels`
+    /// levels.
+    pub(super) fn sampled_levels(&self, levels: usize) -> RangeInclusive<u32> {
+        let last = u32::try_from(levels.saturating_sub(1)).unwrap_or(u32::MAX);
+
+        // OpenGL ES 3.0.6: 3.8.10 Texture Minification
+        // For immutable-format textures, `level_base` is clamped to the range
+        // of levels of the texture, and `level_max` is clamped to the range
+        // from `level_base` to the last level.
+        let base = self.base_level.min(last);
+        let max = self.max_level.clamp(base, last);
+
+        // Filters that do not use mipmaps only sample the base level.
+        if self.min_filter.uses_mipmaps() {
+            base..=max
+        } else {
+            base..=base
+        }
+    }
+
     pub(super) fn set_delta(&self, gl: &glow::Context, current: &Sampler2dSettings) {
         if self.mag_filter != current.mag_filter {
             let mag_filter = self.mag_filter.to_gl() as i32;
@@ -139,6 +215,22 @@ impl Sampler2dSettings {
                 gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap_t);
             }
         }
+
+        if self.base_level != current.base_level {
+            let base_level = i32::try_from(self.base_level).unwrap_or(i32::MAX);
+
+            unsafe {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_BASE_LEVEL, base_level);
+            }
+        }
+
+        if self.max_level != current.max_level {
+            let max_level = i32::try_from(self.max_level).unwrap_or(i32::MAX);
+
+            unsafe {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, max_level);
+            }
+        }
     }
 }
 
@@ -151,3 +243,50 @@ pub(super) fn set_comparison(gl: &glow::Context, target: u32, comparison: Option
         unsafe { gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_COMPARE_FUNC, comparison) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Sampler2dSettings, SamplerMagFilter, SamplerMinFilter};
+
+    #[test]
+    fn sampled_levels_are_clamped_to_the_texture() {
+        let settings = Sampler2dSettings::linear();
+
+        assert_eq!(settings.sampled_levels(4), 0..=3);
+        assert_eq!(settings.with_levels(1, 2).sampled_levels(4), 1..=2);
+        assert_eq!(settings.with_levels(2, 10).sampled_levels(4), 2..=3);
+        assert_eq!(settings.with_levels(6, 10).sampled_levels(4), 3..=3);
+
+        // `max_level` is clamped to be at least `base_level`.
+        assert_eq!(settings.with_levels(2, 1).sampled_levels(4), 2..=2);
+    }
+
+    #[test]
+    fn non_mipmap_filters_sample_only_the_base_level() {
+        let settings = Sampler2dSettings {
+            min_filter: SamplerMinFilter::Linear,
+            ..Sampler2dSettings::linear()
+        };
+
+        assert_eq!(settings.sampled_levels(4), 0..=0);
+        assert_eq!(settings.with_levels(2, 3).sampled_levels(4), 2..=2);
+    }
+
+    #[test]
+    fn nearest_filters_keep_mipmapping() {
+        let settings = Sampler2dSettings::linear().with_nearest_filters();
+
+        assert_eq!(settings.mag_filter, SamplerMagFilter::Nearest);
+        assert_eq!(settings.min_filter, SamplerMinFilter::NearestMipmapNearest);
+        assert_eq!(settings.sampled_levels(4), 0..=3);
+
+        let settings = Sampler2dSettings {
+            min_filter: SamplerMinFilter::Linear,
+            ..Sampler2dSettings::linear()
+        }
+        .with_nearest_filters();
+
+        assert_eq!(settings.min_filter, SamplerMinFilter::Nearest);
+        assert_eq!(settings.sampled_levels(4), 0..=0);
+    }
+}
@@ -348,6 +348,11 @@ impl Default for DrawSettings {
 }
 
 impl DrawSettings {
+    /// Returns whether drawing with these settings clears any buffer first.
+    pub fn clears(&self) -> bool {
+        self.clear_stencil.is_some() || self.clear_depth.is_some() || self.clear_color.is_some()
+    }
+
     pub(super) fn set_delta(
         &self,
         gl: &glow::Context,
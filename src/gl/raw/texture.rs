@@ -5,8 +5,8 @@ use glow::HasContext;
 use crate::gl::{raw::error::check_gl_error, TextureError};
 
 use super::{
-    context::ContextShared, sampler_settings::set_comparison, Caps, Comparison, Image,
-    ImageInternalFormat, Sampler2dSettings,
+    context::ContextShared, sampler_settings::set_comparison, Caps, Comparison, Image, ImageData,
+    ImageInternalFormat, Rect, Sampler2dSettings,
 };
 
 pub struct Texture2d {
@@ -16,6 +16,7 @@ pub struct Texture2d {
     internal_format: ImageInternalFormat,
     levels: usize,
     settings: Cell<Sampler2dSettings>,
+    comparison: Cell<Option<Comparison>>,
 }
 
 #[derive(Clone)]
@@ -30,23 +31,38 @@ pub struct Sampler2d {
     pub comparison: Option<Comparison>,
 }
 
-struct ImageData<'a> {
-    image: &'a Image<'a>,
-    buffer: Vec<u8>,
-}
-
-impl<'a> ImageData<'a> {
-    fn new(image: &'a Image<'a>) -> Self {
-        Self {
-            image,
-            buffer: Vec::new(),
-        }
-    }
-
-    fn as_slice(&mut self) -> Result<&[u8], TextureError> {
-        let len = self.image.required_data_len();
+/// Uploads `image` into the texture that is currently bound to `TEXTURE_2D`.
+fn upload_image(
+    ctx: &ContextShared,
+    image: &Image,
+    level: i32,
+    lower_left_corner: [i32; 2],
+) -> Result<(), TextureError> {
+    let gl = ctx.gl();
+
+    let width = image.size[0]
+        .try_into()
+        .expect("max_texture_size is out of i32 range");
+    let height = image.size[1]
+        .try_into()
+        .expect("max_texture_size is out of i32 range");
+    let format = image.internal_format.to_format().to_gl();
+    let ty = image.ty.to_gl();
+    let len = image.required_data_len();
+    let compressed = image.internal_format.is_compressed();
+
+    match &image.data {
+        ImageData::Zeroed | ImageData::Slice(_) | ImageData::Owned(_) => {
+            let zeroed;
+            let slice: &[u8] = match &image.data {
+                ImageData::Slice(slice) => slice,
+                ImageData::Owned(data) => data,
+                _ => {
+                    zeroed = vec![0; len];
+                    zeroed.as_slice()
+                }
+            };
 
-        if let Some(slice) = self.image.data {
             // Safety: check that `slice` has the correct size.
             if slice.len() != len {
                 return Err(TextureError::DataSizeMismatch {
@@ -55,12 +71,112 @@ impl<'a> ImageData<'a> {
                 });
             }
 
-            Ok(slice)
-        } else {
-            self.buffer.resize(len, 0);
-            Ok(self.buffer.as_slice())
+            if compressed {
+                unsafe {
+                    gl.compressed_tex_sub_image_2d(
+                        glow::TEXTURE_2D,
+                        level,
+                        lower_left_corner[0],
+                        lower_left_corner[1],
+                        width,
+                        height,
+                        image.internal_format.to_gl(),
+                        glow::CompressedPixelUnpackData::Slice(slice),
+                    )
+                };
+            } else {
+                unsafe {
+                    gl.tex_sub_image_2d(
+                        glow::TEXTURE_2D,
+                        level,
+                        lower_left_corner[0],
+                        lower_left_corner[1],
+                        width,
+                        height,
+                        format,
+                        ty,
+                        glow::PixelUnpackData::Slice(slice),
+                    )
+                };
+            }
+        }
+        &ImageData::UnpackBuffer { buffer, offset } => {
+            assert!(buffer.context().ref_eq(ctx));
+
+            // Safety: check that the buffer contains enough data.
+            let in_bounds = offset
+                .checked_add(len)
+                .is_some_and(|end| end <= buffer.len());
+
+            if !in_bounds {
+                return Err(TextureError::UnpackBufferOutOfBounds {
+                    offset,
+                    len,
+                    buffer_len: buffer.len(),
+                });
+            }
+
+            // OpenGL ES 3.0.6: 3.7.1 Unpacking
+            // > If a pixel unpack buffer object is bound and `data` is not
+            // > evenly divisible by the number of basic machine units needed
+            // > to store in memory the corresponding GL data type [...], an
+            // > INVALID_OPERATION error is generated.
+            if offset % image.ty.size_of() != 0 {
+                return Err(TextureError::UnpackBufferMisaligned {
+                    offset,
+                    alignment: image.ty.size_of(),
+                });
+            }
+
+            // OpenGL ES 3.0.6: 2.10.3 Mapping and Unmapping Buffer Data
+            // > Any GL command which attempts to read from [...] a buffer
+            // > object while it is mapped will generate an INVALID_OPERATION
+            // > error.
+            if buffer.is_mapped() {
+                return Err(TextureError::UnpackBufferMapped);
+            }
+
+            let offset: u32 = offset
+                .try_into()
+                .expect("unpack buffer offset is out of u32 range");
+            let end = offset
+                .checked_add(len.try_into().expect("image size is out of u32 range"))
+                .expect("unpack buffer range is out of u32 range");
+
+            unsafe {
+                gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, Some(buffer.id()));
+
+                if compressed {
+                    gl.compressed_tex_sub_image_2d(
+                        glow::TEXTURE_2D,
+                        level,
+                        lower_left_corner[0],
+                        lower_left_corner[1],
+                        width,
+                        height,
+                        image.internal_format.to_gl(),
+                        glow::CompressedPixelUnpackData::BufferRange(offset..end),
+                    );
+                } else {
+                    gl.tex_sub_image_2d(
+                        glow::TEXTURE_2D,
+                        level,
+                        lower_left_corner[0],
+                        lower_left_corner[1],
+                        width,
+                        height,
+                        format,
+                        ty,
+                        glow::PixelUnpackData::BufferOffset(offset),
+                    );
+                }
+
+                gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, None);
+            }
         }
     }
+
+    Ok(())
 }
 
 impl Texture2d {
@@ -68,65 +184,62 @@ impl Texture2d {
         ctx: Rc<ContextShared>,
         image: Image,
         levels: usize,
+    ) -> Result<Self, TextureError> {
+        let texture = Self::allocate(ctx.clone(), image.size, image.internal_format, levels)?;
+
+        // The texture is allocated before uploading, so that it will be
+        // cleaned up if there is an error.
+        upload_image(&ctx, &image, 0, [0, 0])?;
+
+        check_gl_error(ctx.gl(), "after new texture")?;
+
+        Ok(texture)
+    }
+
+    /// Creates a texture object with immutable storage and binds it for
+    /// updates. The contents of the texture are undefined.
+    fn allocate(
+        ctx: Rc<ContextShared>,
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        levels: usize,
     ) -> Result<Self, TextureError> {
         // OpenGL ES 3.0.6: 3.8.4 Immutable-Format Texture Images
         // > If [...] `levels` is less than 1, the error `INVALID_VALUE` is
         // > generated.
         assert!(levels > 0);
 
-        let levels = levels.try_into().expect("levels is out of i32 range");
-        let width = image.size[0]
+        let gl_levels = levels.try_into().expect("levels is out of i32 range");
+        let width = size[0]
             .try_into()
             .expect("max_texture_size is out of i32 range");
-        let height = image.size[1]
+        let height = size[1]
             .try_into()
             .expect("max_texture_size is out of i32 range");
 
-        let mut data = ImageData::new(&image);
-        let slice = data.as_slice()?;
-
         let gl = ctx.gl();
         let id = unsafe { gl.create_texture() }.map_err(TextureError::ObjectCreation)?;
 
-        unsafe { gl.bind_texture(glow::TEXTURE_2D, Some(id)) };
+        ctx.bindings().bind_texture_2d_for_update(gl, id);
         unsafe {
             gl.tex_storage_2d(
                 glow::TEXTURE_2D,
-                levels,
-                image.internal_format.to_gl(),
-                width,
-                height,
-            )
-        };
-        unsafe {
-            gl.tex_sub_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                0,
-                0,
+                gl_levels,
+                internal_format.to_gl(),
                 width,
                 height,
-                image.internal_format.to_format().to_gl(),
-                image.ty.to_gl(),
-                glow::PixelUnpackData::Slice(slice),
             )
         };
-        unsafe { gl.bind_texture(glow::TEXTURE_2D, None) };
 
-        let texture = Texture2d {
+        Ok(Texture2d {
             ctx: ctx.clone(),
             id,
-            size: image.size,
-            internal_format: image.internal_format,
-            levels: levels as usize,
+            size,
+            internal_format,
+            levels,
             settings: Default::default(),
-        };
-
-        // Check for errors *after* passing ownership of the texture to
-        // `shared` so that it will be cleaned up if there is an error.
-        check_gl_error(gl, "after new texture").map_err(TextureError::Unexpected)?;
-
-        Ok(texture)
+            comparison: Cell::new(None),
+        })
     }
 
     pub(super) fn new(ctx: Rc<ContextShared>, image: Image) -> Result<Self, TextureError> {
@@ -146,16 +259,87 @@ impl Texture2d {
         // > `floor(log_2(max(width, height))) + 1`.
         let levels = (image.size[0].max(image.size[1]) as f64).log2() as usize + 1;
 
-        let texture = Self::new_with_levels(ctx.clone(), image, levels)?;
-        let gl = ctx.gl();
+        let texture = Self::new_with_levels(ctx, image, levels)?;
 
-        unsafe {
-            gl.bind_texture(glow::TEXTURE_2D, Some(texture.id));
-            gl.generate_mipmap(glow::TEXTURE_2D);
-            gl.bind_texture(glow::TEXTURE_2D, None);
+        texture.generate_mipmaps()?;
+
+        Ok(texture)
+    }
+
+    /// Creates a texture with `levels` levels whose contents are undefined,
+    /// which is meant for textures that are rendered into.
+    pub(super) fn new_uninitialized(
+        ctx: Rc<ContextShared>,
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        levels: usize,
+    ) -> Result<Self, TextureError> {
+        validate_size(size, ctx.caps())?;
+
+        let max_levels = (size[0].max(size[1]) as f64).log2() as usize + 1;
+
+        // OpenGL ES 3.0.6: 3.8.4 Immutable-Format Texture Images
+        // > An INVALID_OPERATION error is generated if `levels` is greater than
+        // > `floor(log_2(max(width, height))) + 1`.
+        if levels > max_levels {
+            return Err(TextureError::TooManyLevels {
+                levels,
+                max: max_levels,
+            });
         }
 
-        check_gl_error(gl, "after new texture with mipmaps").map_err(TextureError::Unexpected)?;
+        let texture = Self::allocate(ctx.clone(), size, internal_format, levels)?;
+
+        check_gl_error(ctx.gl(), "after new texture")?;
+
+        Ok(texture)
+    }
+
+    /// Creates a texture from a pre-built mip chain, with `images[i]` holding
+    /// level `i`.
+    pub(super) fn new_with_level_images(
+        ctx: Rc<ContextShared>,
+        images: Vec<Image>,
+    ) -> Result<Self, TextureError> {
+        let mut images = images.into_iter();
+        let base = images.next().ok_or(TextureError::Empty)?;
+
+        validate_size(base.size, ctx.caps())?;
+
+        let levels = images.len() + 1;
+        let max_levels = (base.size[0].max(base.size[1]) as f64).log2() as usize + 1;
+
+        // OpenGL ES 3.0.6: 3.8.4 Immutable-Format Texture Images
+        // > An INVALID_OPERATION error is generated if `levels` is greater than
+        // > `floor(log_2(max(width, height))) + 1`.
+        if levels > max_levels {
+            return Err(TextureError::TooManyLevels {
+                levels,
+                max: max_levels,
+            });
+        }
+
+        let texture = Self::new_with_levels(ctx, base, levels)?;
+
+        for (level, image) in images.enumerate() {
+            let level = level + 1;
+            let level_size = texture.level_size(level);
+
+            if image.size != level_size {
+                return Err(TextureError::LevelSizeMismatch {
+                    level,
+                    expected: level_size,
+                    got: image.size,
+                });
+            }
+
+            let rect = Rect {
+                lower_left_corner: [0, 0],
+                size: level_size,
+            };
+
+            texture.set_region(level, rect, image)?;
+        }
 
         Ok(texture)
     }
@@ -172,45 +356,120 @@ impl Texture2d {
         self.internal_format
     }
 
-    pub fn set(
-        &self,
-        level: usize,
-        lower_left_corner: [u32; 2],
-        image: Image,
-    ) -> Result<(), TextureError> {
-        assert!(level <= self.levels);
-        assert_eq!(self.internal_format, image.internal_format);
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
 
-        let mut data = ImageData::new(&image);
-        let slice = data.as_slice()?;
+    /// Returns the size of the given mipmap level.
+    pub fn level_size(&self, level: usize) -> [u32; 2] {
+        // OpenGL ES 3.0.6: 3.8.4 Immutable-Format Texture Images
+        // > `levelwidth = max(1, floor(levelwidth / 2))`
+        self.size.map(|size| {
+            u32::try_from(level)
+                .ok()
+                .and_then(|level| size.checked_shr(level))
+                .unwrap_or(0)
+                .max(1)
+        })
+    }
+
+    pub fn set_region(&self, level: usize, rect: Rect, image: Image) -> Result<(), TextureError> {
+        if level >= self.levels {
+            return Err(TextureError::LevelOutOfRange {
+                level,
+                levels: self.levels,
+            });
+        }
+
+        if image.internal_format != self.internal_format {
+            return Err(TextureError::FormatMismatch {
+                expected: self.internal_format,
+                got: image.internal_format,
+            });
+        }
+
+        if image.size != rect.size {
+            return Err(TextureError::RegionSizeMismatch {
+                region: rect.size,
+                image: image.size,
+            });
+        }
+
+        let level_size = self.level_size(level);
+
+        // OpenGL ES 3.0.6: 3.8.5 Alternate Texture Image Specification
+        // Commands
+        // > An INVALID_VALUE error is generated if `xoffset + width > w` [...]
+        // > or `yoffset + height > h`.
+        let in_bounds = (0..2).all(|i| {
+            rect.lower_left_corner[i]
+                .checked_add(rect.size[i])
+                .is_some_and(|end| end <= level_size[i])
+        });
+
+        if !in_bounds {
+            return Err(TextureError::RegionOutOfBounds { rect, level_size });
+        }
+
+        // OpenGL ES 3.0.6: 3.8.6 Compressed Texture Images
+        // > An INVALID_OPERATION error is generated if `xoffset` or `yoffset`
+        // > is not a multiple of four, or if `width` is not a multiple of four
+        // > and `width + xoffset` is not equal to the width of the texture
+        // > level (similarly for `height`).
+        if self.internal_format.is_compressed() {
+            let aligned = (0..2).all(|i| {
+                rect.lower_left_corner[i].is_multiple_of(4)
+                    && (rect.size[i].is_multiple_of(4)
+                        || rect.lower_left_corner[i] + rect.size[i] == level_size[i])
+            });
+
+            if !aligned {
+                return Err(TextureError::RegionNotBlockAligned { rect, level_size });
+            }
+        }
 
         let gl = self.ctx.gl();
 
         let level = level.try_into().unwrap();
-        let x = lower_left_corner[0].try_into().unwrap();
-        let y = lower_left_corner[1].try_into().unwrap();
-        let width = image.size[0].try_into().unwrap();
-        let height = image.size[1].try_into().unwrap();
+        let x = rect.lower_left_corner[0].try_into().unwrap();
+        let y = rect.lower_left_corner[1].try_into().unwrap();
 
-        unsafe { gl.bind_texture(glow::TEXTURE_2D, Some(self.id)) };
-        unsafe {
-            gl.tex_sub_image_2d(
-                glow::TEXTURE_2D,
-                level,
-                x,
-                y,
-                width,
-                height,
-                image.internal_format.to_format().to_gl(),
-                image.ty.to_gl(),
-                glow::PixelUnpackData::Slice(slice),
-            )
-        };
-        unsafe { gl.bind_texture(glow::TEXTURE_2D, None) };
+        self.ctx.bindings().bind_texture_2d_for_update(gl, self.id);
+        let upload_result = upload_image(&self.ctx, &image, level, [x, y]);
+
+        upload_result?;
+
+        check_gl_error(gl, "after texture set")?;
+
+        Ok(())
+    }
+
+    /// Regenerates all mipmap levels from level 0.
+    ///
+    /// This does nothing for textures that have only one level.
+    pub fn generate_mipmaps(&self) -> Result<(), TextureError> {
+        // OpenGL ES 3.0.6: 3.8.10 Mipmap Generation
+        // > An INVALID_OPERATION error is generated if the levelbase array was
+        // > not specified with an unsized internal format from table 3.3 or a
+        // > sized internal format that is both color-renderable and
+        // > texture-filterable.
+        if !self.internal_format.is_color_renderable(self.ctx.caps())
+            || !self.internal_format.is_texture_filterable()
+        {
+            return Err(TextureError::NotMipmappable(self.internal_format));
+        }
+
+        if self.levels == 1 {
+            return Ok(());
+        }
+
+        let gl = self.ctx.gl();
+
+        self.ctx.bindings().bind_texture_2d_for_update(gl, self.id);
+
+        unsafe { gl.generate_mipmap(glow::TEXTURE_2D) };
 
-        // This might be triggered if `rect` is outside of the texture image
-        // bounds.
-        check_gl_error(gl, "after texture set").map_err(TextureError::Unexpected)?;
+        check_gl_error(gl, "after generating mipmaps")?;
 
         Ok(())
     }
@@ -218,12 +477,31 @@ impl Texture2d {
     pub(super) fn set_settings(&self, new: Sampler2dSettings, comparison: Option<Comparison>) {
         let gl = &self.ctx.gl();
 
+        // OpenGL ES 3.0.6: 3.8.13 Texture Completeness
+        // > The internal format of the texture is integer and either the
+        // > magnification filter is not `NEAREST` or the minification filter
+        // > is neither `NEAREST` nor `NEAREST_MIPMAP_NEAREST`.
+        //
+        // The same holds for depth textures without comparison and for other
+        // formats that are not filterable. Incomplete textures sample as
+        // zero, so we fall back to nearest filtering for them.
+        let filterable = self.internal_format.is_texture_filterable()
+            || (self.internal_format.is_depth_renderable() && comparison.is_some());
+        let new = if filterable {
+            new
+        } else {
+            new.with_nearest_filters()
+        };
+
         let current = self.settings.get();
         new.set_delta(gl, &current);
         self.settings.set(new);
 
         // FIXME: Check that comparison can be applied to the texture.
-        set_comparison(gl, glow::TEXTURE_2D, comparison);
+        if self.comparison.get() != comparison {
+            set_comparison(gl, glow::TEXTURE_2D, comparison);
+            self.comparison.set(comparison);
+        }
 
         #[cfg(debug_assertions)]
         check_gl_error(gl, "after texture settings").unwrap();
@@ -234,6 +512,8 @@ impl Drop for Texture2d {
     fn drop(&mut self) {
         let gl = self.ctx.gl();
 
+        self.ctx.bindings().forget_texture(gl, self.id);
+
         unsafe {
             gl.delete_texture(self.id);
         }
@@ -249,7 +529,9 @@ impl Sampler {
         }
     }
 
-    pub(super) fn bind(&self) {
+    /// Binds the sampler's texture to the texture unit `unit`, which is given
+    /// as `TEXTURE0 + i`, and applies the sampler's settings to it.
+    pub(super) fn bind(&self, unit: u32) {
         match self {
             Sampler::Sampler2d(Sampler2d {
                 texture,
@@ -257,30 +539,16 @@ impl Sampler {
                 comparison,
             }) => {
                 let gl = texture.ctx.gl();
-                let id = texture.id;
 
-                unsafe {
-                    gl.bind_texture(glow::TEXTURE_2D, Some(id));
-                }
+                texture
+                    .ctx
+                    .bindings()
+                    .bind_texture_2d(gl, unit, Some(texture.id));
 
                 texture.set_settings(*settings, *comparison);
             }
         }
     }
-
-    pub(super) fn unbind(&self) {
-        use Sampler::*;
-
-        match self {
-            Sampler2d(sampler) => {
-                let gl = sampler.texture.ctx.gl();
-
-                unsafe {
-                    gl.bind_texture(glow::TEXTURE_2D, None);
-                }
-            }
-        }
-    }
 }
 
 fn validate_size(size: [u32; 2], caps: &Caps) -> Result<(), TextureError> {
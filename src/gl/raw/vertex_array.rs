@@ -0,0 +1,478 @@
+use std::cell::{Cell, RefCell};
+
+use fxhash::FxHashMap;
+use glow::HasContext;
+
+use super::{ContextError, VertexArrayError};
+
+/// The state of one vertex attribute location in a vertex array object.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct VertexAttributePointer {
+    pub index: u32,
+    pub buffer: glow::Buffer,
+    pub size: i32,
+    pub data_type: u32,
+    pub integer: bool,
+    pub normalized: bool,
+    pub stride: i32,
+    pub offset: i32,
+    pub divisor: u32,
+}
+
+/// The complete state of a vertex array object.
+///
+/// Vertex array objects are cached by their state, so that draw calls with an
+/// unchanged vertex specification only need to bind a vertex array object. The
+/// state consists of the buffers and their layout. Where possible, the start of
+/// the vertex range is passed to the draw call instead of being part of the
+/// attribute offsets, so that draw calls with different ranges of the same
+/// buffers share a vertex array object.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(super) struct VertexArrayKey {
+    pub attributes: Vec<VertexAttributePointer>,
+    pub element_buffer: Option<glow::Buffer>,
+}
+
+impl VertexArrayKey {
+    /// Returns the distinct buffers that the vertex array object refers to.
+    fn buffers(&self) -> Vec<glow::Buffer> {
+        let mut buffers: Vec<_> = self
+            .attributes
+            .iter()
+            .map(|attribute| attribute.buffer)
+            .chain(self.element_buffer)
+            .collect();
+
+        buffers.sort_unstable();
+        buffers.dedup();
+        buffers
+    }
+
+    /// Specifies the state of the currently bound vertex array object.
+    fn apply(&self, gl: &glow::Context) {
+        for attribute in &self.attributes {
+            unsafe {
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(attribute.buffer));
+                gl.enable_vertex_attrib_array(attribute.index);
+                gl.vertex_attrib_divisor(attribute.index, attribute.divisor);
+            }
+
+            if attribute.integer {
+                unsafe {
+                    gl.vertex_attrib_pointer_i32(
+                        attribute.index,
+                        attribute.size,
+                        attribute.data_type,
+                        attribute.stride,
+                        attribute.offset,
+                    );
+                }
+            } else {
+                unsafe {
+                    gl.vertex_attrib_pointer_f32(
+                        attribute.index,
+                        attribute.size,
+                        attribute.data_type,
+                        attribute.normalized,
+                        attribute.stride,
+                        attribute.offset,
+                    );
+                }
+            }
+        }
+
+        // OpenGL ES 3.0.6: 2.10 Vertex Array Objects
+        // The element array buffer binding is part of the vertex array object
+        // state, while the array buffer binding is not.
+        unsafe {
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, self.element_buffer);
+        }
+    }
+}
+
+/// Vertex array objects of a context.
+///
+/// The most recently used vertex array object stays bound after draw calls, so
+/// that consecutive draw calls with the same vertex specification do not need
+/// to bind anything. Buffer operations that bind `ELEMENT_ARRAY_BUFFER` would
+/// modify it, so they bind the default vertex array object first with
+/// [`VertexArrays::unbind`].
+pub(super) struct VertexArrays {
+    default: glow::VertexArray,
+
+    /// The currently bound vertex array object, or `None` if it is not known.
+    bound: Cell<Option<glow::VertexArray>>,
+
+    cache: RefCell<Cache>,
+
+    /// A key that is reused for lookups, so that binding a cached vertex array
+    /// object does not allocate.
+    scratch: RefCell<VertexArrayKey>,
+}
+
+/// The cached vertex array objects of a context, without the GL calls that
+/// create and delete them.
+#[derive(Default)]
+struct Cache {
+    vaos: FxHashMap<VertexArrayKey, glow::VertexArray>,
+    entries: FxHashMap<glow::VertexArray, CacheEntry>,
+
+    /// The cached vertex array objects that refer to each buffer.
+    by_buffer: FxHashMap<glow::Buffer, Vec<glow::VertexArray>>,
+
+    /// Incremented on every lookup.
+    tick: u64,
+}
+
+struct CacheEntry {
+    key: VertexArrayKey,
+    last_bound: u64,
+}
+
+impl Cache {
+    fn len(&self) -> usize {
+        self.vaos.len()
+    }
+
+    /// Returns the vertex array object for `key` if it is cached, marking it
+    /// as the most recently bound one.
+    fn get(&mut self, key: &VertexArrayKey) -> Option<glow::VertexArray> {
+        self.tick += 1;
+
+        let vao = *self.vaos.get(key)?;
+
+        if let Some(entry) = self.entries.get_mut(&vao) {
+            entry.last_bound = self.tick;
+        }
+
+        Some(vao)
+    }
+
+    /// Adds a vertex array object that has just been bound.
+    fn insert(&mut self, key: VertexArrayKey, vao: glow::VertexArray) {
+        for buffer in key.buffers() {
+            self.by_buffer.entry(buffer).or_default().push(vao);
+        }
+
+        self.vaos.insert(key.clone(), vao);
+        self.entries.insert(
+            vao,
+            CacheEntry {
+                key,
+                last_bound: self.tick,
+            },
+        );
+    }
+
+    /// Removes a vertex array object, returning whether it was cached.
+    fn remove(&mut self, vao: glow::VertexArray) -> bool {
+        let Some(entry) = self.entries.remove(&vao) else {
+            return false;
+        };
+
+        self.vaos.remove(&entry.key);
+
+        for buffer in entry.key.buffers() {
+            if let Some(vaos) = self.by_buffer.get_mut(&buffer) {
+                vaos.retain(|&other| other != vao);
+
+                if vaos.is_empty() {
+                    self.by_buffer.remove(&buffer);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Returns the vertex array objects that refer to `buffer`.
+    fn users(&self, buffer: glow::Buffer) -> Vec<glow::VertexArray> {
+        self.by_buffer.get(&buffer).cloned().unwrap_or_default()
+    }
+
+    /// Returns the `n` least recently bound vertex array objects.
+    fn least_recently_bound(&self, n: usize) -> Vec<glow::VertexArray> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        if n >= self.entries.len() {
+            return self.entries.keys().copied().collect();
+        }
+
+        let mut last_bound: Vec<u64> = self
+            .entries
+            .values()
+            .map(|entry| entry.last_bound)
+            .collect();
+
+        // Ticks are unique, so exactly `n` entries are at or below the
+        // threshold.
+        let (_, &mut threshold, _) = last_bound.select_nth_unstable(n - 1);
+
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.last_bound <= threshold)
+            .map(|(&vao, _)| vao)
+            .collect()
+    }
+
+    fn clear(&mut self) -> Vec<glow::VertexArray> {
+        self.vaos.clear();
+        self.by_buffer.clear();
+        self.entries.drain().map(|(vao, _)| vao).collect()
+    }
+}
+
+impl VertexArrays {
+    /// The number of cached vertex array objects at which the least recently
+    /// bound ones are evicted. This bounds the cache size if vertex
+    /// specifications change frequently, e.g. with varying buffer offsets.
+    const MAX_CACHED: usize = 1024;
+
+    /// The number of vertex array objects that are evicted at once, so that
+    /// the cost of finding them is spread over many cache misses.
+    const NUM_EVICTED: usize = Self::MAX_CACHED / 4;
+
+    pub fn new(gl: &glow::Context) -> Result<Self, ContextError> {
+        let default = unsafe { gl.create_vertex_array() }.map_err(ContextError::ObjectCreation)?;
+
+        unsafe { gl.bind_vertex_array(Some(default)) };
+
+        Ok(Self {
+            default,
+            bound: Cell::new(Some(default)),
+            cache: Default::default(),
+            scratch: Default::default(),
+        })
+    }
+
+    /// Binds a vertex array object with the state that is filled in by `fill`,
+    /// creating it if it is not cached yet.
+    ///
+    /// Nothing is bound if `fill` fails.
+    pub fn bind_with<E: From<VertexArrayError>>(
+        &self,
+        gl: &glow::Context,
+        fill: impl FnOnce(&mut VertexArrayKey) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut key = self.scratch.borrow_mut();
+        fill(&mut key)?;
+
+        let mut cache = self.cache.borrow_mut();
+
+        if let Some(vao) = cache.get(&key) {
+            self.bind(gl, vao);
+
+            return Ok(());
+        }
+
+        if cache.len() >= Self::MAX_CACHED {
+            for vao in cache.least_recently_bound(Self::NUM_EVICTED) {
+                self.delete_cached(gl, &mut cache, vao);
+            }
+        }
+
+        let vao = unsafe { gl.create_vertex_array() }.map_err(VertexArrayError::ObjectCreation)?;
+
+        self.bind(gl, vao);
+
+        key.apply(gl);
+
+        cache.insert(key.clone(), vao);
+
+        Ok(())
+    }
+
+    /// Binds the default vertex array object.
+    ///
+    /// This must be called before binding `ELEMENT_ARRAY_BUFFER` outside of
+    /// draw calls.
+    pub fn unbind(&self, gl: &glow::Context) {
+        self.bind(gl, self.default);
+    }
+
+    /// Deletes all cached vertex array objects that refer to `buffer`.
+    ///
+    /// This must be called before `buffer` is deleted, since its name may be
+    /// reused for a new buffer afterwards.
+    pub fn forget_buffer(&self, gl: &glow::Context, buffer: glow::Buffer) {
+        let mut cache = self.cache.borrow_mut();
+
+        for vao in cache.users(buffer) {
+            self.delete_cached(gl, &mut cache, vao);
+        }
+    }
+
+    /// Deletes all vertex array objects, including the default one.
+    ///
+    /// This must be called before the context is dropped.
+    pub fn delete(&self, gl: &glow::Context) {
+        unsafe { gl.bind_vertex_array(None) };
+
+        self.bound.set(None);
+
+        for vao in self.cache.borrow_mut().clear() {
+            unsafe { gl.delete_vertex_array(vao) };
+        }
+
+        unsafe { gl.delete_vertex_array(self.default) };
+    }
+
+    fn bind(&self, gl: &glow::Context, vao: glow::VertexArray) {
+        if self.bound.get() != Some(vao) {
+            unsafe { gl.bind_vertex_array(Some(vao)) };
+
+            self.bound.set(Some(vao));
+        }
+    }
+
+    fn delete_cached(&self, gl: &glow::Context, cache: &mut Cache, vao: glow::VertexArray) {
+        if !cache.remove(vao) {
+            return;
+        }
+
+        // OpenGL ES 3.0.6: 2.10 Vertex Array Objects
+        // > If a vertex array object that is currently bound is deleted, the
+        // > binding for that object reverts to zero and the default vertex
+        // > array becomes current.
+        if self.bound.get() == Some(vao) {
+            self.bound.set(None);
+        }
+
+        unsafe { gl.delete_vertex_array(vao) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        num::NonZeroU32,
+    };
+
+    use super::{Cache, VertexArrayKey, VertexAttributePointer};
+
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+
+        (result, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    fn buffer(id: u32) -> glow::Buffer {
+        glow::NativeBuffer(NonZeroU32::new(id).unwrap())
+    }
+
+    fn vao(id: u32) -> glow::VertexArray {
+        glow::NativeVertexArray(NonZeroU32::new(id).unwrap())
+    }
+
+    fn key(buffers: &[u32], element_buffer: Option<u32>) -> VertexArrayKey {
+        VertexArrayKey {
+            attributes: buffers
+                .iter()
+                .enumerate()
+                .map(|(index, &id)| VertexAttributePointer {
+                    index: index as u32,
+                    buffer: buffer(id),
+                    size: 4,
+                    data_type: glow::FLOAT,
+                    integer: false,
+                    normalized: false,
+                    stride: 16,
+                    offset: 0,
+                    divisor: 0,
+                })
+                .collect(),
+            element_buffer: element_buffer.map(buffer),
+        }
+    }
+
+    #[test]
+    fn cache_hits_do_not_allocate() {
+        let mut cache = Cache::default();
+
+        cache.insert(key(&[1, 2], Some(3)), vao(1));
+        cache.insert(key(&[1], None), vao(2));
+
+        let lookup = key(&[1, 2], Some(3));
+        let (found, allocations) = count_allocations(|| cache.get(&lookup));
+
+        assert_eq!(found, Some(vao(1)));
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn buffers_know_the_vertex_arrays_that_use_them() {
+        let mut cache = Cache::default();
+
+        cache.insert(key(&[1, 1], Some(2)), vao(1));
+        cache.insert(key(&[1], None), vao(2));
+        cache.insert(key(&[3], Some(2)), vao(3));
+
+        let mut users = cache.users(buffer(1));
+        users.sort();
+
+        assert_eq!(users, [vao(1), vao(2)]);
+
+        for vao in users {
+            assert!(cache.remove(vao));
+        }
+
+        assert!(cache.users(buffer(1)).is_empty());
+        assert_eq!(cache.users(buffer(2)), [vao(3)]);
+        assert_eq!(cache.get(&key(&[1], None)), None);
+        assert_eq!(cache.get(&key(&[3], Some(2))), Some(vao(3)));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn least_recently_bound_vertex_arrays_are_evicted_first() {
+        let mut cache = Cache::default();
+
+        for id in 1..=4 {
+            cache.get(&key(&[id], None));
+            cache.insert(key(&[id], None), vao(id));
+        }
+
+        // Binding the first two makes the others the least recently bound.
+        cache.get(&key(&[2], None));
+        cache.get(&key(&[1], None));
+
+        let mut evicted = cache.least_recently_bound(2);
+        evicted.sort();
+
+        assert_eq!(evicted, [vao(3), vao(4)]);
+        assert_eq!(cache.least_recently_bound(5).len(), 4);
+    }
+}
@@ -1,12 +1,22 @@
-use std::mem::size_of;
+use std::{mem::size_of, ops::Range};
 
-use crate::sl::dag::BuiltInType;
+use crate::sl::{
+    dag::BuiltInType,
+    program_def::{VertexAttributeFormat, VertexBlockDef},
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum VertexAttributeType {
     F32,
     I32,
     U32,
+    F16,
+    I8,
+    U8,
+    I16,
+    U16,
+    Int2_10_10_10Rev,
+    UInt2_10_10_10Rev,
 }
 
 impl VertexAttributeType {
@@ -17,9 +27,22 @@ impl VertexAttributeType {
             F32 => size_of::<f32>(),
             I32 => size_of::<i32>(),
             U32 => size_of::<u32>(),
+            F16 => size_of::<u16>(),
+            I8 => size_of::<i8>(),
+            U8 => size_of::<u8>(),
+            I16 => size_of::<i16>(),
+            U16 => size_of::<u16>(),
+            Int2_10_10_10Rev | UInt2_10_10_10Rev => size_of::<u32>(),
         }
     }
 
+    /// Returns whether all components are packed into a single value.
+    pub fn is_packed(self) -> bool {
+        use VertexAttributeType::*;
+
+        matches!(self, Int2_10_10_10Rev | UInt2_10_10_10Rev)
+    }
+
     pub fn to_gl(self) -> u32 {
         use VertexAttributeType::*;
 
@@ -27,6 +50,13 @@ impl VertexAttributeType {
             F32 => glow::FLOAT,
             I32 => glow::INT,
             U32 => glow::UNSIGNED_INT,
+            F16 => glow::HALF_FLOAT,
+            I8 => glow::BYTE,
+            U8 => glow::UNSIGNED_BYTE,
+            I16 => glow::SHORT,
+            U16 => glow::UNSIGNED_SHORT,
+            Int2_10_10_10Rev => glow::INT_2_10_10_10_REV,
+            UInt2_10_10_10Rev => glow::UNSIGNED_INT_2_10_10_10_REV,
         }
     }
 }
@@ -35,13 +65,20 @@ pub struct VertexAttributeLayout {
     pub ty: VertexAttributeType,
     pub components: usize,
     pub locations: usize,
+
+    /// Whether the attribute is read as integers in the shader.
+    pub integer: bool,
+
+    /// Whether fixed-point values are normalized when converted to
+    /// floating-point.
+    pub normalized: bool,
 }
 
 impl VertexAttributeLayout {
-    pub fn new(ty: BuiltInType) -> Result<Self, String> {
+    pub fn new(ty: BuiltInType, format: VertexAttributeFormat) -> Result<Self, String> {
         use VertexAttributeType::*;
 
-        let (ty, components, locations) = match ty {
+        let (native_ty, components, locations) = match ty {
             BuiltInType::F32 => (F32, 1, 1),
             BuiltInType::I32 => (I32, 1, 1),
             BuiltInType::U32 => (U32, 1, 1),
@@ -64,14 +101,171 @@ impl VertexAttributeLayout {
             BuiltInType::Sampler(_) => return Err("sampler types are not supported".to_string()),
         };
 
+        let integer = native_ty != F32;
+
+        if format != VertexAttributeFormat::Native && (integer || locations > 1) {
+            return Err(format!(
+                "vertex attribute format {format:?} is not supported for type {ty:?}"
+            ));
+        }
+
+        let (ty, normalized) = match format {
+            VertexAttributeFormat::Native => (native_ty, false),
+            VertexAttributeFormat::U8Norm => (U8, true),
+            VertexAttributeFormat::I8Norm => (I8, true),
+            VertexAttributeFormat::U16Norm => (U16, true),
+            VertexAttributeFormat::I16Norm => (I16, true),
+            VertexAttributeFormat::F16 => (F16, false),
+            VertexAttributeFormat::Int2_10_10_10RevNorm => (Int2_10_10_10Rev, true),
+            VertexAttributeFormat::UInt2_10_10_10RevNorm => (UInt2_10_10_10Rev, true),
+        };
+
+        // OpenGL ES 3.0.6: 2.9 Vertex Arrays
+        // > An `INVALID_OPERATION` error is generated if `size` is not 4 and
+        // > `type` is `INT_2_10_10_10_REV` or `UNSIGNED_INT_2_10_10_10_REV`.
+        if ty.is_packed() && components != 4 {
+            return Err(format!(
+                "vertex attribute format {format:?} requires four components, but {ty:?} has \
+                 {components}"
+            ));
+        }
+
         Ok(VertexAttributeLayout {
             ty,
             components,
             locations,
+            integer,
+            normalized,
         })
     }
 
     pub fn location_size(&self) -> usize {
-        self.components * self.ty.size()
+        if self.ty.is_packed() {
+            self.ty.size()
+        } else {
+            self.components * self.ty.size()
+        }
+    }
+}
+
+/// Returns the number of attribute locations that a vertex block takes up.
+///
+/// Attributes of invalid type are ignored here, since they are reported when
+/// binding attributes.
+pub fn vertex_block_num_locations(block_def: &VertexBlockDef) -> usize {
+    block_def
+        .attributes
+        .iter()
+        .filter_map(|attribute| VertexAttributeLayout::new(attribute.ty, attribute.format).ok())
+        .map(|layout| layout.locations)
+        .sum()
+}
+
+/// Determines the first attribute location of each vertex block.
+///
+/// Blocks with a user-specified location are placed there. The remaining
+/// blocks are placed in the lowest range of free locations that is large
+/// enough to hold them. Returns the first location at which two blocks with
+/// user-specified locations overlap, if any.
+pub fn vertex_block_locations<'a>(
+    block_defs: impl Iterator<Item = &'a VertexBlockDef> + Clone,
+) -> Result<Vec<usize>, usize> {
+    let mut used: Vec<Range<usize>> = Vec::new();
+    let mut locations = vec![0; block_defs.clone().count()];
+
+    for (i, block_def) in block_defs.clone().enumerate() {
+        if let Some(start) = block_def.location {
+            let range = start..start + vertex_block_num_locations(block_def);
+
+            if let Some(other) = used
+                .iter()
+                .find(|other| other.start < range.end && range.start < other.end)
+            {
+                return Err(range.start.max(other.start));
+            }
+
+            used.push(range);
+            locations[i] = start;
+        }
+    }
+
+    for (i, block_def) in block_defs.enumerate() {
+        if block_def.location.is_none() {
+            let len = vertex_block_num_locations(block_def);
+            let mut start = 0;
+
+            while let Some(other) = used
+                .iter()
+                .find(|other| other.start < start + len && start < other.end)
+            {
+                start = other.end;
+            }
+
+            used.push(start..start + len);
+            locations[i] = start;
+        }
+    }
+
+    Ok(locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sl::{
+        dag::BuiltInType,
+        program_def::{VertexAttributeDef, VertexAttributeFormat, VertexBlockDef},
+    };
+
+    use super::vertex_block_locations;
+
+    fn vertex_block_def(num_attributes: usize, location: Option<usize>) -> VertexBlockDef {
+        let attributes = (0..num_attributes)
+            .map(|i| VertexAttributeDef {
+                name: format!("attribute_{i}"),
+                ty: BuiltInType::Vec4,
+                offset: i * 16,
+                format: VertexAttributeFormat::Native,
+            })
+            .collect();
+
+        VertexBlockDef {
+            attributes,
+            location,
+        }
+    }
+
+    #[test]
+    fn explicit_locations_are_kept() {
+        let block_defs = [vertex_block_def(2, Some(5)), vertex_block_def(1, Some(0))];
+
+        assert_eq!(vertex_block_locations(block_defs.iter()), Ok(vec![5, 0]));
+    }
+
+    #[test]
+    fn automatic_locations_fill_the_lowest_free_gaps() {
+        let block_defs = [
+            vertex_block_def(2, Some(2)),
+            vertex_block_def(1, None),
+            vertex_block_def(3, None),
+            vertex_block_def(1, Some(0)),
+        ];
+
+        // The single location fits into the gap at 1, while the three
+        // locations only fit behind the block at 2.
+        assert_eq!(
+            vertex_block_locations(block_defs.iter()),
+            Ok(vec![2, 1, 4, 0])
+        );
+    }
+
+    #[test]
+    fn overlapping_explicit_locations_return_the_first_overlap() {
+        let block_defs = [vertex_block_def(3, Some(0)), vertex_block_def(1, Some(2))];
+
+        assert_eq!(vertex_block_locations(block_defs.iter()), Err(2));
+
+        let block_defs = [vertex_block_def(2, Some(4)), vertex_block_def(4, Some(1))];
+
+        assert_eq!(vertex_block_locations(block_defs.iter()), Err(4));
     }
 }
@@ -5,12 +5,15 @@ use glow::HasContext;
 use crate::{
     gl::raw::{
         error::check_gl_error,
-        vertex_layout::{VertexAttributeLayout, VertexAttributeType},
+        vertex_array::{VertexArrayKey, VertexAttributePointer},
+        vertex_layout::VertexAttributeLayout,
     },
     sl::program_def::{VertexBlockDef, VertexInputRate},
 };
 
-use super::{context::ContextShared, Buffer};
+use super::{
+    context::ContextShared, error::vertex_binding_path, Buffer, DrawError, ElementIndices,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub enum ElementType {
@@ -65,236 +68,377 @@ impl PrimitiveMode {
     }
 }
 
-// TODO: VertexBlockDef should eventually be split into program definition and
-// vertex stream definition.
-
+/// A vertex buffer binding for one of a program's vertex blocks.
+///
+/// The attributes of the block are given by the program's
+/// [`VertexBlockDef`] at the same index.
 #[derive(Clone)]
 pub struct VertexBufferBinding {
     pub buffer: Rc<Buffer>,
-    pub block_def: VertexBlockDef,
     pub input_rate: VertexInputRate,
+
+    /// The number of instances that use the same block, if `input_rate` is
+    /// [`VertexInputRate::Instance`].
+    pub instance_divisor: usize,
+
     pub stride: usize,
+
+    /// The range of bytes in `buffer` that holds the blocks.
+    pub range: Range<usize>,
 }
 
 #[derive(Clone)]
 pub struct VertexSpec {
     pub vertex_data: Vec<VertexBufferBinding>,
     pub element_data: Option<(Rc<Buffer>, ElementType)>,
+
+    /// The indices in the element buffer, which are used to check that the
+    /// draw call's elements are in bounds of the vertex buffers.
+    pub element_indices: Option<Rc<ElementIndices>>,
+
     pub mode: PrimitiveMode,
     pub index_range: Range<usize>,
     pub num_instances: usize,
 }
 
 impl VertexSpec {
-    pub fn is_compatible(&self, _vertex_block_defs: &[VertexBlockDef]) -> bool {
-        // TODO: Check vertex stream compatibility. This is already ensured by
-        // the typed interface on top of `raw`, but `raw` should be correct by
-        // iself.
-        true
+    /// Checks that there is one vertex buffer per vertex block, and that the
+    /// attributes of each block fit into the stride of its buffer.
+    pub fn check_compatible(&self, vertex_block_defs: &[VertexBlockDef]) -> Result<(), DrawError> {
+        if self.vertex_data.len() != vertex_block_defs.len() {
+            return Err(DrawError::VertexBufferCountMismatch {
+                expected: vertex_block_defs.len(),
+                got: self.vertex_data.len(),
+            });
+        }
+
+        for (index, (binding, block_def)) in
+            self.vertex_data.iter().zip(vertex_block_defs).enumerate()
+        {
+            let compatible = binding.stride > 0
+                && block_def.attributes.iter().all(|attribute| {
+                    VertexAttributeLayout::new(attribute.ty, attribute.format).is_ok_and(|layout| {
+                        attribute.offset + layout.locations * layout.location_size()
+                            <= binding.stride
+                    })
+                });
+
+            if !compatible {
+                return Err(DrawError::IncompatibleVertexSpec {
+                    binding: index,
+                    path: vertex_binding_path(index),
+                });
+            }
+        }
+
+        Ok(())
     }
 
-    fn bind(&self, ctx: &ContextShared) {
-        let mut index = 0;
+    /// Checks that the draw call only accesses vertices and instances that
+    /// are in bounds of the vertex buffers.
+    pub(super) fn validate(&self, ctx: &ContextShared) -> Result<(), DrawError> {
+        for (index, binding) in self.vertex_data.iter().enumerate() {
+            if !binding.buffer.context().ref_eq(ctx) {
+                return Err(DrawError::VertexBufferContextMismatch {
+                    binding: index,
+                    path: vertex_binding_path(index),
+                });
+            }
 
-        let gl = ctx.gl();
+            if binding.buffer.is_mapped() {
+                return Err(DrawError::VertexBufferMapped {
+                    binding: index,
+                    path: vertex_binding_path(index),
+                });
+            }
 
-        for VertexBufferBinding {
-            buffer,
-            block_def,
-            input_rate,
-            stride,
-        } in &self.vertex_data
-        {
-            assert!(*stride > 0);
-            assert_eq!(buffer.len() % stride, 0);
-            assert!(buffer.context().ref_eq(ctx));
+            if binding.range.start > binding.range.end || binding.range.end > binding.buffer.len() {
+                return Err(DrawError::VertexRangeOutOfBounds {
+                    start: binding.range.start / binding.stride,
+                    end: binding.range.end / binding.stride,
+                    num_vertices: binding.buffer.len() / binding.stride,
+                });
+            }
 
-            unsafe {
-                gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer.id()));
+            if !binding.range.start.is_multiple_of(binding.stride)
+                || !binding.range.len().is_multiple_of(binding.stride)
+            {
+                return Err(DrawError::VertexRangeMisaligned {
+                    binding: index,
+                    path: vertex_binding_path(index),
+                    start: binding.range.start,
+                    end: binding.range.end,
+                    stride: binding.stride,
+                });
             }
+        }
 
-            for attribute in &block_def.attributes {
-                let attribute_info =
-                    VertexAttributeLayout::new(attribute.ty).expect("invalid vertex attribute");
+        if let Some((buffer, _)) = &self.element_data {
+            if !buffer.context().ref_eq(ctx) {
+                return Err(DrawError::ContextMismatch("element buffer".to_string()));
+            }
 
-                for i in 0..attribute_info.locations {
-                    let offset = attribute.offset + i * attribute_info.location_size();
+            if buffer.is_mapped() {
+                return Err(DrawError::ElementBufferMapped);
+            }
+        }
 
-                    assert!(offset + attribute_info.location_size() <= *stride);
+        if self.index_range.start >= self.index_range.end || self.num_instances == 0 {
+            return Ok(());
+        }
 
-                    unsafe {
-                        gl.enable_vertex_attrib_array(index);
-                    }
+        if let Some((buffer, element_type)) = &self.element_data {
+            let num_elements = buffer.len() / element_type.size();
+
+            if self.index_range.end > num_elements {
+                return Err(DrawError::ElementRangeOutOfBounds {
+                    start: self.index_range.start,
+                    end: self.index_range.end,
+                    num_elements,
+                });
+            }
+        }
 
-                    let divisor = match input_rate {
-                        VertexInputRate::Vertex => 0,
-                        VertexInputRate::Instance => 1,
-                    };
+        let max_element = self
+            .element_indices
+            .as_ref()
+            .and_then(|indices| indices.max(self.index_range.clone()));
 
-                    unsafe {
-                        gl.vertex_attrib_divisor(index, divisor);
+        for (
+            index,
+            VertexBufferBinding {
+                stride,
+                range,
+                input_rate,
+                instance_divisor,
+                ..
+            },
+        ) in self.vertex_data.iter().enumerate()
+        {
+            let num = range.len() / stride;
+
+            match input_rate {
+                VertexInputRate::Vertex => match (&self.element_data, max_element) {
+                    (Some(_), Some(max_index)) if max_index >= num => {
+                        return Err(DrawError::ElementOutOfBounds {
+                            binding: index,
+                            path: vertex_binding_path(index),
+                            max_index,
+                            num_vertices: num,
+                        });
                     }
-
-                    let size = i32::try_from(attribute_info.components).unwrap();
-                    let data_type = attribute_info.ty.to_gl();
-                    let stride = i32::try_from(*stride).unwrap();
-                    let offset = i32::try_from(offset).unwrap();
-
-                    match attribute_info.ty {
-                        VertexAttributeType::F32 => unsafe {
-                            gl.vertex_attrib_pointer_f32(
-                                index, size, data_type, false, stride, offset,
-                            )
-                        },
-                        VertexAttributeType::I32 | VertexAttributeType::U32 => unsafe {
-                            gl.vertex_attrib_pointer_i32(index, size, data_type, stride, offset)
-                        },
+                    (None, _) if self.index_range.end > num => {
+                        return Err(DrawError::VertexRangeOutOfBounds {
+                            start: self.index_range.start,
+                            end: self.index_range.end,
+                            num_vertices: num,
+                        });
+                    }
+                    _ => (),
+                },
+                VertexInputRate::Instance => {
+                    if num < self.num_instances.div_ceil(*instance_divisor) {
+                        return Err(DrawError::InstanceRangeOutOfBounds {
+                            num_instances: self.num_instances,
+                            num_available: num * instance_divisor,
+                        });
                     }
-
-                    index += 1;
                 }
             }
         }
 
-        if let Some((buffer, _)) = self.element_data.as_ref() {
-            assert!(buffer.context().ref_eq(ctx));
+        Ok(())
+    }
 
-            unsafe {
-                gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(buffer.id()));
-            }
+    /// Returns the first vertex of the vertex buffer ranges if it can be passed
+    /// to the draw call instead of being part of the vertex array object.
+    ///
+    /// This requires all per-vertex ranges to start at the same vertex. Indexed
+    /// draw calls can not offset the vertices, since OpenGL ES 3.0 does not
+    /// have a base vertex, and instances can not be offset at all.
+    fn base_vertex(&self) -> Option<usize> {
+        if self.element_data.is_some() {
+            return None;
         }
 
-        #[cfg(debug_assertions)]
-        check_gl_error(gl, "").expect("OpenGL error after VertexSpec::bind()");
+        let mut starts = self
+            .vertex_data
+            .iter()
+            .filter(|binding| binding.input_rate == VertexInputRate::Vertex)
+            .map(|binding| binding.range.start / binding.stride);
+
+        let first = starts.next()?;
+
+        starts.all(|start| start == first).then_some(first)
     }
 
-    fn unbind(&self, ctx: &ContextShared) {
-        let gl = ctx.gl();
+    /// Fills `key` with the vertex array object state for this vertex
+    /// specification, with the per-vertex ranges offset by `base_vertex`.
+    ///
+    /// The attributes of the vertex blocks are given by `block_defs`, starting
+    /// at `block_locations`. The vertex stream must have been checked with
+    /// [`VertexSpec::check_compatible`] and [`VertexSpec::validate`] before, so
+    /// that all attributes are valid and in bounds of their buffers.
+    fn fill_vertex_array_key(
+        &self,
+        block_defs: &[VertexBlockDef],
+        block_locations: &[usize],
+        base_vertex: usize,
+        key: &mut VertexArrayKey,
+    ) -> Result<(), DrawError> {
+        key.attributes.clear();
+
+        for (
+            index,
+            (
+                (
+                    VertexBufferBinding {
+                        buffer,
+                        input_rate,
+                        instance_divisor,
+                        stride,
+                        range,
+                    },
+                    block_def,
+                ),
+                &location,
+            ),
+        ) in self
+            .vertex_data
+            .iter()
+            .zip(block_defs)
+            .zip(block_locations)
+            .enumerate()
+        {
+            let mut attribute_index = to_gl_int("vertex attribute location", location)?;
 
-        let mut index = 0;
+            let (divisor, start) = match input_rate {
+                VertexInputRate::Vertex => (0, range.start - base_vertex * stride),
+                VertexInputRate::Instance => (
+                    to_gl_int("instance divisor", *instance_divisor)?,
+                    range.start,
+                ),
+            };
 
-        for VertexBufferBinding { block_def, .. } in &self.vertex_data {
             for attribute in &block_def.attributes {
-                let attribute_info =
-                    VertexAttributeLayout::new(attribute.ty).expect("invalid vertex attribute");
+                let attribute_info = VertexAttributeLayout::new(attribute.ty, attribute.format)
+                    .map_err(|_| DrawError::IncompatibleVertexSpec {
+                        binding: index,
+                        path: vertex_binding_path(index),
+                    })?;
 
-                for _ in 0..attribute_info.locations {
-                    unsafe {
-                        gl.disable_vertex_attrib_array(index);
-                    }
+                for i in 0..attribute_info.locations {
+                    let offset = attribute.offset + i * attribute_info.location_size();
 
-                    index += 1;
+                    key.attributes.push(VertexAttributePointer {
+                        index: attribute_index,
+                        buffer: buffer.id(),
+                        size: to_gl_int("vertex attribute size", attribute_info.components)?,
+                        data_type: attribute_info.ty.to_gl(),
+                        integer: attribute_info.integer,
+                        normalized: attribute_info.normalized,
+                        stride: to_gl_int("vertex stride", *stride)?,
+                        offset: to_gl_int("vertex attribute offset", start + offset)?,
+                        divisor,
+                    });
+
+                    attribute_index += 1;
                 }
             }
         }
 
-        if self.element_data.is_some() {
-            unsafe {
-                gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None);
-            }
-        }
+        key.element_buffer = self.element_data.as_ref().map(|(buffer, _)| buffer.id());
 
-        #[cfg(debug_assertions)]
-        check_gl_error(gl, "").expect("OpenGL error after VertexSpec::unbind()");
+        Ok(())
     }
 
-    pub(super) fn draw(&self, ctx: &ContextShared) {
+    /// Draws the vertex stream with the attributes given by `block_defs`,
+    /// starting at `block_locations`.
+    ///
+    /// The vertex stream must have been checked with [`VertexSpec::validate`]
+    /// before.
+    pub(super) fn draw(
+        &self,
+        ctx: &ContextShared,
+        block_defs: &[VertexBlockDef],
+        block_locations: &[usize],
+    ) -> Result<(), DrawError> {
         if self.index_range.start >= self.index_range.end {
-            return;
+            return Ok(());
         }
 
         if self.num_instances == 0 {
-            return;
+            return Ok(());
         }
 
         let gl = ctx.gl();
 
-        self.bind(ctx);
-
         let mode = self.mode.to_gl();
         let first = self.index_range.start;
-        let count = self.index_range.end - self.index_range.start;
-
-        if let Some((buffer, element_type)) = &self.element_data {
-            let element_size = element_type.size();
+        let count = to_gl_int(
+            "element count",
+            self.index_range.end - self.index_range.start,
+        )?;
+        let num_instances = to_gl_int("instance count", self.num_instances)?;
+
+        let base_vertex = self.base_vertex().unwrap_or(0);
+
+        ctx.vertex_arrays().bind_with(gl, |key| {
+            self.fill_vertex_array_key(block_defs, block_locations, base_vertex, key)
+        })?;
+
+        if let Some((_, element_type)) = &self.element_data {
+            // The element range is in bounds of the element buffer, which is
+            // checked in `validate`, so the offset can not overflow.
+            let offset = to_gl_int("element offset", first * element_type.size())?;
             let element_type = element_type.to_gl();
 
-            let offset = first.checked_mul(element_size).unwrap();
-
-            // Safety: check element range.
-            {
-                let size = count.checked_mul(element_size).unwrap();
-
-                assert!(offset.checked_add(size).unwrap() <= buffer.len());
-            }
-
-            let count = count.try_into().expect("count is out of i32 range");
-            let offset = offset.try_into().expect("offset is out of i32 range");
-            let num_instances = self
-                .num_instances
-                .try_into()
-                .expect("num_instance is out of i32 range");
-
-            for VertexBufferBinding {
-                stride,
-                buffer,
-                input_rate,
-                ..
-            } in &self.vertex_data
-            {
-                let num = buffer.len() / stride;
-                match input_rate {
-                    VertexInputRate::Vertex => {
-                        // Safety: this is only safe if the element buffer does not have any
-                        // elements which are out of bound for one of the vertex buffers.
-                        // Here, we assume that this is checked by the caller.
-                    }
-                    VertexInputRate::Instance => {
-                        assert!(num >= self.num_instances);
-                    }
-                }
-            }
+            // Safety: this is only safe if the element buffer does not have any
+            // elements which are out of bound for one of the vertex buffers.
+            // This is checked in `validate` if the maximum element is known.
+            // Instance buffer sizes are checked in `validate` as well.
 
             unsafe {
                 gl.draw_elements_instanced(mode, count, element_type, offset, num_instances);
             }
         } else {
-            // Safety: check vertex buffer sizes.
-            let end = first.checked_add(count).unwrap();
-
-            for VertexBufferBinding {
-                stride,
-                buffer,
-                input_rate,
-                ..
-            } in &self.vertex_data
-            {
-                let num = buffer.len() / stride;
-                match input_rate {
-                    VertexInputRate::Vertex => {
-                        assert!(num >= end);
-                    }
-                    VertexInputRate::Instance => {
-                        assert!(num >= self.num_instances);
-                    }
-                }
-            }
+            // Safety: vertex and instance buffer sizes are checked in
+            // `validate`.
+            let first = to_gl_int("first vertex", base_vertex + first)?;
 
-            let first = first.try_into().expect("first is out of i32 range");
-            let count = count.try_into().expect("count is out of i32 range");
-            let num_instances = self
-                .num_instances
-                .try_into()
-                .expect("num_instance is out of i32 range");
             unsafe {
                 gl.draw_arrays_instanced(mode, first, count, num_instances);
             }
         }
 
         #[cfg(debug_assertions)]
-        check_gl_error(gl, "").expect("OpenGL error in VertexStream::draw()");
+        check_gl_error(gl, "after drawing vertex spec")?;
+
+        Ok(())
+    }
+}
+
+/// Converts `value` to the integer type that OpenGL takes for `name`.
+fn to_gl_int<T: TryFrom<usize>>(name: &'static str, value: usize) -> Result<T, DrawError> {
+    T::try_from(value).map_err(|_| DrawError::ParameterOutOfRange { name, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_gl_int, DrawError};
+
+    #[test]
+    fn out_of_range_parameters_are_errors() {
+        assert_eq!(to_gl_int::<i32>("element count", 42).unwrap(), 42);
+
+        let value = usize::try_from(i32::MAX).unwrap() + 1;
 
-        // TODO: Remove overly conservative unbinding.
-        self.unbind(ctx);
+        assert!(matches!(
+            to_gl_int::<i32>("element count", value),
+            Err(DrawError::ParameterOutOfRange {
+                name: "element count",
+                value: v,
+            }) if v == value
+        ));
     }
 }
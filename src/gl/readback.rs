@@ -0,0 +1,43 @@
+use super::{raw, ImageComponentType, ImageFormat, ReadbackError, ReadbackImage};
+
+/// Pixels that are being copied from a framebuffer into a pixel pack buffer.
+///
+/// Instances of `PixelReadback` can be created with
+/// [`Context::read_pixels_async`](crate::gl::Context::read_pixels_async). The
+/// copy is guarded by a fence, so [`PixelReadback::try_read`] can be polled
+/// every frame without stalling the pipeline.
+pub struct PixelReadback {
+    raw: raw::PixelReadback,
+}
+
+impl PixelReadback {
+    pub(super) fn from_raw(raw: raw::PixelReadback) -> Self {
+        Self { raw }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.raw.size()
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.raw.format()
+    }
+
+    pub fn ty(&self) -> ImageComponentType {
+        self.raw.ty()
+    }
+
+    pub fn is_ready(&self) -> Result<bool, ReadbackError> {
+        self.raw.is_ready()
+    }
+
+    /// Returns the pixels if they are available, without waiting for the GPU.
+    pub fn try_read(&self) -> Result<Option<ReadbackImage>, ReadbackError> {
+        self.raw.try_read()
+    }
+
+    /// Returns the pixels, waiting for the GPU if necessary.
+    pub fn read(&self) -> Result<ReadbackImage, ReadbackError> {
+        self.raw.read()
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    marker::PhantomData,
+    mem::{self, size_of},
+    ops::Range,
+    rc::Rc,
+};
+
+use crate::{Block, Gl};
+
+use super::{
+    element_buffer::indices, raw, BufferError, Element, ElementBufferBinding, PrimitiveMode,
+    VertexBufferBinding, VertexSpec,
+};
+
+/// Growable GPU storage that is appended to, like a `Vec`.
+struct Storage {
+    raw: Rc<raw::Buffer>,
+    len: usize,
+
+    /// Buffers that were replaced in [`Storage::clear`] while they were still
+    /// referenced by bindings.
+    retired: Vec<Rc<raw::Buffer>>,
+}
+
+impl Storage {
+    fn new(raw: raw::Buffer) -> Self {
+        Self {
+            raw: Rc::new(raw),
+            len: 0,
+            retired: Vec::new(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.raw.len()
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), BufferError> {
+        let needed = self.len + additional;
+
+        if needed <= self.capacity() {
+            return Ok(());
+        }
+
+        let capacity = needed.max(2 * self.capacity());
+
+        // Existing bindings keep referring to the old buffer, so their data
+        // stays intact.
+        self.raw = Rc::new(self.raw.resized(capacity, self.len)?);
+
+        Ok(())
+    }
+
+    fn extend(&mut self, data: &[u8]) -> Result<Range<usize>, BufferError> {
+        self.reserve(data.len())?;
+
+        let start = self.len;
+        self.raw.set_range(start, data)?;
+        self.len += data.len();
+
+        Ok(start..self.len)
+    }
+
+    fn clear(&mut self) -> Result<(), BufferError> {
+        if Rc::strong_count(&self.raw) == 1 {
+            self.raw.orphan()?;
+        } else {
+            // The buffer is still referenced by bindings, whose data must not
+            // be invalidated. Continue with a retired buffer whose bindings
+            // have all been dropped in the meantime, so that a buffer that is
+            // cleared every frame does not allocate every frame.
+            let capacity = self.capacity();
+            let free = self
+                .retired
+                .iter()
+                .position(|raw| Rc::strong_count(raw) == 1 && raw.len() >= capacity);
+
+            let raw = match free {
+                Some(index) => {
+                    let raw = self.retired.swap_remove(index);
+                    raw.orphan()?;
+                    raw
+                }
+                None => Rc::new(self.raw.resized(capacity, 0)?),
+            };
+
+            self.retired.push(mem::replace(&mut self.raw, raw));
+        }
+
+        // Keep only the buffers that are still referenced, so that the
+        // number of retired buffers stays bounded.
+        self.retired.retain(|raw| Rc::strong_count(raw) > 1);
+
+        self.len = 0;
+
+        Ok(())
+    }
+}
+
+/// Stores vertex blocks in a growable buffer on the GPU that is refilled
+/// frequently.
+///
+/// Blocks are appended to the buffer with
+/// [`extend_from_slice`](StreamingVertexBuffer::extend_from_slice), which grows
+/// the buffer as needed. [`clear`](StreamingVertexBuffer::clear) orphans the
+/// buffer's storage, so that it can be refilled without waiting for pending
+/// draw calls.
+///
+/// Bindings created from the buffer keep referring to the blocks that were in
+/// the buffer when the binding was created, even if the buffer is cleared or
+/// grows afterwards.
+///
+/// Instances of `StreamingVertexBuffer` can be created with
+/// [`Context::create_streaming_vertex_buffer`](crate::gl::Context::create_streaming_vertex_buffer).
+pub struct StreamingVertexBuffer<B> {
+    storage: Storage,
+    _phantom: PhantomData<B>,
+}
+
+impl<B> StreamingVertexBuffer<B>
+where
+    B: Block<Gl> + bytemuck::Pod,
+{
+    pub(super) fn from_raw(raw: raw::Buffer) -> Self {
+        assert!(size_of::<B>() > 0);
+
+        Self {
+            storage: Storage::new(raw),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.storage.len / size_of::<B>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of blocks that the buffer can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.storage.capacity() / size_of::<B>()
+    }
+
+    /// Grows the buffer so that it can hold at least `additional` more blocks.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), BufferError> {
+        self.storage.reserve(additional * size_of::<B>())
+    }
+
+    /// Appends `data` to the buffer, returning the range of the new blocks.
+    pub fn extend_from_slice(&mut self, data: &[B]) -> Result<Range<usize>, BufferError> {
+        let range = self.storage.extend(bytemuck::cast_slice(data))?;
+
+        Ok(range.start / size_of::<B>()..range.end / size_of::<B>())
+    }
+
+    /// Appends `block` to the buffer, returning its index.
+    pub fn push(&mut self, block: B) -> Result<usize, BufferError> {
+        Ok(self.extend_from_slice(&[block])?.start)
+    }
+
+    /// Removes all blocks from the buffer, keeping its capacity.
+    pub fn clear(&mut self) -> Result<(), BufferError> {
+        self.storage.clear()
+    }
+
+    /// Returns a binding to the blocks that are currently in the buffer.
+    pub fn as_binding(&self) -> VertexBufferBinding<B::Sl> {
+        VertexBufferBinding::from_raw(self.storage.raw.clone(), Some(0..self.len()))
+    }
+
+    pub fn as_vertex_spec(&self, mode: PrimitiveMode) -> VertexSpec<B::Sl> {
+        VertexSpec::new(mode).with_vertex_data(self.as_binding())
+    }
+}
+
+/// Stores element data in a growable buffer on the GPU that is refilled
+/// frequently.
+///
+/// This is the element counterpart of [`StreamingVertexBuffer`].
+///
+/// Instances of `StreamingElementBuffer` can be created with
+/// [`Context::create_streaming_element_buffer`](crate::gl::Context::create_streaming_element_buffer).
+pub struct StreamingElementBuffer<E = u32> {
+    storage: Storage,
+    indices: Rc<raw::ElementIndices>,
+    _phantom: PhantomData<E>,
+}
+
+impl<E: Element> StreamingElementBuffer<E> {
+    pub(super) fn from_raw(raw: raw::Buffer) -> Self {
+        Self {
+            storage: Storage::new(raw),
+            indices: Rc::new(raw::ElementIndices::new()),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.storage.len / size_of::<E>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of elements that the buffer can hold without
+    /// growing.
+    pub fn capacity(&self) -> usize {
+        self.storage.capacity() / size_of::<E>()
+    }
+
+    /// Grows the buffer so that it can hold at least `additional` more
+    /// elements.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), BufferError> {
+        self.storage.reserve(additional * size_of::<E>())
+    }
+
+    /// Appends `data` to the buffer, returning the range of the new elements.
+    pub fn extend_from_slice(&mut self, data: &[E]) -> Result<Range<usize>, BufferError> {
+        let range = self.storage.extend(bytemuck::cast_slice(data))?;
+        let range = range.start / size_of::<E>()..range.end / size_of::<E>();
+
+        self.indices.write(range.start, indices(data));
+
+        Ok(range)
+    }
+
+    /// Removes all elements from the buffer, keeping its capacity.
+    pub fn clear(&mut self) -> Result<(), BufferError> {
+        self.storage.clear()?;
+
+        if Rc::strong_count(&self.indices) == 1 {
+            self.indices.clear();
+        } else {
+            // Bindings keep referring to the old elements.
+            self.indices = Rc::new(raw::ElementIndices::new());
+        }
+
+        Ok(())
+    }
+
+    /// Returns a binding to the elements that are currently in the buffer.
+    pub fn as_binding(&self) -> ElementBufferBinding {
+        // Elements are only appended until the buffer is cleared, so the
+        // binding's elements stay the same.
+        ElementBufferBinding::from_raw(
+            self.storage.raw.clone(),
+            E::TYPE,
+            0..self.len(),
+            self.indices.clone(),
+        )
+    }
+}
@@ -4,7 +4,7 @@ use crate::sl::{self, ColorSample};
 
 use super::{
     raw::{self, Sampler2dSettings},
-    ColorAttachment, ColorImage, Comparison, DepthAttachment, DepthImage, TextureError,
+    ColorAttachment, ColorImage, Comparison, DepthAttachment, DepthImage, Rect, TextureError,
 };
 
 pub struct ColorTexture2d<S = sl::Vec4> {
@@ -36,6 +36,17 @@ impl<S> ColorTexture2d<S> {
             _phantom: PhantomData,
         }
     }
+
+    pub(super) fn from_shared_raw(raw: Rc<raw::Texture2d>) -> Self {
+        Self {
+            raw,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(super) fn raw(&self) -> &Rc<raw::Texture2d> {
+        &self.raw
+    }
 }
 
 impl<S: ColorSample> ColorTexture2d<S> {
@@ -63,13 +74,43 @@ impl<S: ColorSample> ColorTexture2d<S> {
         self.raw.size()
     }
 
+    pub fn levels(&self) -> usize {
+        self.raw.levels()
+    }
+
     pub fn set(
         &self,
         level: usize,
         lower_left_corner: [u32; 2],
         image: ColorImage<S>,
     ) -> Result<(), TextureError> {
-        self.raw.set(level, lower_left_corner, image.raw())
+        let image = image.raw();
+        let rect = Rect {
+            lower_left_corner,
+            size: image.size,
+        };
+
+        self.raw.set_region(level, rect, image)
+    }
+
+    /// Replaces a region of the given level with `image`, which must have the
+    /// same size as `rect`.
+    pub fn set_region(
+        &self,
+        level: usize,
+        rect: Rect,
+        image: ColorImage<S>,
+    ) -> Result<(), TextureError> {
+        self.raw.set_region(level, rect, image.raw())
+    }
+
+    /// Regenerates all mipmap levels from the contents of level 0.
+    ///
+    /// This is useful after rendering into level 0. The texture must have
+    /// been created with mipmap levels, for example with
+    /// [`Context::create_color_texture_2d_with_mipmap`](crate::gl::Context::create_color_texture_2d_with_mipmap).
+    pub fn generate_mipmaps(&self) -> Result<(), TextureError> {
+        self.raw.generate_mipmaps()
     }
 }
 
@@ -79,6 +120,14 @@ impl DepthTexture2d {
 
         Self { raw: Rc::new(raw) }
     }
+
+    pub(super) fn from_shared_raw(raw: Rc<raw::Texture2d>) -> Self {
+        Self { raw }
+    }
+
+    pub(super) fn raw(&self) -> &Rc<raw::Texture2d> {
+        &self.raw
+    }
 }
 
 impl DepthTexture2d {
@@ -119,13 +168,34 @@ impl DepthTexture2d {
         self.raw.size()
     }
 
+    pub fn levels(&self) -> usize {
+        self.raw.levels()
+    }
+
     pub fn set(
         &self,
         level: usize,
         lower_left_corner: [u32; 2],
         image: DepthImage,
     ) -> Result<(), TextureError> {
-        self.raw.set(level, lower_left_corner, image.raw())
+        let image = image.raw();
+        let rect = Rect {
+            lower_left_corner,
+            size: image.size,
+        };
+
+        self.raw.set_region(level, rect, image)
+    }
+
+    /// Replaces a region of the given level with `image`, which must have the
+    /// same size as `rect`.
+    pub fn set_region(
+        &self,
+        level: usize,
+        rect: Rect,
+        image: DepthImage,
+    ) -> Result<(), TextureError> {
+        self.raw.set_region(level, rect, image.raw())
     }
 }
 
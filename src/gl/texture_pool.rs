@@ -0,0 +1,415 @@
+use std::{
+    cell::RefCell,
+    ops::Deref,
+    rc::{Rc, Weak},
+};
+
+use crate::sl::{self, ColorSample};
+
+use super::{raw, ColorTexture2d, DepthTexture2d, ImageInternalFormat, TextureError};
+
+/// The number of frames for which unused textures are kept by default.
+const DEFAULT_MAX_UNUSED_FRAMES: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PoolKey {
+    size: [u32; 2],
+    internal_format: ImageInternalFormat,
+    levels: usize,
+}
+
+struct PoolEntry<T> {
+    key: PoolKey,
+    texture: Rc<T>,
+    leased: bool,
+    last_used_frame: u64,
+}
+
+/// The bookkeeping of a pool, which decides when textures are reused and
+/// dropped, independently of how they are created.
+struct PoolEntries<T> {
+    entries: Vec<PoolEntry<T>>,
+    frame: u64,
+    max_unused_frames: u64,
+}
+
+impl<T> PoolEntries<T> {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            frame: 0,
+            max_unused_frames: DEFAULT_MAX_UNUSED_FRAMES,
+        }
+    }
+
+    /// Leases an entry with `key` if there is one that can be reused.
+    fn lease(&mut self, key: PoolKey) -> Option<Rc<T>> {
+        let frame = self.frame;
+
+        // Attachments and samplers that were created from a lease can outlive
+        // it, and their owners may still draw with them, so their textures are
+        // not handed out again. Pending draw calls, on the other hand, do not
+        // prevent reuse: GL executes commands in order, so draw calls that
+        // render into the texture later wait for earlier ones that sample it.
+        let entry = self.entries.iter_mut().find(|entry| {
+            entry.key == key && !entry.leased && Rc::strong_count(&entry.texture) == 1
+        })?;
+
+        entry.leased = true;
+        entry.last_used_frame = frame;
+
+        Some(entry.texture.clone())
+    }
+
+    /// Adds a newly created texture that is leased.
+    fn insert_leased(&mut self, key: PoolKey, texture: Rc<T>) {
+        self.entries.push(PoolEntry {
+            key,
+            texture,
+            leased: true,
+            last_used_frame: self.frame,
+        });
+    }
+
+    /// Returns a leased texture to the pool.
+    fn release(&mut self, texture: &Rc<T>) {
+        let frame = self.frame;
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| Rc::ptr_eq(&entry.texture, texture))
+        {
+            entry.leased = false;
+            entry.last_used_frame = frame;
+        }
+    }
+
+    /// Advances to the next frame and drops the textures that have not been
+    /// leased in the last `max_unused_frames` frames.
+    fn next_frame(&mut self) {
+        self.frame += 1;
+
+        let frame = self.frame;
+        let max_unused_frames = self.max_unused_frames;
+
+        self.entries
+            .retain(|entry| entry.leased || frame - entry.last_used_frame <= max_unused_frames);
+    }
+
+    /// Drops all textures that are not leased.
+    fn clear(&mut self) {
+        self.entries.retain(|entry| entry.leased);
+    }
+}
+
+struct PoolState {
+    gl: Rc<raw::Context>,
+    entries: PoolEntries<raw::Texture2d>,
+}
+
+/// A pool of textures that are reused as render targets.
+///
+/// Textures are leased from the pool by size, internal format and number of
+/// levels. When a lease is dropped, its texture returns to the pool and can be
+/// leased again. A texture is only handed out again once all attachments and
+/// samplers that were created from it have been dropped as well.
+///
+/// The pool is advanced with [`next_frame`](TexturePool::next_frame), which
+/// drops textures that have not been leased for a number of frames, e.g.
+/// textures of an old window size. Executing a
+/// [`FrameGraph`](super::graph::FrameGraph) does this automatically; otherwise,
+/// `next_frame` should be called once per frame.
+///
+/// The pool of a context is obtained with
+/// [`Context::texture_pool`](super::Context::texture_pool).
+#[derive(Clone)]
+pub struct TexturePool {
+    state: Rc<RefCell<PoolState>>,
+}
+
+impl TexturePool {
+    pub(super) fn new(gl: Rc<raw::Context>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(PoolState {
+                gl,
+                entries: PoolEntries::new(),
+            })),
+        }
+    }
+
+    /// Leases a color texture.
+    ///
+    /// The contents of the texture are undefined.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `internal_format` is not a color format or if `levels` is
+    /// zero.
+    pub fn lease_color_texture_2d<S: ColorSample>(
+        &self,
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        levels: usize,
+    ) -> Result<LeasedColorTexture2d<S>, TextureError> {
+        assert!(internal_format.is_color());
+
+        let lease = self.lease_raw(size, internal_format, levels)?;
+        let texture = ColorTexture2d::from_shared_raw(lease.texture().clone());
+
+        Ok(LeasedColorTexture2d {
+            texture,
+            _lease: lease,
+        })
+    }
+
+    /// Leases a depth texture.
+    ///
+    /// The contents of the texture are undefined.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `internal_format` is not a depth format or if `levels` is
+    /// zero.
+    pub fn lease_depth_texture_2d(
+        &self,
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        levels: usize,
+    ) -> Result<LeasedDepthTexture2d, TextureError> {
+        assert!(internal_format.is_depth_renderable());
+
+        let lease = self.lease_raw(size, internal_format, levels)?;
+        let texture = DepthTexture2d::from_shared_raw(lease.texture().clone());
+
+        Ok(LeasedDepthTexture2d {
+            texture,
+            _lease: lease,
+        })
+    }
+
+    /// Advances the pool to the next frame and drops the textures that have
+    /// not been leased in the last `max_unused_frames` frames.
+    pub fn next_frame(&self) {
+        self.state.borrow_mut().entries.next_frame();
+    }
+
+    /// Sets the number of frames for which textures are kept in the pool
+    /// after they were last leased.
+    pub fn set_max_unused_frames(&self, frames: u64) {
+        self.state.borrow_mut().entries.max_unused_frames = frames;
+    }
+
+    /// Drops all textures that are not leased.
+    pub fn clear(&self) {
+        self.state.borrow_mut().entries.clear();
+    }
+
+    /// Returns the number of textures in the pool, including leased ones.
+    pub fn len(&self) -> usize {
+        self.state.borrow().entries.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(super) fn lease_raw(
+        &self,
+        size: [u32; 2],
+        internal_format: ImageInternalFormat,
+        levels: usize,
+    ) -> Result<RawLease, TextureError> {
+        let key = PoolKey {
+            size,
+            internal_format,
+            levels,
+        };
+
+        let mut state = self.state.borrow_mut();
+
+        let texture = match state.entries.lease(key) {
+            Some(texture) => texture,
+            None => {
+                let texture = Rc::new(state.gl.create_texture_2d_uninitialized(
+                    size,
+                    internal_format,
+                    levels,
+                )?);
+
+                state.entries.insert_leased(key, texture.clone());
+
+                texture
+            }
+        };
+
+        Ok(RawLease {
+            pool: Rc::downgrade(&self.state),
+            texture,
+        })
+    }
+}
+
+/// A texture that is leased from a [`TexturePool`] and returned on drop.
+pub(super) struct RawLease {
+    pool: Weak<RefCell<PoolState>>,
+    texture: Rc<raw::Texture2d>,
+}
+
+impl RawLease {
+    pub fn texture(&self) -> &Rc<raw::Texture2d> {
+        &self.texture
+    }
+}
+
+impl Drop for RawLease {
+    fn drop(&mut self) {
+        let Some(state) = self.pool.upgrade() else {
+            return;
+        };
+
+        state.borrow_mut().entries.release(&self.texture);
+    }
+}
+
+/// A color texture that is leased from a [`TexturePool`].
+///
+/// The texture returns to the pool when this is dropped.
+pub struct LeasedColorTexture2d<S = sl::Vec4> {
+    texture: ColorTexture2d<S>,
+    _lease: RawLease,
+}
+
+impl<S> Deref for LeasedColorTexture2d<S> {
+    type Target = ColorTexture2d<S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.texture
+    }
+}
+
+/// A depth texture that is leased from a [`TexturePool`].
+///
+/// The texture returns to the pool when this is dropped.
+pub struct LeasedDepthTexture2d {
+    texture: DepthTexture2d,
+    _lease: RawLease,
+}
+
+impl Deref for LeasedDepthTexture2d {
+    type Target = DepthTexture2d;
+
+    fn deref(&self) -> &Self::Target {
+        &self.texture
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{ImageInternalFormat, PoolEntries, PoolKey};
+
+    fn key(size: u32) -> PoolKey {
+        PoolKey {
+            size: [size, size],
+            internal_format: ImageInternalFormat::RgbaU8,
+            levels: 1,
+        }
+    }
+
+    fn lease(entries: &mut PoolEntries<u32>, key: PoolKey, id: u32) -> Rc<u32> {
+        entries.lease(key).unwrap_or_else(|| {
+            let texture = Rc::new(id);
+            entries.insert_leased(key, texture.clone());
+            texture
+        })
+    }
+
+    #[test]
+    fn released_textures_are_reused() {
+        let mut entries = PoolEntries::new();
+
+        let a = lease(&mut entries, key(64), 1);
+        let b = lease(&mut entries, key(64), 2);
+
+        // Leased textures are not handed out twice.
+        assert_eq!((*a, *b), (1, 2));
+
+        entries.release(&a);
+        drop(a);
+
+        assert_eq!(*lease(&mut entries, key(64), 3), 1);
+        assert_eq!(entries.entries.len(), 2);
+    }
+
+    #[test]
+    fn textures_are_only_reused_with_the_same_key() {
+        let mut entries = PoolEntries::new();
+
+        let a = lease(&mut entries, key(64), 1);
+        entries.release(&a);
+        drop(a);
+
+        assert_eq!(*lease(&mut entries, key(32), 2), 2);
+    }
+
+    #[test]
+    fn textures_that_are_still_referenced_are_not_reused() {
+        let mut entries = PoolEntries::new();
+
+        let a = lease(&mut entries, key(64), 1);
+        entries.release(&a);
+
+        // `a` stands in for an attachment or sampler that outlives its lease.
+        assert_eq!(*lease(&mut entries, key(64), 2), 2);
+
+        drop(a);
+
+        assert_eq!(*lease(&mut entries, key(64), 3), 1);
+    }
+
+    #[test]
+    fn unused_textures_are_dropped_after_max_unused_frames() {
+        let mut entries = PoolEntries::new();
+        entries.max_unused_frames = 2;
+
+        let a = lease(&mut entries, key(64), 1);
+        let b = lease(&mut entries, key(32), 2);
+
+        entries.release(&a);
+        drop(a);
+
+        entries.next_frame();
+        entries.next_frame();
+
+        assert_eq!(entries.entries.len(), 2);
+
+        // Leased textures are kept no matter how long they are leased.
+        entries.next_frame();
+
+        assert_eq!(entries.entries.len(), 1);
+        assert!(Rc::ptr_eq(&entries.entries[0].texture, &b));
+
+        // Releasing a texture restarts its count of unused frames.
+        entries.release(&b);
+        entries.next_frame();
+        entries.next_frame();
+
+        assert_eq!(entries.entries.len(), 1);
+    }
+
+    #[test]
+    fn clear_keeps_leased_textures() {
+        let mut entries = PoolEntries::new();
+
+        let a = lease(&mut entries, key(64), 1);
+        let b = lease(&mut entries, key(64), 2);
+        entries.release(&a);
+
+        entries.clear();
+
+        assert_eq!(entries.entries.len(), 1);
+        assert!(Rc::ptr_eq(&entries.entries[0].texture, &b));
+    }
+}
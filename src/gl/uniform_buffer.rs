@@ -1,25 +1,34 @@
-use std::{marker::PhantomData, mem::size_of, rc::Rc};
+use std::{marker::PhantomData, mem::size_of, ops::Range, rc::Rc};
 
 use crevice::std140::{AsStd140, Std140};
 
 use crate::{Block, Gl};
 
-use super::{raw, BufferError, BufferUsage};
+use super::{raw, BufferError, BufferMap, BufferMapMut, BufferMapSettings, BufferUsage};
 
 /// Stores a uniform block in a buffer on the GPU.
 ///
 /// Instances of `UniformBuffer` can be created with
 /// [`Context::create_uniform_buffer`](crate::gl::Context::create_uniform_buffer).
-pub struct UniformBuffer<B> {
+///
+/// A `UniformBuffer<[B]>` stores many blocks in a single buffer, each of which
+/// can be bound separately. These can be created with
+/// [`Context::create_uniform_buffer_array`](crate::gl::Context::create_uniform_buffer_array).
+pub struct UniformBuffer<B: ?Sized> {
     pub(super) raw: Rc<raw::Buffer>,
+
+    /// The distance in bytes between the starts of consecutive blocks.
+    stride: usize,
+
     _phantom: PhantomData<B>,
 }
 
 #[derive(Clone)]
 pub struct UniformBufferBinding<B> {
-    raw: Rc<raw::Buffer>,
+    /// The bound buffer, or `None` for the global uniform block of type `B`.
+    raw: Option<Rc<raw::Buffer>>,
+    range: Range<usize>,
     _phantom: PhantomData<B>,
-    // TODO: UniformInterface buffer slicing.
 }
 
 impl<B: Block<Gl>> UniformBuffer<B> {
@@ -35,6 +44,7 @@ impl<B: Block<Gl>> UniformBuffer<B> {
         let raw = ctx.create_buffer(bytes, glow::UNIFORM_BUFFER, usage)?;
 
         Ok(Self {
+            stride: raw.len(),
             raw: Rc::new(raw),
             _phantom: PhantomData,
         })
@@ -44,28 +54,368 @@ impl<B: Block<Gl>> UniformBuffer<B> {
         self.raw.usage()
     }
 
-    pub fn set(&self, data: B::Gl) {
+    /// Replaces the block in the buffer.
+    ///
+    /// Returns an error if the buffer is mapped.
+    pub fn set(&self, data: B::Gl) -> Result<(), BufferError> {
         let mut buffer = Vec::new();
         let data = data.as_std140();
         let bytes = to_bytes(&data, &mut buffer);
 
-        self.raw.set(bytes);
+        self.raw.set(bytes)
+    }
+
+    /// Maps the block for writing, in its `std140` representation.
+    ///
+    /// The mapping contains exactly one item. Buffer mapping is not available
+    /// in WebGL, where this returns an error.
+    pub fn map_mut(
+        &self,
+        settings: BufferMapSettings,
+    ) -> Result<BufferMapMut<'_, <B::Gl as AsStd140>::Output>, BufferError> {
+        let len = size_of::<<B::Gl as AsStd140>::Output>();
+        let raw = self.raw.map_write(0, len, settings)?;
+
+        BufferMapMut::from_raw(raw)
+    }
+
+    /// Maps the block for reading, in its `std140` representation.
+    ///
+    /// The mapping contains exactly one item. Buffer mapping is not available
+    /// in WebGL, where this returns an error.
+    pub fn map(&self) -> Result<BufferMap<'_, <B::Gl as AsStd140>::Output>, BufferError> {
+        let len = size_of::<<B::Gl as AsStd140>::Output>();
+        let raw = self.raw.map_read(0, len)?;
+
+        BufferMap::from_raw(raw)
     }
 
     pub fn as_binding(&self) -> UniformBufferBinding<B::Sl> {
         UniformBufferBinding {
-            raw: self.raw.clone(),
+            raw: Some(self.raw.clone()),
+            range: 0..self.raw.len(),
             _phantom: PhantomData,
         }
     }
 }
 
+impl<B: Block<Gl>> UniformBuffer<[B]> {
+    pub(super) fn new_array(
+        ctx: &raw::Context,
+        data: &[B::Gl],
+        usage: BufferUsage,
+    ) -> Result<Self, BufferError> {
+        // OpenGL ES 3.0.6: 2.10.1.1 Binding Buffer Objects to Indexed Targets
+        // > An `INVALID_VALUE` error is generated if `offset` is not a
+        // > multiple of the value of `UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+        let alignment = usize::try_from(ctx.caps().uniform_buffer_offset_alignment).unwrap();
+        let stride = padded_len::<<B::Gl as AsStd140>::Output>().next_multiple_of(alignment);
+
+        let raw = ctx.create_buffer(
+            &array_to_bytes::<B>(data, stride),
+            glow::UNIFORM_BUFFER,
+            usage,
+        )?;
+
+        Ok(Self {
+            raw: Rc::new(raw),
+            stride,
+            _phantom: PhantomData,
+        })
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.raw.usage()
+    }
+
+    /// Returns the number of blocks in the buffer.
+    pub fn len(&self) -> usize {
+        self.raw.len() / self.stride
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replaces all blocks in the buffer, reallocating it.
+    ///
+    /// Returns an error if the buffer is mapped.
+    pub fn set(&self, data: &[B::Gl]) -> Result<(), BufferError> {
+        self.raw.set(&array_to_bytes::<B>(data, self.stride))
+    }
+
+    /// Overwrites the block at `index`.
+    pub fn set_element(&self, index: usize, data: B::Gl) -> Result<(), BufferError> {
+        if index >= self.len() {
+            return Err(BufferError::OutOfBounds {
+                offset: index * self.stride,
+                len: self.stride,
+                buffer_len: self.raw.len(),
+            });
+        }
+
+        let mut buffer = Vec::new();
+        let data = data.as_std140();
+        let bytes = to_bytes(&data, &mut buffer);
+
+        self.raw.set_range(index * self.stride, bytes)
+    }
+
+    /// Maps the block at `index` for writing, in its `std140` representation.
+    ///
+    /// The mapping contains exactly one item. Buffer mapping is not available
+    /// in WebGL, where this returns an error.
+    pub fn map_element_mut(
+        &self,
+        index: usize,
+        settings: BufferMapSettings,
+    ) -> Result<BufferMapMut<'_, <B::Gl as AsStd140>::Output>, BufferError> {
+        let len = size_of::<<B::Gl as AsStd140>::Output>();
+        let raw = self.raw.map_write(index * self.stride, len, settings)?;
+
+        BufferMapMut::from_raw(raw)
+    }
+
+    /// Maps the block at `index` for reading, in its `std140` representation.
+    ///
+    /// The mapping contains exactly one item. Buffer mapping is not available
+    /// in WebGL, where this returns an error.
+    pub fn map_element(
+        &self,
+        index: usize,
+    ) -> Result<BufferMap<'_, <B::Gl as AsStd140>::Output>, BufferError> {
+        let len = size_of::<<B::Gl as AsStd140>::Output>();
+        let raw = self.raw.map_read(index * self.stride, len)?;
+
+        BufferMap::from_raw(raw)
+    }
+
+    /// Returns a binding to the block at `index`.
+    ///
+    /// See [`get_binding`](Self::get_binding) for a non-panicking version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn binding(&self, index: usize) -> UniformBufferBinding<B::Sl> {
+        self.get_binding(index)
+            .expect("uniform buffer index out of bounds")
+    }
+
+    /// Returns a binding to the block at `index`, or `None` if `index` is out
+    /// of bounds.
+    pub fn get_binding(&self, index: usize) -> Option<UniformBufferBinding<B::Sl>> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let start = index * self.stride;
+        let len = padded_len::<<B::Gl as AsStd140>::Output>();
+
+        Some(UniformBufferBinding {
+            raw: Some(self.raw.clone()),
+            range: start..start + len,
+            _phantom: PhantomData,
+        })
+    }
+}
+
 impl<B> UniformBufferBinding<B> {
-    pub(super) fn raw(&self) -> &raw::Buffer {
-        &self.raw
+    /// Returns a binding that refers to the global uniform block of type `B`.
+    ///
+    /// Drawing with this binding does not bind anything, since the block is
+    /// already bound by
+    /// [`Context::set_global_uniform_block`](super::Context::set_global_uniform_block).
+    /// Drawing fails if the program was created before the global block was
+    /// set.
+    pub fn global() -> Self {
+        Self {
+            raw: None,
+            range: 0..0,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(super) fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub(super) fn shared_raw(&self) -> Option<Rc<raw::Buffer>> {
+        self.raw.clone()
     }
 }
 
+/// A placeholder for a uniform block that is provided globally by the context.
+///
+/// See [`Context::set_global_uniform_block`](super::Context::set_global_uniform_block).
+pub struct GlobalUniformBlock<B> {
+    _phantom: PhantomData<B>,
+}
+
+impl<B> GlobalUniformBlock<B> {
+    pub fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<B> Default for GlobalUniformBlock<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> Clone for GlobalUniformBlock<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B> Copy for GlobalUniformBlock<B> {}
+
+/// Storage for transient uniform block values.
+///
+/// Values are written into consecutive, suitably aligned ranges of a shared
+/// buffer. Once a buffer is full, the ring moves on to a buffer that is no
+/// longer referenced by any binding, or creates a new one. This way, values
+/// that are still referenced are never overwritten.
+pub(crate) struct UniformRing {
+    gl: Rc<raw::Context>,
+    buffers: Vec<Rc<raw::Buffer>>,
+    current: usize,
+    offset: usize,
+
+    /// Scratch space for padding values.
+    padding: Vec<u8>,
+}
+
+impl UniformRing {
+    const BUFFER_LEN: usize = 64 * 1024;
+
+    /// The maximum number of buffers that the ring keeps for reuse.
+    const MAX_BUFFERS: usize = 16;
+
+    pub fn new(gl: Rc<raw::Context>) -> Self {
+        Self {
+            gl,
+            buffers: Vec::new(),
+            current: 0,
+            offset: 0,
+            padding: Vec::new(),
+        }
+    }
+
+    pub fn push<B>(
+        &mut self,
+        value: &impl AsStd140,
+    ) -> Result<UniformBufferBinding<B>, BufferError> {
+        let mut padding = std::mem::take(&mut self.padding);
+        let value = value.as_std140();
+        let bytes = to_bytes(&value, &mut padding);
+
+        let result = self.push_bytes(bytes);
+        self.padding = padding;
+
+        let (raw, range) = result?;
+
+        Ok(UniformBufferBinding {
+            raw: Some(raw),
+            range,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) -> Result<(Rc<raw::Buffer>, Range<usize>), BufferError> {
+        let alignment = usize::try_from(self.gl.caps().uniform_buffer_offset_alignment).unwrap();
+        let offset = self.offset.next_multiple_of(alignment);
+
+        let fits = self
+            .buffers
+            .get(self.current)
+            .is_some_and(|raw| offset + bytes.len() <= raw.len());
+
+        let offset = if fits {
+            offset
+        } else {
+            self.next_buffer(bytes.len())?;
+            0
+        };
+
+        let raw = self.buffers[self.current].clone();
+        raw.set_range(offset, bytes)?;
+
+        self.offset = offset + bytes.len();
+
+        Ok((raw, offset..offset + bytes.len()))
+    }
+
+    fn next_buffer(&mut self, len: usize) -> Result<(), BufferError> {
+        // Buffers that are referenced only by the ring have no bindings left,
+        // but draw calls that are still pending on the GPU may read from them.
+        // Orphaning gives the buffer new storage, so that writing to it does
+        // not have to wait for these draw calls.
+        let free = self
+            .buffers
+            .iter()
+            .position(|raw| Rc::strong_count(raw) == 1 && raw.len() >= len);
+
+        if let Some(index) = free {
+            self.buffers[index].orphan()?;
+            self.current = index;
+            self.offset = 0;
+
+            return Ok(());
+        }
+
+        if self.buffers.len() >= Self::MAX_BUFFERS {
+            // Stop keeping a buffer, preferring one that is too small to be
+            // reused. Buffers that are still referenced by bindings are
+            // deleted once the bindings are dropped.
+            let index = self
+                .buffers
+                .iter()
+                .position(|raw| Rc::strong_count(raw) == 1)
+                .unwrap_or(0);
+
+            self.buffers.swap_remove(index);
+        }
+
+        let raw = self.gl.create_buffer_with_len(
+            len.max(Self::BUFFER_LEN),
+            glow::UNIFORM_BUFFER,
+            BufferUsage::StreamDraw,
+        )?;
+
+        self.buffers.push(Rc::new(raw));
+        self.current = self.buffers.len() - 1;
+        self.offset = 0;
+
+        Ok(())
+    }
+}
+
+fn array_to_bytes<B: Block<Gl>>(data: &[B::Gl], stride: usize) -> Vec<u8> {
+    let mut bytes = vec![0; data.len() * stride];
+    let mut buffer = Vec::new();
+
+    for (value, chunk) in data.iter().zip(bytes.chunks_exact_mut(stride)) {
+        let value = value.as_std140();
+        let value_bytes = to_bytes(&value, &mut buffer);
+
+        chunk[..value_bytes.len()].copy_from_slice(value_bytes);
+    }
+
+    bytes
+}
+
+/// Returns the number of bytes that [`to_bytes`] produces.
+fn padded_len<B: Std140>() -> usize {
+    size_of::<<B as AsStd140>::Output>().next_multiple_of(MIN_ALIGNMENT)
+}
+
+const MIN_ALIGNMENT: usize = 16;
+
 fn to_bytes<'a, B: Std140>(data: &'a B, buffer: &'a mut Vec<u8>) -> &'a [u8] {
     // FIXME: This is a workaround for cases like an uniform buffer that
     // contains only a `Vec2`. In this case, `crevice` gives us a type that is
@@ -73,8 +423,6 @@ fn to_bytes<'a, B: Std140>(data: &'a B, buffer: &'a mut Vec<u8>) -> &'a [u8] {
     // to round up to multiples of 16 bytes. At least Firefox complains
     // if we do not do this.
 
-    const MIN_ALIGNMENT: usize = 16;
-
     let bytes = data.as_bytes();
 
     let rem = size_of::<<B as AsStd140>::Output>() % MIN_ALIGNMENT;
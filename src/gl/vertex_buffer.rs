@@ -1,8 +1,11 @@
-use std::{marker::PhantomData, mem::size_of, rc::Rc};
+use std::{marker::PhantomData, mem::size_of, ops::Range, rc::Rc};
 
 use crate::{sl::program_def::VertexInputRate, Block, Gl, Sl};
 
-use super::{raw, BufferUsage, PrimitiveMode, VertexSpec};
+use super::{
+    raw, BufferError, BufferMap, BufferMapMut, BufferMapSettings, BufferUsage, PrimitiveMode,
+    VertexSpec,
+};
 
 /// Stores vertex blocks in a buffer on the GPU.
 ///
@@ -16,7 +19,9 @@ pub struct VertexBuffer<B> {
 #[derive(Clone)]
 pub struct VertexBufferBinding<B> {
     raw: Rc<raw::Buffer>,
+    range: Option<Range<usize>>,
     input_rate: VertexInputRate,
+    instance_divisor: usize,
     _phantom: PhantomData<B>,
 }
 
@@ -48,16 +53,52 @@ where
         self.len() == 0
     }
 
-    pub fn set(&self, data: &[B]) {
-        self.raw.set(bytemuck::cast_slice(data));
+    /// Replaces all blocks in the buffer, reallocating it.
+    ///
+    /// Returns an error if the buffer is mapped.
+    pub fn set(&self, data: &[B]) -> Result<(), BufferError> {
+        self.raw.set(bytemuck::cast_slice(data))
+    }
+
+    /// Overwrites the blocks starting at `offset` with `data`, without
+    /// reallocating the buffer.
+    pub fn set_range(&self, offset: usize, data: &[B]) -> Result<(), BufferError> {
+        self.raw
+            .set_range(offset * size_of::<B>(), bytemuck::cast_slice(data))
+    }
+
+    /// Maps the blocks in `range` for writing.
+    ///
+    /// Buffer mapping is not available in WebGL, where this returns an error.
+    pub fn map_mut(
+        &self,
+        range: Range<usize>,
+        settings: BufferMapSettings,
+    ) -> Result<BufferMapMut<'_, B>, BufferError> {
+        let raw = self.raw.map_write(
+            range.start * size_of::<B>(),
+            range.len() * size_of::<B>(),
+            settings,
+        )?;
+
+        BufferMapMut::from_raw(raw)
+    }
+
+    /// Maps the blocks in `range` for reading.
+    ///
+    /// This blocks until the GPU has finished writing to the buffer, e.g. with
+    /// transform feedback. Buffer mapping is not available in WebGL, where this
+    /// returns an error.
+    pub fn map(&self, range: Range<usize>) -> Result<BufferMap<'_, B>, BufferError> {
+        let raw = self
+            .raw
+            .map_read(range.start * size_of::<B>(), range.len() * size_of::<B>())?;
+
+        BufferMap::from_raw(raw)
     }
 
     pub fn as_binding(&self) -> VertexBufferBinding<B::Sl> {
-        VertexBufferBinding {
-            raw: self.raw.clone(),
-            input_rate: VertexInputRate::Vertex,
-            _phantom: PhantomData,
-        }
+        VertexBufferBinding::from_raw(self.raw.clone(), None)
     }
 
     pub fn as_vertex_spec(&self, mode: PrimitiveMode) -> VertexSpec<B::Sl> {
@@ -66,26 +107,82 @@ where
 }
 
 impl<B: Block<Sl>> VertexBufferBinding<B> {
+    pub(super) fn from_raw(raw: Rc<raw::Buffer>, range: Option<Range<usize>>) -> Self {
+        Self {
+            raw,
+            range,
+            input_rate: VertexInputRate::Vertex,
+            instance_divisor: 1,
+            _phantom: PhantomData,
+        }
+    }
+
     pub(crate) fn raw(&self) -> &Rc<raw::Buffer> {
         &self.raw
     }
 
-    pub fn len(&self) -> usize {
-        assert_eq!(self.raw.len() % size_of::<B::Gl>(), 0);
+    /// Returns the range of blocks in the buffer that are bound.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone().unwrap_or_else(|| {
+            assert_eq!(self.raw.len() % size_of::<B::Gl>(), 0);
+
+            0..self.raw.len() / size_of::<B::Gl>()
+        })
+    }
 
-        self.raw.len() / size_of::<B::Gl>()
+    pub fn len(&self) -> usize {
+        self.range().len()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    /// Restricts the binding to the blocks in `range`.
+    ///
+    /// The first block in `range` is used as the block with index zero. This
+    /// allows packing the vertices of multiple meshes into one buffer, with
+    /// each mesh's elements being relative to the start of its range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is not within the currently bound range.
+    pub fn with_range(mut self, range: Range<usize>) -> Self {
+        let bound = self.range();
+
+        assert!(
+            range.start <= range.end && range.end <= bound.len(),
+            "vertex buffer range out of bounds"
+        );
+
+        self.range = Some(bound.start + range.start..bound.start + range.end);
+        self
+    }
+
     pub fn with_instancing(mut self) -> Self {
         self.input_rate = VertexInputRate::Instance;
         self
     }
 
+    /// Enables instancing, with each block being used for `divisor`
+    /// consecutive instances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    pub fn with_instance_divisor(mut self, divisor: usize) -> Self {
+        assert!(divisor > 0, "instance divisor must be positive");
+
+        self.input_rate = VertexInputRate::Instance;
+        self.instance_divisor = divisor;
+        self
+    }
+
     pub(crate) fn input_rate(&self) -> VertexInputRate {
         self.input_rate
     }
+
+    pub(crate) fn instance_divisor(&self) -> usize {
+        self.instance_divisor
+    }
 }
@@ -0,0 +1,397 @@
+use bytemuck::{Pod, Zeroable};
+use crevice::std140::AsStd140;
+use sealed::sealed;
+
+use crate::{
+    sl::{
+        self,
+        program_def::{VertexAttributeDef, VertexAttributeFormat},
+    },
+    Block, Gl, ToSl,
+};
+
+use super::{Vec2, Vec3, Vec4, F16};
+
+/// A compact vertex attribute type.
+///
+/// Vertex formats store attribute data in fewer bytes than the corresponding
+/// shader type. They are converted to floating-point values when the vertex
+/// shader reads them:
+///
+/// - `Normalized<[T; N]>` with `T` being `u8` or `u16` is normalized to `[0,
+///   1]`, and with `T` being `i8` or `i16` is normalized to `[-1, 1]`.
+/// - [`F16`] and `F16VecN` are 16-bit floating-point.
+/// - `Normalized<Int2_10_10_10Rev>` and `Normalized<UInt2_10_10_10Rev>` store
+///   four components packed into a `u32`.
+///
+/// Vertex formats can be used in [`Block`] declarations through
+/// [`BlockDom::Packed`](crate::BlockDom::Packed), or through the
+/// `#[vertex(format = ...)]` field attribute of
+/// [`vertex_formats`](macro@crate::vertex_formats).
+#[sealed]
+pub trait VertexFormat: Block<Gl, Gl = Self> + ToSl<Output = <Self as Block<Gl>>::Sl> {
+    const FORMAT: VertexAttributeFormat;
+}
+
+/// Vertex attribute data with normalized integer components.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Normalized<T>(pub T);
+
+unsafe impl<T: Zeroable> Zeroable for Normalized<T> {}
+unsafe impl<T: Pod> Pod for Normalized<T> {}
+
+/// Two 16-bit floating-point numbers.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct F16Vec2(pub [F16; 2]);
+
+/// Three 16-bit floating-point numbers.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct F16Vec3(pub [F16; 3]);
+
+/// Four 16-bit floating-point numbers.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct F16Vec4(pub [F16; 4]);
+
+/// Four signed components packed as `INT_2_10_10_10_REV`.
+///
+/// The first component is stored in the lowest 10 bits, the fourth component
+/// in the highest 2 bits.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Int2_10_10_10Rev(pub u32);
+
+/// Four unsigned components packed as `UNSIGNED_INT_2_10_10_10_REV`.
+///
+/// The first component is stored in the lowest 10 bits, the fourth component
+/// in the highest 2 bits.
+#[derive(Clone, Copy, Default, Zeroable, Pod, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct UInt2_10_10_10Rev(pub u32);
+
+/// Conversion of vertex formats to the floating-point values seen by shaders.
+///
+/// This is used for `ToSl` and `AsStd140`, so that vertex formats can also be
+/// used in uniform blocks.
+trait ToFloats: Sized {
+    type Floats: AsStd140 + ToSl + Copy;
+
+    fn to_floats(self) -> Self::Floats;
+
+    fn from_floats(floats: Self::Floats) -> Self;
+}
+
+trait NormalizedComponent: Copy {
+    fn to_f32(self) -> f32;
+
+    fn from_f32(value: f32) -> Self;
+}
+
+macro_rules! impl_normalized_component {
+    ($ty:ty, $min:literal) => {
+        impl NormalizedComponent for $ty {
+            fn to_f32(self) -> f32 {
+                // OpenGL ES 3.0.6: 2.1.6.1
+                (self as f32 / <$ty>::MAX as f32).max($min)
+            }
+
+            fn from_f32(value: f32) -> Self {
+                (value.clamp($min, 1.0) * <$ty>::MAX as f32).round() as $ty
+            }
+        }
+    };
+}
+
+impl_normalized_component!(u8, 0.0);
+impl_normalized_component!(i8, -1.0);
+impl_normalized_component!(u16, 0.0);
+impl_normalized_component!(i16, -1.0);
+
+impl<T: NormalizedComponent> ToFloats for Normalized<[T; 1]> {
+    type Floats = f32;
+
+    fn to_floats(self) -> f32 {
+        self.0[0].to_f32()
+    }
+
+    fn from_floats(floats: f32) -> Self {
+        Self([T::from_f32(floats)])
+    }
+}
+
+impl ToFloats for F16 {
+    type Floats = f32;
+
+    fn to_floats(self) -> f32 {
+        self.to_f32()
+    }
+
+    fn from_floats(floats: f32) -> Self {
+        F16::from_f32(floats)
+    }
+}
+
+macro_rules! impl_floats_vec {
+    ($n:literal, $vec:ident, $half:ident) => {
+        impl<T: NormalizedComponent> ToFloats for Normalized<[T; $n]> {
+            type Floats = $vec;
+
+            fn to_floats(self) -> $vec {
+                self.0.map(T::to_f32).into()
+            }
+
+            fn from_floats(floats: $vec) -> Self {
+                let floats: [f32; $n] = floats.into();
+
+                Self(floats.map(T::from_f32))
+            }
+        }
+
+        impl ToFloats for $half {
+            type Floats = $vec;
+
+            fn to_floats(self) -> $vec {
+                self.0.map(F16::to_f32).into()
+            }
+
+            fn from_floats(floats: $vec) -> Self {
+                let floats: [f32; $n] = floats.into();
+
+                Self(floats.map(F16::from_f32))
+            }
+        }
+    };
+}
+
+impl_floats_vec!(2, Vec2, F16Vec2);
+impl_floats_vec!(3, Vec3, F16Vec3);
+impl_floats_vec!(4, Vec4, F16Vec4);
+
+impl ToFloats for Normalized<Int2_10_10_10Rev> {
+    type Floats = Vec4;
+
+    fn to_floats(self) -> Vec4 {
+        let bits = self.0 .0;
+
+        // Sign-extend each component by shifting it to the top of an `i32`.
+        let component = |shift: u32, width: u32| {
+            let value = ((bits << (32 - shift - width)) as i32) >> (32 - width);
+            let max = (1 << (width - 1)) - 1;
+
+            (value as f32 / max as f32).max(-1.0)
+        };
+
+        [
+            component(0, 10),
+            component(10, 10),
+            component(20, 10),
+            component(30, 2),
+        ]
+        .into()
+    }
+
+    fn from_floats(floats: Vec4) -> Self {
+        let floats: [f32; 4] = floats.into();
+
+        let component = |value: f32, shift: u32, width: u32| {
+            let max = (1 << (width - 1)) - 1;
+            let value = (value.clamp(-1.0, 1.0) * max as f32).round() as i32;
+
+            ((value as u32) & ((1 << width) - 1)) << shift
+        };
+
+        Self(Int2_10_10_10Rev(
+            component(floats[0], 0, 10)
+                | component(floats[1], 10, 10)
+                | component(floats[2], 20, 10)
+                | component(floats[3], 30, 2),
+        ))
+    }
+}
+
+impl ToFloats for Normalized<UInt2_10_10_10Rev> {
+    type Floats = Vec4;
+
+    fn to_floats(self) -> Vec4 {
+        let bits = self.0 .0;
+
+        let component = |shift: u32, width: u32| {
+            let max = (1 << width) - 1;
+
+            ((bits >> shift) & max) as f32 / max as f32
+        };
+
+        [
+            component(0, 10),
+            component(10, 10),
+            component(20, 10),
+            component(30, 2),
+        ]
+        .into()
+    }
+
+    fn from_floats(floats: Vec4) -> Self {
+        let floats: [f32; 4] = floats.into();
+
+        let component = |value: f32, shift: u32, width: u32| {
+            let max = (1 << width) - 1;
+
+            ((value.clamp(0.0, 1.0) * max as f32).round() as u32) << shift
+        };
+
+        Self(UInt2_10_10_10Rev(
+            component(floats[0], 0, 10)
+                | component(floats[1], 10, 10)
+                | component(floats[2], 20, 10)
+                | component(floats[3], 30, 2),
+        ))
+    }
+}
+
+macro_rules! impl_vertex_format {
+    ($ty:ty, $floats:ty, $sl:ident, $format:ident) => {
+        #[sealed]
+        impl VertexFormat for $ty {
+            const FORMAT: VertexAttributeFormat = VertexAttributeFormat::$format;
+        }
+
+        impl ToSl for $ty {
+            type Output = sl::$sl;
+
+            fn to_sl(self) -> Self::Output {
+                self.to_floats().to_sl()
+            }
+        }
+
+        impl AsStd140 for $ty {
+            type Output = <$floats as AsStd140>::Output;
+
+            fn as_std140(&self) -> Self::Output {
+                self.to_floats().as_std140()
+            }
+
+            fn from_std140(value: Self::Output) -> Self {
+                Self::from_floats(AsStd140::from_std140(value))
+            }
+        }
+
+        unsafe impl Block<Gl> for $ty {
+            type Gl = $ty;
+            type Sl = sl::$sl;
+
+            fn vertex_attribute_defs(path: &str) -> Vec<VertexAttributeDef> {
+                vec![VertexAttributeDef {
+                    name: path.to_string(),
+                    ty: <sl::$sl as sl::Object>::ty().built_in_type().unwrap(),
+                    offset: 0,
+                    format: Self::FORMAT,
+                }]
+            }
+        }
+    };
+}
+
+impl_vertex_format!(Normalized<[u8; 1]>, f32, F32, U8Norm);
+impl_vertex_format!(Normalized<[u8; 2]>, Vec2, Vec2, U8Norm);
+impl_vertex_format!(Normalized<[u8; 3]>, Vec3, Vec3, U8Norm);
+impl_vertex_format!(Normalized<[u8; 4]>, Vec4, Vec4, U8Norm);
+impl_vertex_format!(Normalized<[i8; 1]>, f32, F32, I8Norm);
+impl_vertex_format!(Normalized<[i8; 2]>, Vec2, Vec2, I8Norm);
+impl_vertex_format!(Normalized<[i8; 3]>, Vec3, Vec3, I8Norm);
+impl_vertex_format!(Normalized<[i8; 4]>, Vec4, Vec4, I8Norm);
+impl_vertex_format!(Normalized<[u16; 1]>, f32, F32, U16Norm);
+impl_vertex_format!(Normalized<[u16; 2]>, Vec2, Vec2, U16Norm);
+impl_vertex_format!(Normalized<[u16; 3]>, Vec3, Vec3, U16Norm);
+impl_vertex_format!(Normalized<[u16; 4]>, Vec4, Vec4, U16Norm);
+impl_vertex_format!(Normalized<[i16; 1]>, f32, F32, I16Norm);
+impl_vertex_format!(Normalized<[i16; 2]>, Vec2, Vec2, I16Norm);
+impl_vertex_format!(Normalized<[i16; 3]>, Vec3, Vec3, I16Norm);
+impl_vertex_format!(Normalized<[i16; 4]>, Vec4, Vec4, I16Norm);
+impl_vertex_format!(F16, f32, F32, F16);
+impl_vertex_format!(F16Vec2, Vec2, Vec2, F16);
+impl_vertex_format!(F16Vec3, Vec3, Vec3, F16);
+impl_vertex_format!(F16Vec4, Vec4, Vec4, F16);
+impl_vertex_format!(
+    Normalized<Int2_10_10_10Rev>,
+    Vec4,
+    Vec4,
+    Int2_10_10_10RevNorm
+);
+impl_vertex_format!(
+    Normalized<UInt2_10_10_10Rev>,
+    Vec4,
+    Vec4,
+    UInt2_10_10_10RevNorm
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        F16Vec2, Int2_10_10_10Rev, Normalized, ToFloats, UInt2_10_10_10Rev, Vec2, Vec4, F16,
+    };
+
+    fn floats4(value: impl ToFloats<Floats = Vec4>) -> [f32; 4] {
+        value.to_floats().into()
+    }
+
+    #[test]
+    fn unsigned_normalized_components() {
+        assert_eq!(
+            floats4(Normalized([0u8, 255, 51, 255])),
+            [0.0, 1.0, 0.2, 1.0]
+        );
+        assert_eq!(
+            Normalized::<[u8; 4]>::from_floats([0.0, 1.0, 0.2, 2.0].into()),
+            Normalized([0, 255, 51, 255])
+        );
+        assert_eq!(Normalized::<[u16; 1]>::from_floats(-1.0), Normalized([0]));
+    }
+
+    #[test]
+    fn signed_normalized_components() {
+        // Both the minimum and the value above it map to -1.
+        let floats: [f32; 2] = Normalized([i8::MIN, i8::MIN + 1]).to_floats().into();
+        assert_eq!(floats, [-1.0, -1.0]);
+
+        let floats: [f32; 2] = Normalized([i16::MAX, 0]).to_floats().into();
+        assert_eq!(floats, [1.0, 0.0]);
+
+        assert_eq!(
+            Normalized::<[i16; 2]>::from_floats(Vec2::from([-2.0, 1.0])),
+            Normalized([-i16::MAX, i16::MAX])
+        );
+    }
+
+    #[test]
+    fn half_float_components() {
+        assert_eq!(F16::from_floats(1.5).to_floats(), 1.5);
+
+        let floats: [f32; 2] = F16Vec2([F16::from_f32(-2.0), F16::from_f32(0.25)])
+            .to_floats()
+            .into();
+        assert_eq!(floats, [-2.0, 0.25]);
+    }
+
+    #[test]
+    fn signed_2_10_10_10_packing() {
+        let packed = Normalized::<Int2_10_10_10Rev>::from_floats([1.0, -1.0, 0.0, 1.0].into());
+
+        assert_eq!(packed.0 .0, 0x1FF | (0x201 << 10) | (0b01 << 30));
+        assert_eq!(floats4(packed), [1.0, -1.0, 0.0, 1.0]);
+
+        // The most negative values of each component are clamped to -1.
+        let min = Normalized(Int2_10_10_10Rev(0x200 | (0b10 << 30)));
+        assert_eq!(floats4(min), [-1.0, 0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn unsigned_2_10_10_10_packing() {
+        let packed = Normalized::<UInt2_10_10_10Rev>::from_floats([1.0, 0.0, 0.5, 1.0].into());
+
+        assert_eq!(packed.0 .0, 0x3FF | (512 << 20) | (0b11 << 30));
+        assert_eq!(floats4(packed), [1.0, 0.0, 512.0 / 1023.0, 1.0]);
+    }
+}
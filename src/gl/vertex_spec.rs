@@ -1,13 +1,11 @@
 use std::{mem::size_of, ops::Range};
 
 use crate::{
-    gl::VertexBufferBinding,
-    interface::VertexVisitor,
-    sl::program_def::{VertexBlockDef, VertexInputRate},
-    Block, Gl, Sl, VsInterface,
+    gl::VertexBufferBinding, interface::VertexVisitor, sl::program_def::VertexInputRate, Block, Gl,
+    Sl, VsInterface,
 };
 
-use super::{raw, ElementBufferBinding, PrimitiveMode};
+use super::{raw, DrawError, ElementBufferBinding, PrimitiveMode};
 
 #[derive(Clone)]
 pub struct VertexSpec<V: VsInterface<Sl>> {
@@ -16,6 +14,9 @@ pub struct VertexSpec<V: VsInterface<Sl>> {
     vertex_range: Option<Range<usize>>,
     element_data: Option<ElementBufferBinding>,
     num_instances: Option<usize>,
+
+    /// An inconsistency in the vertex data, which is reported when drawing.
+    error: Option<DrawError>,
 }
 
 impl VertexSpec<()> {
@@ -26,6 +27,7 @@ impl VertexSpec<()> {
             vertex_range: None,
             element_data: None,
             num_instances: None,
+            error: None,
         }
     }
 
@@ -36,6 +38,8 @@ impl VertexSpec<()> {
         let Counts {
             num_vertices,
             num_instances,
+            error,
+            ..
         } = get_counts(&vertex_data);
 
         VertexSpec {
@@ -44,7 +48,7 @@ impl VertexSpec<()> {
             vertex_range: if self.vertex_range.is_some() {
                 self.vertex_range
             } else {
-                num_vertices.map(|num_vertices| 0..num_vertices)
+                num_vertices.map(|(_, num_vertices)| 0..num_vertices)
             },
             element_data: self.element_data,
             num_instances: if self.num_instances.is_some() {
@@ -52,6 +56,7 @@ impl VertexSpec<()> {
             } else {
                 num_instances
             },
+            error,
         }
     }
 }
@@ -74,81 +79,183 @@ impl<V: VsInterface<Sl>> VertexSpec<V> {
         self
     }
 
-    pub(super) fn raw(&self) -> raw::VertexSpec {
-        raw::VertexSpec {
-            vertex_data: raw_vertices(&self.vertex_data),
+    /// Returns the paths of the vertex buffer bindings, which are used for
+    /// naming them in errors.
+    pub(super) fn vertex_paths(&self) -> Vec<String> {
+        struct Visitor(Vec<String>);
+
+        impl<'a> VertexVisitor<'a, Gl> for Visitor {
+            fn accept<B: Block<Sl>>(
+                &mut self,
+                path: &str,
+                _: Option<usize>,
+                _: &VertexBufferBinding<B>,
+            ) {
+                self.0.push(path.to_string());
+            }
+        }
+
+        let mut visitor = Visitor(Vec::new());
+
+        // TODO: Remove hardcoded path names.
+        self.vertex_data.visit("vertex_input", &mut visitor);
+
+        visitor.0
+    }
+
+    /// Returns the raw vertex specification, filling `vertex_data` with the
+    /// vertex buffer bindings.
+    ///
+    /// This does not allocate if `vertex_data` has enough capacity.
+    pub(super) fn raw(
+        &self,
+        mut vertex_data: Vec<raw::VertexBufferBinding>,
+    ) -> Result<raw::VertexSpec, DrawError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        vertex_data.clear();
+        raw_vertices(&self.vertex_data, &mut vertex_data);
+
+        Ok(raw::VertexSpec {
+            vertex_data,
             element_data: self
                 .element_data
                 .as_ref()
                 .map(|elements| (elements.raw().clone(), elements.ty())),
+            element_indices: self
+                .element_data
+                .as_ref()
+                .map(|elements| elements.indices().clone()),
             mode: self.mode,
             index_range: self.element_data.as_ref().map_or_else(
                 || self.vertex_range.clone().unwrap_or(0..0),
                 |binding| binding.range(),
             ),
             num_instances: self.num_instances.unwrap_or(1),
+        })
+    }
+}
+
+/// Replaces the raw layer's name of the vertex buffer binding that `error`
+/// refers to with its path in the vertex data.
+pub(super) fn with_vertex_path(mut error: DrawError, paths: &[String]) -> DrawError {
+    if let Some((binding, path)) = error.vertex_binding_mut() {
+        if let Some(binding_path) = paths.get(binding) {
+            path.clone_from(binding_path);
         }
     }
+
+    error
 }
 
-fn raw_vertices<V: VsInterface<Gl>>(vertices: &V) -> Vec<raw::VertexBufferBinding> {
-    // TODO: Reduce per-draw-call allocations.
-    struct Visitor(Vec<raw::VertexBufferBinding>);
+fn raw_vertices<V: VsInterface<Gl>>(vertices: &V, raw: &mut Vec<raw::VertexBufferBinding>) {
+    struct Visitor<'r>(&'r mut Vec<raw::VertexBufferBinding>);
 
-    impl<'a> VertexVisitor<'a, Gl> for Visitor {
-        fn accept<B: Block<Sl>>(&mut self, path: &str, binding: &'a VertexBufferBinding<B>) {
+    impl<'a, 'r> VertexVisitor<'a, Gl> for Visitor<'r> {
+        fn accept<B: Block<Sl>>(
+            &mut self,
+            _: &str,
+            _: Option<usize>,
+            binding: &'a VertexBufferBinding<B>,
+        ) {
+            // The block's attributes are given by the program's vertex block
+            // definitions, which are in the same order.
             self.0.push(raw::VertexBufferBinding {
                 buffer: binding.raw().clone(),
-                block_def: VertexBlockDef {
-                    attributes: B::vertex_attribute_defs(path),
-                },
                 input_rate: binding.input_rate(),
+                instance_divisor: binding.instance_divisor(),
                 stride: size_of::<B::Gl>(),
+                range: {
+                    let range = binding.range();
+
+                    range.start * size_of::<B::Gl>()..range.end * size_of::<B::Gl>()
+                },
             });
         }
+
+        fn needs_paths(&self) -> bool {
+            false
+        }
     }
 
     // TODO: Remove hardcoded path names.
-    let mut visitor = Visitor(Vec::new());
-    vertices.visit("vertex_input", &mut visitor);
-
-    visitor.0
+    vertices.visit("vertex_input", &mut Visitor(raw));
 }
 
+/// The number of vertices in vertex data, together with the path of the first
+/// buffer that determined it, and the number of instances that all instance
+/// buffers have data for.
+///
+/// Paths are only collected if `needs_paths` is set.
 #[derive(Clone)]
 struct Counts {
-    num_vertices: Option<usize>,
+    num_vertices: Option<(String, usize)>,
     num_instances: Option<usize>,
+    error: Option<DrawError>,
+    needs_paths: bool,
 }
 
 impl<'a> VertexVisitor<'a, Gl> for Counts {
-    fn accept<B: Block<Sl>>(&mut self, _: &str, binding: &'a VertexBufferBinding<B>) {
+    fn accept<B: Block<Sl>>(
+        &mut self,
+        path: &str,
+        _: Option<usize>,
+        binding: &'a VertexBufferBinding<B>,
+    ) {
         let len = binding.len();
 
         match binding.input_rate() {
-            VertexInputRate::Vertex => {
-                if let Some(num_vertices) = self.num_vertices {
-                    assert!(num_vertices == len);
+            VertexInputRate::Vertex => match &self.num_vertices {
+                Some((other_path, other_len)) => {
+                    if *other_len != len && self.error.is_none() {
+                        self.error = Some(DrawError::VertexBufferLengthMismatch {
+                            path: path.to_string(),
+                            len,
+                            other_path: other_path.clone(),
+                            other_len: *other_len,
+                        });
+                    }
                 }
-                self.num_vertices = Some(len);
-            }
+                None => self.num_vertices = Some((path.to_string(), len)),
+            },
             VertexInputRate::Instance => {
-                if let Some(num_instances) = self.num_instances {
-                    assert!(num_instances == len);
-                }
-                self.num_instances = Some(len);
+                // Instance buffers only need to be large enough for the drawn
+                // instances, so the smallest one determines the default.
+                let len = len * binding.instance_divisor();
+
+                self.num_instances = Some(self.num_instances.map_or(len, |other| other.min(len)));
             }
         }
     }
+
+    fn needs_paths(&self) -> bool {
+        self.needs_paths
+    }
 }
 
 fn get_counts<V: VsInterface<Gl>>(vertices: &V) -> Counts {
-    // TODO: Remove hardcoded path names.
-    let mut counts = Counts {
-        num_vertices: None,
-        num_instances: None,
+    let visit = |needs_paths| {
+        // TODO: Remove hardcoded path names.
+        let mut counts = Counts {
+            num_vertices: None,
+            num_instances: None,
+            error: None,
+            needs_paths,
+        };
+        vertices.visit("vertex_input", &mut counts);
+
+        counts
     };
-    vertices.visit("vertex_input", &mut counts);
 
-    counts
+    // Paths are only needed for reporting errors, so they are collected in a
+    // second pass if there is an error.
+    let counts = visit(false);
+
+    if counts.error.is_some() {
+        visit(true)
+    } else {
+        counts
+    }
 }
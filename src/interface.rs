@@ -6,6 +6,7 @@ mod vs_interface;
 pub use block::{Block, BlockDom};
 pub use fs_interface::{FragmentVisitor, FsInterface, FsInterfaceDom};
 pub use uniform_interface::{
-    UniformInterface, UniformInterfaceDom, UniformNonUnit, UniformUnion, UniformVisitor,
+    BlockField, SamplerField, UniformInterface, UniformInterfaceDom, UniformNonUnit, UniformUnion,
+    UniformVisitor,
 };
 pub use vs_interface::{VertexField, VertexVisitor, VsInterface, VsInterfaceDom};
@@ -4,7 +4,10 @@ use sealed::sealed;
 
 use crate::{
     gl,
-    sl::{self, program_def::VertexAttributeDef},
+    sl::{
+        self,
+        program_def::{VertexAttributeDef, VertexAttributeFormat},
+    },
     Gl, Sl, ToSl,
 };
 
@@ -107,6 +110,16 @@ pub trait BlockDom: Copy {
     /// Has [`gl::Mat4`](crate::gl::Mat4) as its physical view and [`sl::Mat4`]
     /// as its logical view.
     type Mat4: Block<Self> + ToSl<Output = sl::Mat4>;
+
+    /// A compact vertex attribute.
+    ///
+    /// Has `T` as its physical view and the floating-point type that `T` is
+    /// converted to as its logical view. See
+    /// [`gl::VertexFormat`](crate::gl::VertexFormat) for the available
+    /// formats. Fields can also be declared with their logical type and a
+    /// `#[vertex(format = T)]` attribute, see
+    /// [`vertex_formats`](macro@crate::vertex_formats).
+    type Packed<T: gl::VertexFormat>: Block<Self> + ToSl<Output = <T as Block<Gl>>::Sl>;
 }
 
 #[sealed]
@@ -126,6 +139,7 @@ impl BlockDom for Gl {
     type Mat2 = gl::Mat2;
     type Mat3 = gl::Mat3;
     type Mat4 = gl::Mat4;
+    type Packed<T: gl::VertexFormat> = T;
 }
 
 #[sealed]
@@ -145,6 +159,7 @@ impl BlockDom for Sl {
     type Mat2 = sl::Mat2;
     type Mat3 = sl::Mat3;
     type Mat4 = sl::Mat4;
+    type Packed<T: gl::VertexFormat> = <T as Block<Gl>>::Sl;
 }
 
 /// Plain-old vertex or uniform block data.
@@ -250,6 +265,7 @@ macro_rules! impl_block {
                     name: path.to_string(),
                     ty: <Self as sl::Object>::ty().built_in_type().unwrap(),
                     offset: 0,
+                    format: VertexAttributeFormat::Native,
                 }]
             }
         }
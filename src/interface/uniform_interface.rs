@@ -2,7 +2,11 @@ use std::array;
 
 use sealed::sealed;
 
-use crate::{gl, internal::join_ident_path, sl, Gl, Sl};
+use crate::{
+    gl,
+    internal::{join_ident_path, join_ident_path_if},
+    sl, Gl, Sl,
+};
 
 use super::Block;
 
@@ -12,13 +16,29 @@ use super::Block;
 #[sealed]
 pub trait UniformInterfaceDom: Copy {
     /// A block field.
-    type Block<B: Block<Sl, Sl = B>>: UniformInterface<Self>;
+    ///
+    /// The uniform block binding can be specified with the field attribute
+    /// `#[uniform(binding = N)]`.
+    type Block<B: Block<Sl, Sl = B>>: UniformInterface<Self> + BlockField<Self>;
 
     /// A two-dimensional color sampler field.
-    type ColorSampler2d<S: sl::ColorSample>: UniformInterface<Self>;
+    ///
+    /// The texture unit can be specified with the field attribute
+    /// `#[uniform(unit = N)]`.
+    type ColorSampler2d<S: sl::ColorSample>: UniformInterface<Self> + SamplerField<Self>;
 
     /// A two-dimensional comparison sampler field.
-    type ComparisonSampler2d: UniformInterface<Self>;
+    ///
+    /// The texture unit can be specified with the field attribute
+    /// `#[uniform(unit = N)]`.
+    type ComparisonSampler2d: UniformInterface<Self> + SamplerField<Self>;
+
+    /// A block field that is provided globally by the context.
+    ///
+    /// In shaders, this is accessed like a regular block field. Draw calls do
+    /// not need to provide a binding for it, since the block is bound once with
+    /// [`set_global_uniform_block`](crate::gl::Context::set_global_uniform_block).
+    type GlobalBlock<B: Block<Sl, Sl = B>>: UniformInterface<Self>;
 
     /// A nested uniform interface field.
     type UniformInterface<U: UniformInterface<Sl>>: UniformInterface<Self>;
@@ -32,6 +52,7 @@ impl UniformInterfaceDom for Gl {
     type Block<B: Block<Sl, Sl = B>> = gl::UniformBufferBinding<B>;
     type ColorSampler2d<S: sl::ColorSample> = gl::ColorSampler2d<S>;
     type ComparisonSampler2d = gl::ComparisonSampler2d;
+    type GlobalBlock<B: Block<Sl, Sl = B>> = gl::GlobalUniformBlock<B>;
     type UniformInterface<R: UniformInterface<Sl>> = R::Gl;
     type Array<U: UniformInterface<Sl>, const N: usize> = [U::Gl; N];
 }
@@ -41,6 +62,7 @@ impl UniformInterfaceDom for Sl {
     type Block<B: Block<Sl, Sl = B>> = B;
     type ColorSampler2d<S: sl::ColorSample> = sl::ColorSampler2d<S>;
     type ComparisonSampler2d = sl::ComparisonSampler2d;
+    type GlobalBlock<B: Block<Sl, Sl = B>> = B;
     type UniformInterface<R: UniformInterface<Sl>> = R;
     type Array<U: UniformInterface<Sl>, const N: usize> = [U; N];
 }
@@ -106,7 +128,16 @@ unsafe impl<B: Block<Sl, Sl = B>> UniformInterface<Gl> for gl::UniformBufferBind
     type Sl = B;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Gl>) {
-        visitor.accept_block::<B::Sl>(path, self);
+        visitor.accept_block::<B::Sl>(path, None, self);
+    }
+}
+
+unsafe impl<B: Block<Sl, Sl = B>> UniformInterface<Gl> for gl::GlobalUniformBlock<B> {
+    type Gl = gl::GlobalUniformBlock<B>;
+    type Sl = B;
+
+    fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Gl>) {
+        visitor.accept_global_block::<B>(path);
     }
 }
 
@@ -115,7 +146,7 @@ unsafe impl<B: Block<Sl, Sl = B>> UniformInterface<Sl> for B {
     type Sl = B;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Sl>) {
-        visitor.accept_block(path, self)
+        visitor.accept_block(path, None, self)
     }
 
     fn shader_input(path: &str) -> Self {
@@ -128,7 +159,7 @@ unsafe impl<S: sl::ColorSample> UniformInterface<Gl> for gl::ColorSampler2d<S> {
     type Sl = sl::ColorSampler2d<S>;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Gl>) {
-        visitor.accept_color_sampler_2d(path, self);
+        visitor.accept_color_sampler_2d(path, None, self);
     }
 }
 
@@ -137,7 +168,7 @@ unsafe impl<S: sl::ColorSample> UniformInterface<Sl> for sl::ColorSampler2d<S> {
     type Sl = Self;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Sl>) {
-        visitor.accept_color_sampler_2d(path, self)
+        visitor.accept_color_sampler_2d(path, None, self)
     }
 
     fn shader_input(path: &str) -> Self {
@@ -150,7 +181,7 @@ unsafe impl UniformInterface<Gl> for gl::ComparisonSampler2d {
     type Sl = sl::ComparisonSampler2d;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Gl>) {
-        visitor.accept_comparison_sampler_2d(path, self);
+        visitor.accept_comparison_sampler_2d(path, None, self);
     }
 }
 
@@ -159,7 +190,7 @@ unsafe impl UniformInterface<Sl> for sl::ComparisonSampler2d {
     type Sl = sl::ComparisonSampler2d;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, Sl>) {
-        visitor.accept_comparison_sampler_2d(path, self)
+        visitor.accept_comparison_sampler_2d(path, None, self)
     }
 
     fn shader_input(path: &str) -> Self {
@@ -177,8 +208,14 @@ where
     type Sl = (U::Sl, V::Sl);
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, D>) {
-        self.0.visit(&join_ident_path(path, "a"), visitor);
-        self.1.visit(&join_ident_path(path, "b"), visitor);
+        self.0.visit(
+            &join_ident_path_if(visitor.needs_paths(), path, "a"),
+            visitor,
+        );
+        self.1.visit(
+            &join_ident_path_if(visitor.needs_paths(), path, "b"),
+            visitor,
+        );
     }
 
     fn shader_input(path: &str) -> Self {
@@ -202,7 +239,13 @@ where
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl UniformVisitor<'a, D>) {
         for i in 0..N {
-            self[i].visit(&join_ident_path(path, &i.to_string()), visitor);
+            let path = if visitor.needs_paths() {
+                join_ident_path(path, &i.to_string())
+            } else {
+                String::new()
+            };
+
+            self[i].visit(&path, visitor);
         }
     }
 
@@ -211,15 +254,134 @@ where
     }
 }
 
+/// Uniform block fields, which can have a user-specified binding.
+#[sealed]
+#[doc(hidden)]
+pub trait BlockField<D: UniformInterfaceDom> {
+    fn visit_with_binding<'a>(
+        &'a self,
+        path: &str,
+        binding: usize,
+        visitor: &mut impl UniformVisitor<'a, D>,
+    );
+}
+
+#[sealed]
+impl<B: Block<Sl, Sl = B>> BlockField<Gl> for gl::UniformBufferBinding<B> {
+    fn visit_with_binding<'a>(
+        &'a self,
+        path: &str,
+        binding: usize,
+        visitor: &mut impl UniformVisitor<'a, Gl>,
+    ) {
+        visitor.accept_block::<B::Sl>(path, Some(binding), self);
+    }
+}
+
+#[sealed]
+impl<B: Block<Sl, Sl = B>> BlockField<Sl> for B {
+    fn visit_with_binding<'a>(
+        &'a self,
+        path: &str,
+        binding: usize,
+        visitor: &mut impl UniformVisitor<'a, Sl>,
+    ) {
+        visitor.accept_block(path, Some(binding), self);
+    }
+}
+
+/// Sampler fields, which can have a user-specified texture unit.
+#[sealed]
+#[doc(hidden)]
+pub trait SamplerField<D: UniformInterfaceDom> {
+    fn visit_with_unit<'a>(
+        &'a self,
+        path: &str,
+        unit: usize,
+        visitor: &mut impl UniformVisitor<'a, D>,
+    );
+}
+
+#[sealed]
+impl<S: sl::ColorSample> SamplerField<Gl> for gl::ColorSampler2d<S> {
+    fn visit_with_unit<'a>(
+        &'a self,
+        path: &str,
+        unit: usize,
+        visitor: &mut impl UniformVisitor<'a, Gl>,
+    ) {
+        visitor.accept_color_sampler_2d(path, Some(unit), self);
+    }
+}
+
+#[sealed]
+impl<S: sl::ColorSample> SamplerField<Sl> for sl::ColorSampler2d<S> {
+    fn visit_with_unit<'a>(
+        &'a self,
+        path: &str,
+        unit: usize,
+        visitor: &mut impl UniformVisitor<'a, Sl>,
+    ) {
+        visitor.accept_color_sampler_2d(path, Some(unit), self);
+    }
+}
+
+#[sealed]
+impl SamplerField<Gl> for gl::ComparisonSampler2d {
+    fn visit_with_unit<'a>(
+        &'a self,
+        path: &str,
+        unit: usize,
+        visitor: &mut impl UniformVisitor<'a, Gl>,
+    ) {
+        visitor.accept_comparison_sampler_2d(path, Some(unit), self);
+    }
+}
+
+#[sealed]
+impl SamplerField<Sl> for sl::ComparisonSampler2d {
+    fn visit_with_unit<'a>(
+        &'a self,
+        path: &str,
+        unit: usize,
+        visitor: &mut impl UniformVisitor<'a, Sl>,
+    ) {
+        visitor.accept_comparison_sampler_2d(path, Some(unit), self);
+    }
+}
+
 #[doc(hidden)]
 pub trait UniformVisitor<'a, D: UniformInterfaceDom> {
-    fn accept_block<B: Block<Sl, Sl = B>>(&mut self, path: &str, block: &'a D::Block<B>);
+    fn accept_block<B: Block<Sl, Sl = B>>(
+        &mut self,
+        path: &str,
+        binding: Option<usize>,
+        block: &'a D::Block<B>,
+    );
     fn accept_color_sampler_2d<S: sl::ColorSample>(
         &mut self,
         path: &str,
+        unit: Option<usize>,
         sampler: &'a D::ColorSampler2d<S>,
     );
-    fn accept_comparison_sampler_2d(&mut self, path: &str, sampler: &'a D::ComparisonSampler2d);
+    fn accept_comparison_sampler_2d(
+        &mut self,
+        path: &str,
+        unit: Option<usize>,
+        sampler: &'a D::ComparisonSampler2d,
+    );
+
+    // Global blocks look like regular blocks in shaders, so this is only
+    // called by the `Gl` view.
+    fn accept_global_block<B: Block<Sl, Sl = B>>(&mut self, _path: &str) {}
+
+    /// Returns whether the visitor uses the `path` arguments.
+    ///
+    /// Visitors that are used in draw calls return `false`, so that visiting
+    /// does not allocate paths.
+    fn needs_paths(&self) -> bool {
+        true
+    }
 }
 
 /// Non-empty uniform data.
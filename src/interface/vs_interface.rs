@@ -1,6 +1,10 @@
 use sealed::sealed;
 
-use crate::{gl, internal::join_ident_path, Gl, Sl};
+use crate::{
+    gl,
+    internal::{join_ident_path, join_ident_path_if},
+    Gl, Sl,
+};
 
 use super::{Block, BlockDom};
 
@@ -10,6 +14,10 @@ use super::{Block, BlockDom};
 #[sealed]
 pub trait VsInterfaceDom: BlockDom {
     /// A vertex block field.
+    ///
+    /// The location of the block's first attribute can be specified with the
+    /// field attribute `#[vertex(location = N)]`. The remaining attributes
+    /// take up consecutive locations.
     type Block<B: Block<Sl>>: VertexField<Self>;
 }
 
@@ -107,7 +115,7 @@ unsafe impl<B: Block<Sl>> VsInterface<Gl> for gl::VertexBufferBinding<B> {
     type Sl = B::Sl;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl VertexVisitor<'a, Gl>) {
-        visitor.accept(path, self)
+        visitor.accept(path, None, self)
     }
 }
 
@@ -116,7 +124,7 @@ unsafe impl<B: Block<Sl>> VsInterface<Sl> for B {
     type Sl = B::Sl;
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl VertexVisitor<'a, Sl>) {
-        visitor.accept(path, self);
+        visitor.accept(path, None, self);
     }
 
     fn shader_input(path: &str) -> Self {
@@ -142,8 +150,14 @@ where
     type Sl = (U::Sl, V::Sl);
 
     fn visit<'a>(&'a self, path: &str, visitor: &mut impl VertexVisitor<'a, Gl>) {
-        self.0.visit(&join_ident_path(path, "a"), visitor);
-        self.1.visit(&join_ident_path(path, "b"), visitor);
+        self.0.visit(
+            &join_ident_path_if(visitor.needs_paths(), path, "a"),
+            visitor,
+        );
+        self.1.visit(
+            &join_ident_path_if(visitor.needs_paths(), path, "b"),
+            visitor,
+        );
     }
 }
 
@@ -190,5 +204,18 @@ impl<B: Block<Sl>> VertexField<Sl> for B {
 }
 
 pub trait VertexVisitor<'a, D: VsInterfaceDom> {
-    fn accept<B: Block<Sl>>(&mut self, path: &str, vertex: &'a D::Block<B>);
+    fn accept<B: Block<Sl>>(
+        &mut self,
+        path: &str,
+        location: Option<usize>,
+        vertex: &'a D::Block<B>,
+    );
+
+    /// Returns whether the visitor uses the `path` argument of `accept`.
+    ///
+    /// Visitors that are used in draw calls return `false`, so that visiting
+    /// does not allocate paths.
+    fn needs_paths(&self) -> bool {
+        true
+    }
 }
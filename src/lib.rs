@@ -26,7 +26,7 @@ pub use interface::{
     UniformNonUnit, UniformUnion, VsInterface, VsInterfaceDom,
 };
 
-pub use posh_derive::{Block, FsInterface, UniformInterface, VsInterface};
+pub use posh_derive::{vertex_formats, Block, FsInterface, UniformInterface, VsInterface};
 
 pub use bytemuck;
 pub use crevice;
@@ -53,7 +53,9 @@ pub trait ToSl: Copy {
 #[doc(hidden)]
 pub mod internal {
     pub use super::{
-        interface::{FragmentVisitor, UniformVisitor, VertexField, VertexVisitor},
+        interface::{
+            BlockField, FragmentVisitor, SamplerField, UniformVisitor, VertexField, VertexVisitor,
+        },
         sl::{
             dag::{Expr, StructType, Type},
             primitives::{field, simplify_struct_literal, value_arg},
@@ -67,6 +69,17 @@ pub mod internal {
         // symbol names.
         format!("{lhs}_{rhs}")
     }
+
+    /// Joins paths only if `needed` is true, so that visitors which ignore
+    /// paths do not cause any allocations.
+    #[doc(hidden)]
+    pub fn join_ident_path_if(needed: bool, lhs: &str, rhs: &str) -> String {
+        if needed {
+            join_ident_path(lhs, rhs)
+        } else {
+            String::new()
+        }
+    }
 }
 
 // Re-export `crate` as `posh` for `posh-derive`.
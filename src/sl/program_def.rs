@@ -3,6 +3,8 @@
 //! This is exposed only in order to make the internally generated source code
 //! more transparent. It is typically not necessary to use this module.
 
+use std::any::TypeId;
+
 use super::dag::{BuiltInType, SamplerType, Type};
 
 /// UniformInterface input definition.
@@ -19,6 +21,13 @@ pub struct UniformBlockDef {
 
     /// The location to which this uniform block is to be bound in the program.
     pub location: usize,
+
+    /// The size of the uniform block in bytes, following the `std140` layout.
+    pub size: usize,
+
+    /// The type of the block's logical view, which is used for matching global
+    /// uniform blocks.
+    pub type_id: TypeId,
 }
 
 /// Sampler input definition.
@@ -29,12 +38,49 @@ pub struct UniformSamplerDef {
     pub texture_unit: usize,
 }
 
+/// The format in which a vertex attribute is stored in vertex buffers.
+///
+/// All formats other than [`VertexAttributeFormat::Native`] are converted to
+/// floating-point values and can only be used for attributes of type `float`,
+/// `vec2`, `vec3` or `vec4`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum VertexAttributeFormat {
+    /// 32-bit components that match the attribute's type.
+    #[default]
+    Native,
+
+    /// Unsigned 8-bit integers, normalized to `[0, 1]`.
+    U8Norm,
+
+    /// Signed 8-bit integers, normalized to `[-1, 1]`.
+    I8Norm,
+
+    /// Unsigned 16-bit integers, normalized to `[0, 1]`.
+    U16Norm,
+
+    /// Signed 16-bit integers, normalized to `[-1, 1]`.
+    I16Norm,
+
+    /// 16-bit floating-point numbers.
+    F16,
+
+    /// Four signed components packed into 32 bits as `INT_2_10_10_10_REV`,
+    /// normalized to `[-1, 1]`. Requires a `vec4` attribute.
+    Int2_10_10_10RevNorm,
+
+    /// Four unsigned components packed into 32 bits as
+    /// `UNSIGNED_INT_2_10_10_10_REV`, normalized to `[0, 1]`. Requires a `vec4`
+    /// attribute.
+    UInt2_10_10_10RevNorm,
+}
+
 /// VsInterface attribute definition.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VertexAttributeDef {
     pub name: String,
     pub ty: BuiltInType,
     pub offset: usize,
+    pub format: VertexAttributeFormat,
 }
 
 /// The rate at which a particular vertex input advances.
@@ -65,6 +111,28 @@ impl InterpolationQualifier {
 #[derive(Debug, Clone)]
 pub struct VertexBlockDef {
     pub attributes: Vec<VertexAttributeDef>,
+
+    /// The user-specified location of the block's first attribute.
+    ///
+    /// If this is `None`, the block is assigned the lowest free locations.
+    pub location: Option<usize>,
+}
+
+/// Varying definition, i.e. an output of the vertex shader that is
+/// interpolated as an input of the fragment shader.
+#[derive(Debug, Clone)]
+pub struct VaryingDef {
+    pub name: String,
+    pub ty: Type,
+    pub interpolation: InterpolationQualifier,
+}
+
+/// FsInterface output definition.
+#[derive(Debug, Clone)]
+pub struct FragmentOutputDef {
+    pub name: String,
+    pub ty: Type,
+    pub location: usize,
 }
 
 /// Type-erased definition of a program.
@@ -83,6 +151,12 @@ pub struct ProgramDef {
     /// VsInterface blocks that the program needs.
     pub vertex_block_defs: Vec<VertexBlockDef>,
 
+    /// Varyings that are passed from the vertex shader to the fragment shader.
+    pub varying_defs: Vec<VaryingDef>,
+
+    /// FsInterface outputs that the program writes to.
+    pub fragment_output_defs: Vec<FragmentOutputDef>,
+
     /// VsInterface shader source code.
     pub vertex_shader_source: String,
 
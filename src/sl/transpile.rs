@@ -3,7 +3,9 @@
 //! This is exposed only in order to make the internally generated source code
 //! more transparent. It is typically not necessary to use this module.
 
-use std::{iter::once, rc::Rc};
+use std::{any::TypeId, collections::BTreeSet, iter::once, mem::size_of, rc::Rc};
+
+use crevice::std140::AsStd140;
 
 use crate::{
     interface::{FragmentVisitor, UniformUnion, UniformVisitor, VertexVisitor},
@@ -14,12 +16,29 @@ use super::{
     codegen,
     dag::{Expr, SamplerType, Type},
     primitives::value_arg,
-    program_def::{ProgramDef, UniformBlockDef, UniformSamplerDef, VertexBlockDef},
+    program_def::{
+        FragmentOutputDef, ProgramDef, UniformBlockDef, UniformSamplerDef, VaryingDef,
+        VertexBlockDef,
+    },
     sig::{FromFsInput, FromVsInput, VsFunc, VsSig},
     ColorSample, ColorSampler2d, ComparisonSampler2d, Derivatives, FsFunc, FsInput, FsSig,
     Interpolant, IntoFullFsOutput, IntoFullVsOutput, Object, VsInput, I32,
 };
 
+/// The path under which uniforms are named in shaders.
+const UNIFORMS_PATH: &str = "uniforms";
+
+/// Returns the path of a uniform within the program's uniform interface, given
+/// its name in shaders.
+///
+/// For example, the sampler field `scene` is named `uniforms_scene`. Names that
+/// were not generated by transpilation are returned unchanged.
+pub(crate) fn uniform_path(name: &str) -> &str {
+    name.strip_prefix(UNIFORMS_PATH)
+        .and_then(|path| path.strip_prefix('_'))
+        .unwrap_or(name)
+}
+
 /// Transpiles a vertex shader and a fragment shader to GLSL source code.
 ///
 /// This is used internally by `posh` in order to create
@@ -60,14 +79,14 @@ where
     FFunc: FsFunc<FSig>,
 {
     // TODO: Remove hardcoded path names.
-    let uniforms = U::shader_input("uniforms");
+    let uniforms = U::shader_input(UNIFORMS_PATH);
 
     let (uniform_block_defs, uniform_sampler_defs) = {
         // TODO: Remove hardcoded path names.
         let mut visitor = CollectUniforms::default();
-        uniforms.visit("uniforms", &mut visitor);
+        uniforms.visit(UNIFORMS_PATH, &mut visitor);
 
-        (visitor.block_defs, visitor.sampler_defs)
+        visitor.into_defs()
     };
 
     let (vertex_block_defs, varying_outputs, vertex_shader_source) = {
@@ -134,9 +153,18 @@ where
     };
 
     // TODO: Remove hardcoded path names.
-    let uniforms = U::shader_input("uniforms");
-
-    let fragment_shader_source = {
+    let uniforms = U::shader_input(UNIFORMS_PATH);
+
+    let varying_defs = varying_outputs
+        .iter()
+        .map(|(name, interpolation, expr)| VaryingDef {
+            name: name.clone(),
+            ty: expr.ty(),
+            interpolation: *interpolation,
+        })
+        .collect();
+
+    let (fragment_output_defs, fragment_shader_source) = {
         let input = FsInput {
             interpolant: <VSig as VsSig>::W::shader_input("vertex_output"),
             fragment_coord: value_arg("gl_FragCoord"),
@@ -187,13 +215,26 @@ where
         )
         .unwrap();
 
-        source
+        let fragment_output_defs = visitor
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(location, (name, expr))| FragmentOutputDef {
+                name: name.clone(),
+                ty: expr.ty(),
+                location,
+            })
+            .collect();
+
+        (fragment_output_defs, source)
     };
 
     ProgramDef {
         uniform_block_defs,
         uniform_sampler_defs,
         vertex_block_defs,
+        varying_defs,
+        fragment_output_defs,
         vertex_shader_source,
         fragment_shader_source,
     }
@@ -201,43 +242,94 @@ where
 
 #[derive(Default)]
 struct CollectUniforms {
-    sampler_defs: Vec<UniformSamplerDef>,
-    block_defs: Vec<UniformBlockDef>,
+    sampler_defs: Vec<(UniformSamplerDef, Option<usize>)>,
+    block_defs: Vec<(UniformBlockDef, Option<usize>)>,
+}
+
+impl CollectUniforms {
+    /// Assigns block locations and texture units.
+    ///
+    /// User-specified values are kept as they are. Duplicates among them are
+    /// reported when validating the program. The remaining uniforms are
+    /// assigned the lowest values that are not in use.
+    fn into_defs(self) -> (Vec<UniformBlockDef>, Vec<UniformSamplerDef>) {
+        let locations = assign_slots(self.block_defs.iter().map(|(_, location)| *location));
+        let block_defs = self
+            .block_defs
+            .into_iter()
+            .zip(locations)
+            .map(|((block_def, _), location)| UniformBlockDef {
+                location,
+                ..block_def
+            })
+            .collect();
+
+        let units = assign_slots(self.sampler_defs.iter().map(|(_, unit)| *unit));
+        let sampler_defs = self
+            .sampler_defs
+            .into_iter()
+            .zip(units)
+            .map(|((sampler_def, _), texture_unit)| UniformSamplerDef {
+                texture_unit,
+                ..sampler_def
+            })
+            .collect();
+
+        (block_defs, sampler_defs)
+    }
+}
+
+fn assign_slots(requested: impl Iterator<Item = Option<usize>> + Clone) -> Vec<usize> {
+    let used: BTreeSet<usize> = requested.clone().flatten().collect();
+    let mut free = (0..).filter(|slot| !used.contains(slot));
+
+    requested
+        .map(|slot| slot.unwrap_or_else(|| free.next().unwrap()))
+        .collect()
 }
 
 impl<'a> UniformVisitor<'a, Sl> for CollectUniforms {
-    fn accept_block<U: Block<Sl>>(&mut self, path: &str, _: &U) {
-        // TODO: Allow user-specified uniform block locations.
+    fn accept_block<U: Block<Sl>>(&mut self, path: &str, location: Option<usize>, _: &U) {
         let block_def = UniformBlockDef {
             block_name: path.to_string() + "_posh_block",
             arg_name: path.to_string(),
             ty: <U::Sl as Object>::ty(),
-            location: self.block_defs.len(),
+            location: 0,
+            size: size_of::<<U::Gl as AsStd140>::Output>(),
+            type_id: TypeId::of::<U::Sl>(),
         };
 
-        self.block_defs.push(block_def)
+        self.block_defs.push((block_def, location))
     }
 
-    fn accept_color_sampler_2d<S: ColorSample>(&mut self, path: &str, _: &ColorSampler2d<S>) {
-        // TODO: Allow user-specified sampler texture units.
+    fn accept_color_sampler_2d<S: ColorSample>(
+        &mut self,
+        path: &str,
+        unit: Option<usize>,
+        _: &ColorSampler2d<S>,
+    ) {
         let sampler_def = UniformSamplerDef {
             name: path.to_string(),
             ty: S::SAMPLER_TYPE,
-            texture_unit: self.sampler_defs.len(),
+            texture_unit: 0,
         };
 
-        self.sampler_defs.push(sampler_def);
+        self.sampler_defs.push((sampler_def, unit));
     }
 
-    fn accept_comparison_sampler_2d(&mut self, path: &str, _: &ComparisonSampler2d) {
-        // TODO: Allow user-specified sampler texture units.
+    fn accept_comparison_sampler_2d(
+        &mut self,
+        path: &str,
+        unit: Option<usize>,
+        _: &ComparisonSampler2d,
+    ) {
         let sampler_def = UniformSamplerDef {
             name: path.to_string(),
             ty: SamplerType::ComparisonSampler2d,
-            texture_unit: self.sampler_defs.len(),
+            texture_unit: 0,
         };
 
-        self.sampler_defs.push(sampler_def);
+        self.sampler_defs.push((sampler_def, unit));
     }
 }
 
@@ -247,9 +339,10 @@ struct CollectVertexBlocks {
 }
 
 impl<'a> VertexVisitor<'a, Sl> for CollectVertexBlocks {
-    fn accept<B: Block<Sl>>(&mut self, path: &str, _: &B) {
+    fn accept<B: Block<Sl>>(&mut self, path: &str, location: Option<usize>, _: &B) {
         let block_def = VertexBlockDef {
             attributes: B::vertex_attribute_defs(path),
+            location,
         };
 
         self.block_defs.push(block_def);
@@ -266,3 +359,39 @@ impl<'a> FragmentVisitor<'a, Sl> for CollectOutputs {
         self.outputs.push((path.to_string(), output.expr()));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{assign_slots, uniform_path};
+
+    #[test]
+    fn uniform_paths_strip_the_root() {
+        assert_eq!(uniform_path("uniforms_scene"), "scene");
+        assert_eq!(uniform_path("uniforms_material_albedo"), "material_albedo");
+        assert_eq!(uniform_path("uniforms"), "uniforms");
+        assert_eq!(uniform_path("uniformsx"), "uniformsx");
+        assert_eq!(uniform_path("shadow_map"), "shadow_map");
+    }
+
+    #[test]
+    fn explicit_slots_are_kept() {
+        let requested = [Some(3), Some(0), Some(7)];
+
+        assert_eq!(assign_slots(requested.into_iter()), [3, 0, 7]);
+    }
+
+    #[test]
+    fn automatic_slots_fill_the_lowest_free_gaps() {
+        let requested = [None, Some(0), None, Some(2), None, None];
+
+        assert_eq!(assign_slots(requested.into_iter()), [1, 0, 3, 2, 4, 5]);
+    }
+
+    #[test]
+    fn duplicate_explicit_slots_are_kept() {
+        // Duplicates are reported when validating the program.
+        let requested = [Some(1), None, Some(1)];
+
+        assert_eq!(assign_slots(requested.into_iter()), [1, 0, 1]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Checks that steady-state draw calls do not allocate.
+//!
+//! This needs a display for creating an OpenGL ES 3.0 context, so it is
+//! ignored by default. Run it with `cargo test --test allocations -- --ignored`.
+//! CI runs it under Xvfb with Mesa's software renderer, see
+//! `.github/workflows/rust.yml`. The vertex array cache lookup is also checked
+//! without a display in `src/gl/raw/vertex_array.rs`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use posh::{gl, sl, Block, BlockDom, Gl, Sl, UniformInterface, UniformInterfaceDom};
+
+// Counting allocator
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+// Shader interface
+
+#[derive(Clone, Copy, Block)]
+#[repr(C)]
+struct State<D: BlockDom> {
+    time: D::F32,
+}
+
+#[derive(Clone, Copy, Block)]
+#[repr(C)]
+struct PresentVertex<D: BlockDom> {
+    pos: D::Vec2,
+    tex_coords: D::Vec2,
+}
+
+#[derive(UniformInterface)]
+struct PresentUniforms<D: UniformInterfaceDom> {
+    state: D::Block<State<Sl>>,
+    scene: D::ColorSampler2d<sl::Vec4>,
+}
+
+// Shaders
+
+fn scene_vertex_shader(vertex: sl::Vec2) -> sl::VsOutput<sl::Vec2> {
+    sl::VsOutput {
+        clip_position: vertex.extend(0.0).extend(1.0),
+        interpolant: vertex,
+    }
+}
+
+fn scene_fragment_shader(state: State<Sl>, interpolant: sl::Vec2) -> sl::Vec4 {
+    let rg = (interpolant + state.time).cos();
+
+    sl::vec4(rg.x, rg.y, 0.5, 1.0)
+}
+
+fn present_vertex_shader(vertex: PresentVertex<Sl>) -> sl::VsOutput<sl::Vec2> {
+    sl::VsOutput {
+        clip_position: vertex.pos.extend(0.0).extend(1.0),
+        interpolant: vertex.tex_coords,
+    }
+}
+
+fn present_fragment_shader(uniforms: PresentUniforms<Sl>, tex_coords: sl::Vec2) -> sl::Vec4 {
+    uniforms.scene.sample(tex_coords) * uniforms.state.time
+}
+
+// Test
+
+#[test]
+#[ignore = "needs a display for creating an OpenGL ES 3.0 context"]
+fn steady_state_draws_do_not_allocate() {
+    let sdl = sdl2::init().unwrap();
+    let video = sdl.video().unwrap();
+
+    let gl_attr = video.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::GLES);
+    gl_attr.set_context_version(3, 0);
+
+    let window = video
+        .window("posh allocation test", 64, 64)
+        .opengl()
+        .hidden()
+        .build()
+        .unwrap();
+
+    let _gl_context = window.gl_create_context().unwrap();
+    let gl = unsafe {
+        glow::Context::from_loader_function(|s| video.gl_get_proc_address(s) as *const _)
+    };
+    let gl = gl::Context::new(gl).unwrap();
+
+    use gl::BufferUsage::*;
+
+    let scene_program: gl::Program<State<Sl>, sl::Vec2> = gl
+        .create_program(scene_vertex_shader, scene_fragment_shader)
+        .unwrap();
+    let present_program: gl::Program<PresentUniforms<Sl>, PresentVertex<Sl>> = gl
+        .create_program(present_vertex_shader, present_fragment_shader)
+        .unwrap();
+
+    let state: gl::UniformBuffer<State<Gl>> = gl
+        .create_uniform_buffer(State { time: 1.0 }, StaticDraw)
+        .unwrap();
+    let texture = gl
+        .create_color_texture_2d(gl::ColorImage::zeroed::<[u8; 4]>([64, 64]))
+        .unwrap();
+    let triangle_vertices: gl::VertexBuffer<gl::Vec2> = gl
+        .create_vertex_buffer(
+            &[
+                [0.0f32, 1.0].into(),
+                [-0.5, -0.5].into(),
+                [0.5, -0.5].into(),
+            ],
+            StaticDraw,
+        )
+        .unwrap();
+    let quad_vertices: gl::VertexBuffer<PresentVertex<Gl>> = gl
+        .create_vertex_buffer(
+            &[
+                PresentVertex {
+                    pos: [-1.0, -1.0].into(),
+                    tex_coords: [0.0, 0.0].into(),
+                },
+                PresentVertex {
+                    pos: [1.0, -1.0].into(),
+                    tex_coords: [1.0, 0.0].into(),
+                },
+                PresentVertex {
+                    pos: [1.0, 1.0].into(),
+                    tex_coords: [1.0, 1.0].into(),
+                },
+                PresentVertex {
+                    pos: [-1.0, 1.0].into(),
+                    tex_coords: [0.0, 1.0].into(),
+                },
+            ],
+            StaticDraw,
+        )
+        .unwrap();
+    let quad_elements = gl
+        .create_element_buffer(&[0u32, 1, 2, 0, 2, 3], StaticDraw)
+        .unwrap();
+
+    let draw = || {
+        // The framebuffer is created anew for each draw call, like in the
+        // examples.
+        scene_program
+            .with_uniforms(state.as_binding())
+            .with_framebuffer(texture.as_color_attachment())
+            .draw(triangle_vertices.as_vertex_spec(gl::PrimitiveMode::Triangles))
+            .unwrap();
+
+        present_program
+            .with_uniforms(PresentUniforms {
+                state: state.as_binding(),
+                scene: texture.as_color_sampler(gl::Sampler2dSettings::linear()),
+            })
+            .draw(
+                quad_vertices
+                    .as_vertex_spec(gl::PrimitiveMode::Triangles)
+                    .with_element_data(quad_elements.as_binding()),
+            )
+            .unwrap();
+    };
+
+    // The first draw calls fill the caches of layouts, vertex arrays and
+    // scratch storage.
+    for _ in 0..3 {
+        draw();
+    }
+
+    assert_eq!(count_allocations(|| (0..100).for_each(|_| draw())), 0);
+}
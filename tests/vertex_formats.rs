@@ -0,0 +1,68 @@
+//! Checks the vertex attributes of blocks with compact vertex formats.
+
+use posh::{
+    gl,
+    sl::program_def::{VertexAttributeDef, VertexAttributeFormat},
+    Block, BlockDom, Gl, Sl,
+};
+
+#[posh::vertex_formats]
+#[derive(Clone, Copy, Block)]
+#[repr(C)]
+struct Vertex<D: BlockDom> {
+    pos: D::Vec3,
+    #[vertex(format = gl::Normalized<[u8; 4]>)]
+    color: D::Vec4,
+    #[vertex(format = gl::Normalized<[i16; 2]>)]
+    tex_coords: D::Vec2,
+    normal: D::Packed<gl::Normalized<gl::Int2_10_10_10Rev>>,
+}
+
+fn attribute(defs: &[VertexAttributeDef], name: &str) -> (usize, VertexAttributeFormat) {
+    let def = defs
+        .iter()
+        .find(|def| def.name == name)
+        .unwrap_or_else(|| panic!("missing attribute {name}"));
+
+    (def.offset, def.format)
+}
+
+#[test]
+fn packed_fields_use_their_vertex_format() {
+    let defs = <Vertex<Sl> as Block<Sl>>::vertex_attribute_defs("vertex");
+
+    assert_eq!(defs.len(), 4);
+    assert_eq!(
+        attribute(&defs, "vertex_pos"),
+        (0, VertexAttributeFormat::Native)
+    );
+    assert_eq!(
+        attribute(&defs, "vertex_color"),
+        (12, VertexAttributeFormat::U8Norm)
+    );
+    assert_eq!(
+        attribute(&defs, "vertex_tex_coords"),
+        (16, VertexAttributeFormat::I16Norm)
+    );
+    assert_eq!(
+        attribute(&defs, "vertex_normal"),
+        (20, VertexAttributeFormat::Int2_10_10_10RevNorm)
+    );
+}
+
+#[test]
+fn packed_fields_store_their_vertex_format() {
+    assert_eq!(std::mem::size_of::<Vertex<Gl>>(), 24);
+
+    let vertex = Vertex::<Gl> {
+        pos: [0.0, 1.0, 2.0].into(),
+        color: gl::Normalized([255, 0, 0, 255]),
+        tex_coords: gl::Normalized([i16::MAX, 0]),
+        normal: gl::Normalized(gl::Int2_10_10_10Rev(0)),
+    };
+
+    let bytes: &[u8] = bytemuck::bytes_of(&vertex);
+
+    assert_eq!(&bytes[12..16], [255, 0, 0, 255]);
+    assert_eq!(&bytes[16..18], i16::MAX.to_ne_bytes());
+}